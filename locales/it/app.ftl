@@ -5,6 +5,56 @@ app-currency = { NUMBER($amount, style: "currency", currency: "eur", currencyDis
 
 app-message-generic-InvalidInput = Operazione non valida
 app-message-generic-PrinterNotConfigured = Stampante non configurata
+app-message-generic-CashDrawerNotConfigured = Cassetto non configurato
+app-message-generic-PrinterNotReady = Stampante non pronta
+app-message-generic-NotLoggedIn = Accesso richiesto
+app-message-generic-Forbidden = Operazione non consentita
+
+## Startup
+
+startup-data-dir = Cartella dei dati: { $path }
+startup-latest-backup = Ultimo backup disponibile: { $date }
+startup-new-database-button = Inizia con un nuovo database
+startup-new-database-confirm = Il database attuale verrà rinominato e lasciato nella cartella dei dati, e l'applicazione partirà con un database vuoto. Continuare?
+startup-no-backup = Nessun backup disponibile
+startup-open-data-folder-button = Apri cartella dati
+startup-restore-latest-backup-button = Ripristina ultimo backup
+startup-restore-latest-backup-confirm = Il database attuale verrà rinominato e lasciato nella cartella dei dati, e sostituito dall'ultimo backup valido. Continuare?
+startup-retry-button = Riprova
+startup-text = Non è stato possibile aprire il database delle vendite. Il database non è stato modificato.
+startup-title = Errore all'avvio
+
+startup-messages-already-started = Il database è già aperto
+startup-messages-no-valid-backup = Nessun backup valido trovato nella cartella dei backup
+
+## Printer status
+
+printer-status-disconnected = Stampante scollegata
+printer-status-cover-open = Coperchio stampante aperto
+printer-status-paper-out = Carta esaurita
+printer-status-paper-near-end = Carta in esaurimento
+printer-status-error = Errore stampante
+printer-status-offline = Stampante offline
+
+## Tickets
+
+tickets-copy-number = copia { $copy }/{ $copies }
+
+## Operators
+
+operators-login-button = Entra
+operators-login-clear-button = Cancella
+operators-login-pin-label = PIN
+operators-login-text = Inserisci il tuo PIN per usare la cassa
+operators-login-title = Accesso operatore
+operators-logout-button = Esci
+
+operators-roles-Admin = Amministratore
+operators-roles-Cashier = Cassiere
+operators-roles-Supervisor = Responsabile
+
+operators-messages-wrong-pin = PIN errato
+operators-messages-too-many-attempts = Troppi PIN errati, riprova tra un minuto
 
 ## POS
 
@@ -17,46 +67,191 @@ pos-cart-total-price = Totale
 pos-cancel-payment-button = Annulla pagamento
 pos-clear-cart-button = Cancella ordine
 pos-menu-title = POS
+pos-open-cash-drawer-button = Apri cassetto
 
 pos-messages-empty-cart = Nessun prodotto
 pos-messages-loading-products = Caricamento prodotti...
 pos-messages-cannot-process-sale-with-no-items = Nessun prodotto nel carrello
 pos-messages-invalid-price-for-product = Prezzo non valido {$price} per prodotto {$product}
 pos-messages-invalid-quantity-for-product = Quantità non valida {$quantity} per prodotto {$product}
+pos-messages-price-changed-for-product = Il prezzo di {$product} è cambiato, ricomponi l'ordine
+pos-messages-product-not-available = Prodotto {$product} non più disponibile
+pos-messages-invalid-discount = Sconto non valido
+pos-messages-invalid-sale-code = Codice vendita non riconosciuto
+pos-messages-printer-not-ready-confirm = La stampante non è pronta e i ticket potrebbero non essere stampati. Registrare comunque la vendita?
+pos-messages-sale-not-printed = Vendita registrata, ma i ticket non sono stati stampati: ristampali dalle vendite di oggi
 
 pos-products-title = Prodotti
 
+pos-process-card-payment-button = Pagamento con carta
 pos-process-payment-button = Completa pagamento
 
 pos-recap-amount-due = Dovuto
 pos-recap-amount-payed = Pagato
 pos-recap-change = Resto
+pos-recap-discount = Sconto
 pos-recap-subtotal = Subtotale
 pos-recap-total = Totale
 
 pos-reprint-tickets-button = Ristampa ultima vendita
 pos-start-payment-button = Pagamento
 
+## Dashboard
+
+dashboard-items-sold = Articoli venduti
+dashboard-open-button = Apri dashboard
+dashboard-recent-items-sold = Articoli negli ultimi { $minutes } minuti
+dashboard-revenue = Incasso di oggi
+dashboard-sales-count = Vendite
+dashboard-title = Dashboard vendite
+dashboard-updated-at = Aggiornato alle { $time }
+
 ## Reports
 
 reports-clear-reports-button = Cancella report e dati sul venduto
+reports-comparison-add-period-button = Aggiungi periodo
+reports-comparison-compare-button = Confronta
+reports-comparison-day = Giorno { $period }
+reports-comparison-day-number = Giornata
+reports-comparison-days-title = Giornate a confronto
+reports-comparison-export-button = Esporta confronto
+reports-comparison-file-name = confronto
+reports-comparison-from = Dal
+reports-comparison-label-placeholder = Nome del periodo
+reports-comparison-menu-title = Confronto
+reports-comparison-period = Periodo
+reports-comparison-periods-title = Periodi
+reports-comparison-products-title = Prodotti a confronto
+reports-comparison-quantity = Q.tà { $period }
+reports-comparison-quantity-delta = Variazione q.tà { $period }
+reports-comparison-remove-period-button = Rimuovi
+reports-comparison-sales-count = Vendite { $period }
+reports-comparison-text = Confronta edizioni, turni o intervalli di date. I prodotti sono abbinati per nome e le giornate sono allineate in ordine, la prima con la prima.
+reports-comparison-title = Confronto tra periodi
+reports-comparison-to = Al
+reports-comparison-trend = Andamento
+reports-comparison-trend-discontinued = Non più venduto
+reports-comparison-trend-new = Nuovo
+reports-comparison-value = Totale { $period }
+reports-comparison-value-delta = Variazione totale { $period }
+reports-comparison-xlsx-days-tab-title = Giornate
+reports-comparison-xlsx-periods-tab-title = Periodi
+reports-comparison-xlsx-products-tab-title = Prodotti
+reports-export-csv-button = Esporta CSV
+reports-export-json-button = Esporta JSON
+reports-export-pdf-button = Report PDF
 reports-export-xlsx-button = Esporta report
 reports-menu-title = Report
 reports-title = Report di vendita
 
+reports-export-amount = Importo
+reports-export-category = Categoria
+reports-export-discount = Sconto
+reports-export-hour = Ora
+reports-export-items-sold = Articoli venduti
+reports-export-order-number = Numero vendita
+reports-export-payment-method = Metodo di pagamento
 reports-export-product-name = Prodotto
 reports-export-quantity = Q.tà
 reports-export-sale-id = # Scontrino
+reports-export-sale-time = Data e ora
+reports-export-sales-count = Vendite
+reports-export-total = Totale
+reports-export-voided-at = Annullata il
 
-reports-export-xlsx-currency-format = "#,##0.00 €"
-reports-export-xlsx-date-time-format = "%Y-%M-%d %H:%m:%S"
+reports-export-xlsx-breakdown-tab-title = Andamento orario
+reports-export-xlsx-currency-format = #,##0.00 "€"
+reports-export-xlsx-date-format = dd/mm/yyyy
+reports-export-xlsx-date-time-format = dd/mm/yyyy hh:mm:ss
 reports-export-xlsx-unit-cost = Costo unitario
-reports-export-xslx-invoices-tab-title = "Scontrini"
-reports-export-xslx-invoices-details-tab-title = "Dettaglio"
+reports-export-xlsx-invoices-tab-title = Scontrini
+reports-export-xlsx-invoices-details-tab-title = Dettaglio
+
+reports-breakdown-interval-day = Per giorno
+reports-breakdown-interval-hour = Per ora
+reports-breakdown-interval-hour-of-day = Per fascia oraria
+reports-breakdown-title = Andamento delle vendite
+
+reports-filter-all = Tutti
+reports-filter-apply-button = Applica filtri
+reports-filter-category-label = Categoria
+reports-filter-event-label = Evento
+reports-filter-from-day-label = Dal giorno
+reports-filter-operator-label = Operatore
+reports-filter-payment-method-label = Metodo di pagamento
+reports-filter-pos-name-label = Cassa
+reports-filter-reset-button = Azzera filtri
+reports-filter-shift-label = Turno
+reports-filter-to-day-label = Al giorno
+reports-filtered-sales-title = Vendite filtrate
 
+reports-open-export-folder-button = Apri cartella
+
+reports-payment-method-card = Carta
+reports-payment-method-cash = Contanti
+
+reports-pdf-by-category-title = Vendite per categoria
+reports-pdf-by-day-title = Vendite per giornata
+reports-pdf-by-payment-method-title = Metodi di pagamento
+reports-pdf-day = Giornata
+reports-pdf-generated-at = Generato il { $date }
+reports-pdf-hourly-chart-title = Andamento orario
+reports-pdf-hourly-day-chart-title = Andamento orario del { $day }
+reports-pdf-period = Dal { $from } al { $to }
+reports-pdf-share = Quota
+reports-pdf-title = Report di fine evento
+reports-pdf-top-products-title = Prodotti più venduti
+reports-pdf-totals-title = Totali
+
+reports-messages-comparison-needs-two-periods = Seleziona almeno due periodi da confrontare
 reports-messages-loading-data = Caricamento dati...
+reports-messages-sale-already-voided = Vendita già annullata
 reports-messages-no-data-available = Nessun dato sulle vendite disponibile
-reports-messages-export-xlsx-completed = Report esportato
+reports-messages-export-completed = Report esportato in { $path }
+reports-messages-export-file-already-exists = Il file esiste già, scegli un altro nome
+
+reports-summary-average-ticket = Scontrino medio
+reports-summary-category = Categoria
+reports-summary-discounts-total = Sconti
+reports-summary-items-per-sale = Articoli per vendita
+reports-summary-items-sold = Articoli venduti
+reports-summary-net-total = Incasso
+reports-summary-print-button = Stampa riepilogo
+reports-summary-sales-count = Vendite
+reports-summary-title = Riepilogo
+reports-summary-voids = Vendite annullate
+
+reports-summary-print-average-ticket = Scontrino medio
+reports-summary-print-by-category-title = Per categoria
+reports-summary-print-by-payment-method-title = Per metodo di pagamento
+reports-summary-print-discounts-total = Sconti
+reports-summary-print-gross-total = Totale lordo
+reports-summary-print-items-per-sale = Articoli per vendita
+reports-summary-print-items-sold = Articoli venduti
+reports-summary-print-journal-head = Hash del registro vendite
+reports-summary-print-net-total = Totale incassato
+reports-summary-print-period = Dal { $from } al { $to }
+reports-summary-print-printed-at = Stampato il { $date }
+reports-summary-print-products-title = Prodotti
+reports-summary-print-sales-count = Vendite
+reports-summary-print-title = RIEPILOGO VENDITE
+reports-summary-print-voids = Annullate
+
+reports-performance-attach-rate = Abbinamento
+reports-performance-attach-rate-title = Abbinamenti tra categorie
+reports-performance-attached-category = Con almeno un articolo di
+reports-performance-base-category = Ordini con
+reports-performance-category-share = Quota categoria
+reports-performance-idle = Fermo da { $hours } ore
+reports-performance-idle-hours-label = Fermo da (ore)
+reports-performance-last-sold-at = Ultima vendita
+reports-performance-min-quantity-label = Soglia minima (pezzi)
+reports-performance-only-flagged-label = Solo prodotti segnalati
+reports-performance-orders = Ordini
+reports-performance-refresh-button = Aggiorna
+reports-performance-revenue-rank = Pos. per incasso
+reports-performance-slow = Sotto soglia
+reports-performance-title = Andamento prodotti
 
 reports-sales-by-product-title = Prodotti venduti
 reports-sales-by-product-product-name = Prodotto
@@ -69,11 +264,107 @@ reports-today-sales-amount-sold = Importo venduto
 reports-today-sales-payment-method = Metodo di pagamento
 reports-today-sales-reprint-tickets-button = Ristampa vendita
 reports-today-sales-sales-time = Orario vendita
+reports-today-sales-void-button = Annulla vendita
+reports-today-sales-void-reason-prompt = Motivo dell'annullamento della vendita { $number }
 
 ## Settings
 
 settings-menu-title = Impostazioni
 
+settings-audit-action-all = Tutte
+settings-audit-action-label = Modifica
+settings-audit-actions-cash-drawer-opened = Cassetto aperto
+settings-audit-actions-database-merged = Unione database
+settings-audit-actions-operator-created = Operatore creato
+settings-audit-actions-operator-updated = Operatore modificato
+settings-audit-actions-price-changed = Cambio prezzo
+settings-audit-actions-product-created = Prodotto creato
+settings-audit-actions-product-deleted = Prodotto eliminato
+settings-audit-actions-product-updated = Prodotto modificato
+settings-audit-actions-sale-voided = Vendita annullata
+settings-audit-actions-sales-cleared = Vendite cancellate
+settings-audit-actions-settings-changed = Impostazioni modificate
+settings-audit-actor = Utente
+settings-audit-changes = Valori modificati
+settings-audit-empty = Nessuna modifica registrata
+settings-audit-entity = Elemento
+settings-audit-filter-button = Filtra
+settings-audit-journal-head-hash = Hash attuale: { $hash }
+settings-audit-journal-issues-broken-chain = Record { $sequence } del registro modificato, o record precedenti eliminati
+settings-audit-journal-issues-sale-missing = Vendita { $id } del record { $sequence } eliminata
+settings-audit-journal-issues-sale-modified = Vendita n. { $number } modificata dopo la registrazione
+settings-audit-journal-issues-sale-not-recorded = Vendita n. { $number } aggiunta senza essere registrata
+settings-audit-journal-issues-void-modified = Annullamento della vendita n. { $number } non registrato o modificato
+settings-audit-journal-issues-journal-empty = Registro svuotato, ma nel database ci sono { $sales } vendite
+settings-audit-journal-known-hash-found = L'hash indicato è presente nel registro
+settings-audit-journal-known-hash-label = Hash stampato su un riepilogo precedente (facoltativo)
+settings-audit-journal-known-hash-not-found = L'hash indicato non è presente nel registro: alcuni record sono stati eliminati o il registro è stato ricreato
+settings-audit-journal-records = { $count } record nel registro
+settings-audit-journal-text = Ogni vendita, annullamento e cancellazione dello storico viene aggiunto al registro delle vendite con un hash collegato al record precedente. La verifica controlla che nessuna vendita sia stata modificata o eliminata. L'hash dell'ultimo record viene stampato sul riepilogo di fine giornata: indicalo qui per controllare che il registro non sia stato ricreato.
+settings-audit-journal-title = Registro delle vendite
+settings-audit-journal-valid = Registro integro: nessuna vendita modificata o eliminata
+settings-audit-journal-verify-button = Verifica registro
+settings-audit-menu-title = Registro modifiche
+settings-audit-messages-exported = Registro esportato in { $path }
+settings-audit-messages-unsupported-format = Il registro delle modifiche può essere esportato solo in CSV o JSON
+settings-audit-pos-name = Cassa
+settings-audit-recorded-at = Data
+settings-audit-text = Ogni modifica del catalogo, delle impostazioni e delle vendite viene registrata con l'utente, la cassa e i valori prima e dopo la modifica. Le voci del registro non possono essere modificate né eliminate.
+settings-audit-title = Registro delle modifiche
+
+settings-backup-directory-label = Cartella dei backup
+settings-backup-directory-placeholder = Cartella "backups" nei dati dell'applicazione
+settings-backup-interval-minutes-label = Backup automatico ogni (minuti, 0 per disattivarlo)
+settings-backup-keep-label = Backup da conservare per tipo
+settings-backup-list-created-at = Data
+settings-backup-list-empty = Nessun backup nella cartella
+settings-backup-list-reason = Tipo
+settings-backup-list-size = Dimensione
+settings-backup-menu-title = Backup
+settings-backup-messages-completed = Backup salvato in { $path }
+settings-backup-messages-invalid-file = Il file non è un backup valido di Fulcitt POS
+settings-backup-messages-newer-schema = Il backup è stato creato da una versione più recente dell'applicazione (schema { $version }) e non può essere ripristinato
+settings-backup-messages-saved = Impostazioni salvate
+settings-backup-now-button = Esegui backup ora
+settings-backup-on-close-label = Esegui un backup alla chiusura dell'applicazione
+settings-backup-reason-close = Chiusura
+settings-backup-reason-manual = Manuale
+settings-backup-reason-merge = Prima dell'unione
+settings-backup-reason-migration = Prima dell'aggiornamento
+settings-backup-reason-restore = Prima del ripristino
+settings-backup-reason-scheduled = Automatico
+settings-backup-restore-button = Ripristina
+settings-backup-restore-confirm = Il database attuale verrà sostituito dal backup e l'applicazione verrà riavviata. Prima della sostituzione viene salvato un backup del database attuale. Continuare?
+settings-backup-restore-path-placeholder = Percorso di un file di backup, ad esempio su una chiavetta USB
+settings-backup-restore-text = Ripristina uno dei backup della cartella o un file di backup indicato. Vengono accettati solo backup creati da questa versione o da versioni precedenti.
+settings-backup-restore-title = Ripristino
+settings-backup-save-button = Salva
+settings-backup-text = Il database viene copiato periodicamente nella cartella indicata, che può essere su una chiavetta USB, e prima di ogni aggiornamento. I backup più vecchi vengono eliminati.
+settings-backup-title = Backup del database
+
+settings-event-business-day-cutoff-label = Fine giornata lavorativa (ora)
+settings-event-logo-path-label = Logo del report PDF
+settings-event-logo-path-placeholder = Percorso di un'immagine PNG o JPEG
+settings-event-menu-title = Evento
+settings-event-messages-saved = Impostazioni salvate
+settings-event-name-label = Nome evento
+settings-event-save-button = Salva
+settings-event-shift-label = Turno
+settings-event-text = Le vendite vengono registrate con l'evento e il turno indicati. Le vendite effettuate prima dell'ora di fine giornata sono attribuite al giorno precedente.
+settings-event-title = Evento
+
+settings-export-csv-decimal-comma-label = Usa la virgola come separatore decimale nei CSV
+settings-export-directory-label = Cartella di destinazione
+settings-export-directory-placeholder = Cartella Documenti
+settings-export-file-name-template-label = Nome del file
+settings-export-menu-title = Esportazione
+settings-export-messages-saved = Impostazioni salvate
+settings-export-save-button = Salva
+settings-export-text = I report vengono salvati nella cartella indicata, senza sovrascrivere i file esistenti. Nel nome del file puoi usare {"{"}event{"}"}, {"{"}date{"}"} e {"{"}time{"}"}.
+settings-export-title = Esportazione report
+
+settings-layout-code-size-label = Dimensione codice
+settings-layout-copies-title = Copie dei ticket per categoria
 settings-layout-font-size-label = Dimensione caratteri
 settings-layout-font-size-large = Grande
 settings-layout-font-size-normal = Normale
@@ -84,22 +375,81 @@ settings-layout-justify-left = Sinistra
 settings-layout-justify-right = Destra
 settings-layout-menu-title = Layout
 settings-layout-header-content-label = Contenuto
+settings-layout-print-barcode-label = Stampa codice a barre
 settings-layout-print-body-label = Stampa corpo
+settings-layout-print-copy-number-label = Indica il numero di copia
 settings-layout-print-footer-label = Stampa piè di pagina
 settings-layout-print-group-tickets-by-category = Raggruppa ticket per categoria
 settings-layout-print-header-label = Stampa intestazione
+settings-layout-print-qr-code-label = Stampa codice QR
 settings-layout-save-button = Salva
 settings-layout-title = Layout
 Settings-layout-messages-layout-saved = Layout salvato
 
+settings-merge-button = Unisci
+settings-merge-confirm = Le vendite e i prodotti del database indicato verranno aggiunti a quelli di questa cassa. Prima dell'unione viene salvato un backup del database attuale. Continuare?
+settings-merge-menu-title = Unione casse
+settings-merge-messages-completed = Database unito
+settings-merge-messages-same-database = Il file indicato è il database di questa cassa
+settings-merge-path-placeholder = Percorso del file app.db o di un backup dell'altra cassa
+settings-merge-report-conflicts-deleted = eliminato
+settings-merge-report-conflicts-empty = Nessun conflitto
+settings-merge-report-conflicts-local = Questa cassa (mantenuto)
+settings-merge-report-conflicts-other = Altra cassa
+settings-merge-report-conflicts-product = Prodotto { $name }
+settings-merge-report-conflicts-record = Dato
+settings-merge-report-conflicts-sale = Vendita n. { $number }
+settings-merge-report-conflicts-text = I dati seguenti sono diversi nei due database: è stato mantenuto quello di questa cassa.
+settings-merge-report-items-added = { $count } articoli
+settings-merge-report-products-added = Prodotti aggiunti
+settings-merge-report-products-matched-by-name = { $count } abbinati per nome
+settings-merge-report-sales-added = Vendite aggiunte
+settings-merge-report-sales-already-present = Vendite già presenti
+settings-merge-report-title = Esito dell'unione
+settings-merge-report-voids-applied = { $count } storni applicati
+settings-merge-text = Dopo l'evento, unisci nel database di questa cassa le vendite e i prodotti di un'altra cassa, indicando il suo file app.db o uno dei suoi backup. Le vendite già presenti non vengono duplicate e i prodotti con lo stesso nome vengono considerati lo stesso prodotto, così i report mostrano i totali di tutte le casse.
+settings-merge-title = Unione dei database delle casse
+
+settings-operators-active = Attivo
+settings-operators-add-button = Aggiungi operatore
+settings-operators-empty = Nessun operatore: la cassa è utilizzabile senza accesso
+settings-operators-menu-title = Operatori
+settings-operators-name-label = Nome
+settings-operators-new-pin-label = Nuovo PIN (facoltativo)
+settings-operators-pin-label = PIN
+settings-operators-role-label = Ruolo
+settings-operators-save-button = Salva
+settings-operators-text = Con almeno un operatore attivo la cassa chiede il PIN prima dell'uso. I cassieri vendono e ristampano i biglietti, i responsabili annullano le vendite, aprono il cassetto e vedono i report, gli amministratori modificano il catalogo, le impostazioni e le stampanti e cancellano lo storico. Il primo operatore deve essere un amministratore.
+settings-operators-title = Operatori
+
+settings-operators-messages-created = Operatore { $name } aggiunto
+settings-operators-messages-first-must-be-admin = Il primo operatore deve essere un amministratore
+settings-operators-messages-invalid-pin = Il PIN deve essere di 4-8 cifre
+settings-operators-messages-last-admin = Deve restare almeno un amministratore attivo
+settings-operators-messages-name-in-use = Esiste già un operatore con questo nome
+settings-operators-messages-name-required = Il nome è obbligatorio
+settings-operators-messages-pin-in-use = PIN già usato da un altro operatore
+settings-operators-messages-updated = Operatore { $name } salvato
+
+settings-printer-buzzer-duration-label = Durata segnale (ms)
+settings-printer-buzzer-label = Segnale acustico a ogni ticket
+settings-printer-buzzer-times-label = Numero di segnali
+settings-printer-cash-drawer-label = Apri il cassetto per i pagamenti in contanti
+settings-printer-cash-drawer-pin-label = Pin del cassetto
+settings-printer-cash-drawer-pulse-label = Durata impulso (ms)
+settings-printer-characters-per-line-label = Caratteri per riga
+settings-printer-code-page-label = Codifica caratteri
 settings-printer-device-path-label = Stampante
 settings-printer-menu-title = Stampante
+settings-printer-paper-title = Carta e caratteri
+settings-printer-paper-width-label = Larghezza carta
 settings-printer-title = Stampante
 settings-printer-refresh-available-devices-button = Aggiorna elenco device
 settings-printer-printer-selection-title = Selezione stampante
 settings-printer-printer-selection-text = Assicurati che la stampante sia collegata e il percorso del device sia configurato correttamente.
 settings-printer-save-button = Salva
 settings-printer-test-print-button = Stampa di prova
+settings-printer-test-page-button = Stampa pagina di test
 settings-printer-text-to-be-printed-label = Testo da stampare
 settings-printer-test-example = Ciao da Tauri! Test @ { DATETIME($date, dateStyle: "short", timeStyle: "short") }
 settings-printer-messages-device-cannot-be-empty = Il percorso del device non può essere vuoto.
@@ -117,13 +467,60 @@ settings-products-menu-title = Prodotti
 settings-products-messages-incorrect-fields = Verifica i campi inseriti
 settings-products-messages-loading-existing-products = Caricamento prodotti esistenti...
 settings-products-messages-product-deleted = Prodotto eliminato
-settings-products-messages-product-deleted = Prodotto aggiornato
+settings-products-messages-product-updated = Prodotto aggiornato
 settings-products-messages-price-must-be-positive = Il prezzo deve essere zero o più
 settings-products-price = Prezzo
 settings-products-price-example = 4,00 €
 settings-products-product-name = Nome
 settings-products-product-name-example = Panino con Salamella
 settings-products-save-product-button = Salva
+settings-products-ticket-copies = Copie ticket
+settings-products-ticket-copies-example = Come categoria
 settings-products-title = Prodotti
 
+settings-sync-conflicts-deleted = eliminato
+settings-sync-conflicts-detected-at = Rilevato il
+settings-sync-conflicts-discarded = Modifica scartata
+settings-sync-conflicts-empty = Nessun conflitto
+settings-sync-conflicts-kept = Modifica mantenuta
+settings-sync-conflicts-text = Quando lo stesso prodotto viene modificato su due casse prima che si sincronizzino, resta la modifica più recente. Qui sono elencate le modifiche scartate.
+settings-sync-conflicts-title = Conflitti
+settings-sync-local-changes = Modifiche di questa cassa
+settings-sync-menu-title = Sincronizzazione
+settings-sync-messages-completed = Sincronizzazione completata
+settings-sync-messages-not-peer = La sincronizzazione è disponibile solo per le casse in modalità sincronizzata.
+settings-sync-now-button = Sincronizza ora
+settings-sync-peers-changes-received = Modifiche ricevute
+settings-sync-peers-empty = Nessuna sincronizzazione ancora effettuata
+settings-sync-peers-last-error = Ultimo errore
+settings-sync-peers-last-success = Ultima sincronizzazione
+settings-sync-peers-peer = Cassa
+settings-sync-peers-unreachable = non raggiungibile
+settings-sync-received-changes = Modifiche ricevute
+settings-sync-text = Le casse in modalità sincronizzata lavorano sul proprio database e si scambiano vendite, storni e modifiche ai prodotti ogni 30 secondi, quando sono raggiungibili.
+settings-sync-this-terminal = Questa cassa
+settings-sync-title = Sincronizzazione tra casse
+
+settings-terminal-api-token-label = Chiave di accesso
+settings-terminal-generate-token-button = Genera
+settings-terminal-menu-title = Casse
+settings-terminal-messages-connected = Connesso al server { $name } (versione { $version })
+settings-terminal-messages-pos-name-required = Dai un nome diverso a ogni cassa sincronizzata
+settings-terminal-messages-saved = Impostazioni salvate
+settings-terminal-mode-client = Client: vendite e prodotti sul server
+settings-terminal-mode-label = Ruolo della cassa
+settings-terminal-mode-peer = Sincronizzata: database proprio, sincronizzato con le altre casse
+settings-terminal-mode-server = Server: condivide il database con le altre casse
+settings-terminal-mode-standalone = Cassa singola
+settings-terminal-pos-name-label = Nome della cassa
+settings-terminal-pos-name-placeholder = Es. Cassa 1
+settings-terminal-restart-text = Le modifiche al ruolo, alla porta e al server si applicano al riavvio dell'applicazione.
+settings-terminal-save-button = Salva
+settings-terminal-server-port-label = Porta del server
+settings-terminal-server-url-label = Indirizzo del server
+settings-terminal-sync-peers-label = Indirizzi delle altre casse, uno per riga
+settings-terminal-test-connection-button = Prova connessione
+settings-terminal-text = Più casse possono condividere lo stesso database sulla rete locale: una cassa fa da server e le altre si collegano come client con la stessa chiave di accesso. In alternativa ogni cassa può lavorare sul proprio database e sincronizzarsi con le altre quando la rete è disponibile. Ogni vendita viene registrata con il nome della cassa che l'ha effettuata.
+settings-terminal-title = Casse in rete
+
 settings-title = Impostazioni
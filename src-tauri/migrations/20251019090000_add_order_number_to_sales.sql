@@ -0,0 +1,13 @@
+ALTER TABLE sales
+  ADD COLUMN order_number INTEGER;
+
+-- Sales recorded at the same time are numbered in insertion order
+UPDATE sales
+SET order_number = (
+  SELECT COUNT(*)
+  FROM sales AS previous_sales
+  WHERE previous_sales.sale_time < sales.sale_time
+    OR (previous_sales.sale_time = sales.sale_time AND previous_sales.rowid <= sales.rowid)
+);
+
+CREATE INDEX idx_sales_order_number ON sales (order_number);
@@ -0,0 +1,2 @@
+ALTER TABLE products
+  ADD COLUMN ticket_copies INTEGER CHECK(ticket_copies >= 0);
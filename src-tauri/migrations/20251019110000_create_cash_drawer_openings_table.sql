@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS cash_drawer_openings (
+  id TEXT NOT NULL PRIMARY KEY DEFAULT(uuid()),
+  opened_at DATETIME NOT NULL,
+  reason TEXT NOT NULL,
+  sale_id TEXT,
+  created_at TEXT NOT NULL DEFAULT (STRFTIME('%Y-%m-%d %H:%M:%S', 'NOW')),
+  FOREIGN KEY (sale_id) REFERENCES sales (id) ON DELETE SET NULL
+);
@@ -0,0 +1,9 @@
+ALTER TABLE sales
+  ADD COLUMN event_name TEXT;
+ALTER TABLE sales
+  ADD COLUMN shift_name TEXT;
+ALTER TABLE sales
+  ADD COLUMN operator_name TEXT;
+
+CREATE INDEX idx_sales_sale_time ON sales (sale_time);
+CREATE INDEX idx_sale_items_sale_id ON sale_items (sale_id);
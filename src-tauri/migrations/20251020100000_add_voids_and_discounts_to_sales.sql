@@ -0,0 +1,6 @@
+ALTER TABLE sales
+  ADD COLUMN discount_amount REAL NOT NULL DEFAULT 0 CHECK(discount_amount >= 0);
+ALTER TABLE sales
+  ADD COLUMN voided_at DATETIME;
+ALTER TABLE sales
+  ADD COLUMN void_reason TEXT;
@@ -0,0 +1,49 @@
+CREATE TABLE IF NOT EXISTS sync_state (
+  key TEXT NOT NULL PRIMARY KEY,
+  value TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sync_changes (
+  id BLOB NOT NULL PRIMARY KEY,
+  origin TEXT NOT NULL,
+  sequence INTEGER NOT NULL,
+  entity TEXT NOT NULL,
+  entity_id BLOB NOT NULL,
+  changed_at DATETIME NOT NULL,
+  payload TEXT NOT NULL,
+  received_at DATETIME NOT NULL,
+  UNIQUE (origin, sequence)
+);
+
+CREATE INDEX idx_sync_changes_entity ON sync_changes (entity, entity_id);
+
+CREATE TABLE IF NOT EXISTS sync_product_versions (
+  product_id BLOB NOT NULL PRIMARY KEY,
+  change_id BLOB NOT NULL,
+  origin TEXT NOT NULL,
+  changed_at DATETIME NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sync_product_aliases (
+  remote_id BLOB NOT NULL PRIMARY KEY,
+  local_id BLOB NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sync_conflicts (
+  id BLOB NOT NULL PRIMARY KEY,
+  entity TEXT NOT NULL,
+  entity_id BLOB NOT NULL,
+  kept_change_id BLOB NOT NULL,
+  discarded_change_id BLOB NOT NULL,
+  detected_at DATETIME NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sync_peers (
+  url TEXT NOT NULL PRIMARY KEY,
+  origin TEXT,
+  pos_name TEXT,
+  last_attempt_at DATETIME,
+  last_success_at DATETIME,
+  last_error TEXT,
+  changes_received INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS audit_log (
+  id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  recorded_at DATETIME NOT NULL,
+  action TEXT NOT NULL,
+  entity_id TEXT,
+  actor TEXT,
+  pos_name TEXT,
+  before_value TEXT,
+  after_value TEXT
+);
+
+CREATE INDEX idx_audit_log_recorded_at ON audit_log (recorded_at);
+
+-- The log is append-only, its entries can't be changed or removed
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+  SELECT RAISE(ABORT, 'The audit log is append-only');
+END;
+
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+  SELECT RAISE(ABORT, 'The audit log is append-only');
+END;
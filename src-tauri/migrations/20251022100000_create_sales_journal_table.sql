@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS sales_journal (
+  sequence INTEGER NOT NULL PRIMARY KEY,
+  kind TEXT NOT NULL,
+  sale_id BLOB,
+  recorded_at DATETIME NOT NULL,
+  content_hash TEXT NOT NULL,
+  previous_hash TEXT NOT NULL,
+  hash TEXT NOT NULL
+);
+
+CREATE INDEX idx_sales_journal_sale_id ON sales_journal (sale_id);
+
+-- The journal is append-only, its records can't be changed or removed
+CREATE TRIGGER sales_journal_no_update BEFORE UPDATE ON sales_journal
+BEGIN
+  SELECT RAISE(ABORT, 'The sales journal is append-only');
+END;
+
+CREATE TRIGGER sales_journal_no_delete BEFORE DELETE ON sales_journal
+BEGIN
+  SELECT RAISE(ABORT, 'The sales journal is append-only');
+END;
@@ -0,0 +1,25 @@
+CREATE TABLE IF NOT EXISTS operators (
+  id BLOB NOT NULL PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE,
+  role TEXT NOT NULL,
+  pin_salt TEXT NOT NULL,
+  pin_hash TEXT NOT NULL,
+  is_active BOOLEAN NOT NULL DEFAULT true,
+  created_at TEXT NOT NULL DEFAULT (STRFTIME('%Y-%m-%d %H:%M:%S', 'NOW')),
+  updated_at TEXT NOT NULL DEFAULT (STRFTIME('%Y-%m-%d %H:%M:%S', 'NOW'))
+);
+
+CREATE TRIGGER update_operators_updated_at
+AFTER UPDATE ON operators
+FOR EACH ROW
+BEGIN
+    UPDATE operators
+    SET updated_at = STRFTIME('%Y-%m-%d %H:%M:%S', 'NOW')
+    WHERE id = NEW.id;
+END;
+
+ALTER TABLE sales
+  ADD COLUMN operator_id BLOB;
+
+ALTER TABLE audit_log
+  ADD COLUMN operator_id BLOB;
@@ -0,0 +1,8 @@
+ALTER TABLE cash_drawer_openings
+  ADD COLUMN operator_id BLOB;
+
+ALTER TABLE cash_drawer_openings
+  ADD COLUMN operator_name TEXT;
+
+ALTER TABLE cash_drawer_openings
+  ADD COLUMN pos_name TEXT;
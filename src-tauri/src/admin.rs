@@ -0,0 +1,478 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Local, NaiveDate};
+use clap::{Parser, Subcommand};
+use console::Term;
+use escpos::{driver::UsbDriver, printer::Printer, utils::Protocol};
+use log::{info, LevelFilter};
+use unic_langid::langid;
+
+use crate::{
+    audit::Actor,
+    backup::{self, BackupReason, BackupSettings},
+    begin_write, connect_db,
+    exports::{read_catalog, write_catalog, ExportFormat},
+    fetch_products,
+    headless::setup_logging,
+    host::{FileSettings, SettingsStore},
+    journal, load_backup_settings, load_event_settings, load_export_settings,
+    load_printer_settings, open_database,
+    operators::{self, Role, UnsavedOperator},
+    printing::print_test_page,
+    reports::ReportFilter,
+    upsert_product, usb_devices, write_sales_export, CommandError, CommandResult, Db, Intl,
+    UnsavedProduct, UsbDevice, MIGRATOR,
+};
+
+/// Environment variable with the PIN of `add-operator`, for scripts.
+const OPERATOR_PIN_VAR: &str = "FULCITT_POS_OPERATOR_PIN";
+
+/// Administration of the database of a cash desk, to set it up before an
+/// event and for the accounting after it.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+    /// Database of the cash desk
+    #[arg(long, env = "FULCITT_POS_DB", default_value = "app.db", global = true)]
+    db: PathBuf,
+
+    /// JSON file of the settings, with the same keys as the `store.json` of
+    /// the application, `settings.json` next to the database when not given
+    #[arg(long, env = "FULCITT_POS_SETTINGS", global = true)]
+    settings: Option<PathBuf>,
+
+    /// Name recorded in the audit log with the changes
+    #[arg(long, env = "FULCITT_POS_ACTOR", global = true)]
+    actor: Option<String>,
+
+    /// Logs the progress messages too
+    #[arg(long, global = true)]
+    verbose: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Exports the products of the catalog to a CSV file, or to a JSON file
+    /// for any other extension
+    ExportCatalog { output: PathBuf },
+
+    /// Adds the products of a CSV or JSON file written by `export-catalog`,
+    /// updating the ones with the same name, all of them or none
+    ImportCatalog { input: PathBuf },
+
+    /// Exports the sales of a range of business days
+    ExportSales {
+        /// First business day, e.g. 2025-10-17
+        #[arg(long)]
+        from: Option<NaiveDate>,
+
+        /// Last business day, included
+        #[arg(long)]
+        to: Option<NaiveDate>,
+
+        /// Only the sales of this event
+        #[arg(long)]
+        event: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = ExportFormat::Xlsx)]
+        format: ExportFormat,
+
+        /// File to write, or folder of the CSV files
+        output: PathBuf,
+    },
+
+    /// Backs up the database in the backups folder of the settings, or in
+    /// the given one
+    Backup {
+        #[arg(long)]
+        directory: Option<PathBuf>,
+    },
+
+    /// Replaces the database with a backup, after backing up the current one
+    Restore { backup: PathBuf },
+
+    /// Applies the pending migrations, creating the database when missing
+    Migrate,
+
+    /// Checks that the recorded sales weren't changed or removed, failing
+    /// when they were
+    VerifyJournal {
+        /// Hash printed on a previous sales recap, that must be in the journal
+        #[arg(long)]
+        known_hash: Option<String>,
+    },
+
+    /// Prints a test ticket on the printer of the settings, or on the given one
+    TestTicket {
+        /// Vendor id in hex, as listed by `list-usb-devices`
+        #[arg(long, requires = "product_id", value_parser = parse_usb_id)]
+        vendor_id: Option<u16>,
+
+        /// Product id in hex, as listed by `list-usb-devices`
+        #[arg(long, requires = "vendor_id", value_parser = parse_usb_id)]
+        product_id: Option<u16>,
+    },
+
+    /// Lists the USB devices, to find the ids of the printer
+    ListUsbDevices,
+
+    /// Adds an operator account, e.g. a new admin when the PINs of the others
+    /// are lost. The PIN is asked at a prompt, or read from the
+    /// `FULCITT_POS_OPERATOR_PIN` environment variable in scripts
+    AddOperator {
+        name: String,
+
+        #[arg(long, value_enum, default_value_t = Role::Admin)]
+        role: Role,
+    },
+}
+
+fn parse_usb_id(value: &str) -> Result<u16, String> {
+    u16::from_str_radix(value.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("{} is not a hex USB id", value))
+}
+
+/// Runs the command given on the command line.
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    setup_logging(if args.verbose {
+        LevelFilter::Info
+    } else {
+        LevelFilter::Warn
+    })?;
+
+    let settings_path = match &args.settings {
+        Some(path) => path.clone(),
+        None => args.db.with_file_name("settings.json"),
+    };
+    let settings = FileSettings::open(&settings_path)?;
+
+    match args.command {
+        Command::ListUsbDevices => {
+            for device in usb_devices()? {
+                println!(
+                    "{:04x}:{:04x}  {}  {}",
+                    device.vendor_id, device.product_id, device.vendor_name, device.product_name
+                );
+            }
+        }
+        Command::TestTicket {
+            vendor_id,
+            product_id,
+        } => print_test_ticket(&settings, vendor_id.zip(product_id))?,
+        command => {
+            let intl = Intl::try_new(langid!("it"))?;
+            let actor = Actor {
+                name: args.actor,
+                ..Actor::local(&settings)?
+            };
+
+            tokio::runtime::Runtime::new()?.block_on(run_database_command(
+                &args.db, &settings, &intl, &actor, command,
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_database_command(
+    db_path: &Path,
+    settings: &FileSettings,
+    intl: &Intl,
+    actor: &Actor,
+    command: Command,
+) -> CommandResult<()> {
+    let backup_settings = load_backup_settings(settings)?;
+    let backup_directory = backup_directory(db_path, &backup_settings);
+
+    match command {
+        Command::Migrate => {
+            let db = open_database(db_path, &backup_settings, &backup_directory).await?;
+            db.close().await;
+
+            println!("Database {:?} up to date", db_path);
+        }
+        Command::Restore { backup } => {
+            let schema_version = backup::validate_backup(&backup, &MIGRATOR, intl).await?;
+            info!(
+                "Restoring backup {:?} with schema version {}",
+                backup, schema_version
+            );
+
+            if db_path.exists() {
+                let db = connect_db(db_path).await?;
+                let saved = create_backup(
+                    &db,
+                    &backup_directory,
+                    &backup_settings,
+                    BackupReason::Restore,
+                )
+                .await;
+                db.close().await;
+                println!("Previous database backed up to {:?}", saved?);
+            }
+
+            backup::stage_restore(&backup, db_path)?;
+            let db = open_database(db_path, &backup_settings, &backup_directory).await?;
+            db.close().await;
+
+            println!("Database {:?} restored from {:?}", db_path, backup);
+        }
+        Command::Backup { directory } => {
+            let db = open_existing_database(db_path).await?;
+            let directory = directory.unwrap_or(backup_directory);
+            let saved =
+                create_backup(&db, &directory, &backup_settings, BackupReason::Manual).await;
+            db.close().await;
+
+            println!("Database backed up to {:?}", saved?);
+        }
+        Command::ExportCatalog { output } => {
+            ensure_new_file(&output, intl)?;
+
+            let db = open_migrated_database(db_path, &backup_settings, &backup_directory).await?;
+            let products: Vec<UnsavedProduct> = fetch_products(&db)
+                .await?
+                .into_iter()
+                .map(|product| UnsavedProduct {
+                    name: product.name,
+                    category: product.category,
+                    price: product.price,
+                    ticket_copies: product.ticket_copies,
+                })
+                .collect();
+            db.close().await;
+
+            let export_settings = load_export_settings(settings)?;
+            write_catalog(&products, &output, export_settings.csv_decimal_comma)?;
+
+            println!("{} products exported to {:?}", products.len(), output);
+        }
+        Command::ImportCatalog { input } => {
+            let products = read_catalog(&input)?;
+
+            let db = open_migrated_database(db_path, &backup_settings, &backup_directory).await?;
+            let imported = import_products(&db, actor, &products).await;
+            db.close().await;
+            imported?;
+
+            println!("{} products imported from {:?}", products.len(), input);
+        }
+        Command::ExportSales {
+            from,
+            to,
+            event,
+            format,
+            output,
+        } => {
+            ensure_new_file(&output, intl)?;
+
+            let filter = ReportFilter {
+                from_day: from,
+                to_day: to,
+                event,
+                ..Default::default()
+            };
+            let event_settings = load_event_settings(settings)?;
+            let export_settings = load_export_settings(settings)?;
+
+            let db = open_migrated_database(db_path, &backup_settings, &backup_directory).await?;
+            let exported = write_sales_export(
+                &db,
+                intl,
+                &event_settings,
+                &export_settings,
+                format,
+                &filter,
+                &output,
+            )
+            .await;
+            db.close().await;
+            exported?;
+
+            println!("Sales exported to {:?}", output);
+        }
+        Command::VerifyJournal { known_hash } => {
+            let db = open_migrated_database(db_path, &backup_settings, &backup_directory).await?;
+            let verification = journal::verify(&db, known_hash.as_deref()).await;
+            db.close().await;
+            let verification = verification?;
+
+            println!("{} records", verification.records);
+            if let Some(head_hash) = &verification.head_hash {
+                println!("Head hash {}", head_hash);
+            }
+            for issue in &verification.issues {
+                println!("{:?}", issue);
+            }
+
+            if verification.known_hash_found == Some(false) {
+                return Err(CommandError::InvalidInput(
+                    "The known hash is not in the journal".to_string(),
+                ));
+            }
+            if !verification.issues.is_empty() {
+                return Err(CommandError::InvalidInput(format!(
+                    "{} issues found in the journal",
+                    verification.issues.len()
+                )));
+            }
+
+            println!("Journal verified");
+        }
+        Command::AddOperator { name, role } => {
+            let pin = read_operator_pin()?;
+
+            let db = open_migrated_database(db_path, &backup_settings, &backup_directory).await?;
+            let operator = UnsavedOperator { name, role, pin };
+            let created = operators::create(&db, intl, actor, &operator).await;
+            db.close().await;
+            let created = created?;
+
+            println!("Operator {} added as {:?}", created.name, created.role);
+        }
+        Command::TestTicket { .. } | Command::ListUsbDevices => {}
+    }
+
+    Ok(())
+}
+
+/// Imports the products in one transaction, so that an invalid one leaves the
+/// catalog as it was.
+async fn import_products(db: &Db, actor: &Actor, products: &[UnsavedProduct]) -> CommandResult<()> {
+    let mut tx = begin_write(db).await?;
+    for product in products {
+        upsert_product(&mut tx, actor, product).await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// PIN of a new operator, from the environment or typed twice without echo,
+/// never from the arguments that end up in the shell history and in the
+/// process list.
+fn read_operator_pin() -> CommandResult<String> {
+    if let Ok(pin) = std::env::var(OPERATOR_PIN_VAR) {
+        return Ok(pin);
+    }
+
+    let term = Term::stderr();
+    term.write_str("PIN: ")?;
+    let pin = term.read_secure_line()?;
+    term.write_str("Repeat the PIN: ")?;
+    if term.read_secure_line()? != pin {
+        return Err(CommandError::InvalidInput(
+            "The PINs don't match".to_string(),
+        ));
+    }
+
+    Ok(pin)
+}
+
+/// Backups go next to the database when the settings have no folder, as the
+/// application does in its data folder.
+fn backup_directory(db_path: &Path, settings: &BackupSettings) -> PathBuf {
+    match &settings.directory {
+        Some(directory) => directory.clone(),
+        None => db_path.with_file_name("backups"),
+    }
+}
+
+async fn create_backup(
+    db: &Db,
+    directory: &Path,
+    settings: &BackupSettings,
+    reason: BackupReason,
+) -> CommandResult<PathBuf> {
+    backup::create_backup(
+        db,
+        directory,
+        reason,
+        settings.keep,
+        Local::now().naive_local(),
+    )
+    .await
+}
+
+fn ensure_database_exists(db_path: &Path) -> CommandResult<()> {
+    if !db_path.exists() {
+        return Err(CommandError::InvalidInput(format!(
+            "Database {:?} not found",
+            db_path
+        )));
+    }
+
+    Ok(())
+}
+
+/// Opens the database as it is, e.g. to back it up before a migration.
+async fn open_existing_database(db_path: &Path) -> CommandResult<Db> {
+    ensure_database_exists(db_path)?;
+
+    connect_db(db_path).await
+}
+
+/// Opens the database after bringing its schema up to date, as the
+/// application does at startup.
+async fn open_migrated_database(
+    db_path: &Path,
+    backup_settings: &BackupSettings,
+    backup_directory: &Path,
+) -> CommandResult<Db> {
+    ensure_database_exists(db_path)?;
+
+    open_database(db_path, backup_settings, backup_directory).await
+}
+
+/// Exports never overwrite an existing file, as in the application.
+fn ensure_new_file(path: &Path, intl: &Intl) -> CommandResult<()> {
+    if path.exists() {
+        return Err(CommandError::InvalidInput(
+            intl.t("reports-messages-export-file-already-exists")?
+                .to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn print_test_ticket(settings: &FileSettings, ids: Option<(u16, u16)>) -> CommandResult<()> {
+    let (vendor_id, product_id) = match ids {
+        Some(ids) => ids,
+        None => {
+            let device = settings.get_setting("printer-device")?.ok_or_else(|| {
+                CommandError::InvalidInput(
+                    "No printer in the settings, give its --vendor-id and --product-id".to_string(),
+                )
+            })?;
+            let device = serde_json::from_value::<UsbDevice>(device)?;
+
+            (device.vendor_id, device.product_id)
+        }
+    };
+
+    info!(
+        "Printing a test ticket on {:04x}:{:04x}",
+        vendor_id, product_id
+    );
+
+    let driver = UsbDriver::open(vendor_id, product_id, None)?;
+    let mut printer = Printer::new(driver, Protocol::default(), None);
+
+    print_test_page(&mut printer, &load_printer_settings(settings)?)?;
+
+    println!(
+        "Test ticket printed on {:04x}:{:04x}",
+        vendor_id, product_id
+    );
+
+    Ok(())
+}
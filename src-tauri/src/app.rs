@@ -0,0 +1,1602 @@
+use std::{
+    future::Future,
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use chrono::Local;
+#[cfg(debug_assertions)]
+use escpos::driver::ConsoleDriver;
+use escpos::{
+    driver::UsbDriver,
+    printer::Printer,
+    utils::{DebugMode, Protocol},
+};
+use log::info;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, RunEvent, State};
+use tauri_plugin_opener::OpenerExt;
+use tauri_plugin_store::StoreExt;
+use unic_langid::langid;
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, Actor, AuditAction, AuditEntry, AuditFilter},
+    backup::{self, BackupFile, BackupReason, BackupSettings},
+    backup_app_database, backup_directory, begin_write, database_path,
+    exports::{
+        export_comparison_report, unique_export_path, write_audit_log, ExportFormat,
+        ExportSettings,
+    },
+    fetch_products, find_sale,
+    host::Host,
+    insert_product,
+    journal::{self, JournalVerification},
+    list_today_sales,
+    live::{self, LiveTotals, LiveTotalsChange},
+    load_backup_settings, load_event_settings, load_export_settings, load_last_sale,
+    load_print_layout, load_printer_settings, load_recorded_sale, load_terminal_settings,
+    mark_product_deleted,
+    merge::{self, MergeReport},
+    models::{
+        AggregatedSaleItem, CartItem, PaymentMethod, Product, RecordedSale, Sale, SaleRequest,
+        UnsavedProduct,
+    },
+    operators::{
+        self, LoginAttempts, Operator, OperatorUpdate, Role, Session, SessionStatus,
+        UnsavedOperator,
+    },
+    pdf_report, print_recorded_sale, print_summary,
+    printing::{kick_cash_drawer, print_test_page, print_tickets, PrinterSettings, PrintingLayout},
+    record_cash_drawer_opening, record_sale, record_void,
+    reports::{
+        self, BreakdownInterval, ComparisonPeriod, EventSettings, ProductPerformanceOptions,
+        ProductPerformanceReport, ReportFilter, ReportFilterOptions, SalesBreakdown,
+        SalesComparison, SalesSummary,
+    },
+    run_scheduled_backups, save_product, server, setup_db, setup_printer_state,
+    status::PrinterStatus,
+    summary_filter,
+    sync::{self, SyncStatus},
+    terminal::{self, RemoteClient, ServerStatus, TerminalMode, TerminalSettings},
+    usb_devices, watch_printer_status, write_sales_export,
+    CommandError, CommandResult, Db, Intl, PrinterMonitorState, PrinterState, UsbDevice,
+    MIGRATOR,
+};
+
+#[derive(Clone)]
+pub(crate) struct AppState {
+    pub db: Db,
+}
+
+/// Operator checks of the commands, against the operator logged in on this
+/// terminal.
+trait Authorize {
+    /// Client of the server with the session of the operator logged in on
+    /// this terminal, or `None` when this terminal works on its own database.
+    fn remote_client(&self) -> CommandResult<Option<RemoteClient>>;
+
+    /// Checks that the operator logged in on this terminal may run a command
+    /// of the role, and returns them as the actor of the changes. Client
+    /// terminals check the operator logged in on the server, which checks
+    /// them again for the requests it serves.
+    fn authorize(&self, role: Role) -> impl Future<Output = CommandResult<Actor>> + Send;
+}
+
+impl Authorize for AppHandle {
+    fn remote_client(&self) -> CommandResult<Option<RemoteClient>> {
+        let session_token = self.state::<Session>().server_token()?;
+
+        Ok(RemoteClient::from_settings(&load_terminal_settings(self)?)?
+            .map(|client| client.with_session(session_token)))
+    }
+
+    async fn authorize(&self, role: Role) -> CommandResult<Actor> {
+        let session = self.state::<Session>();
+        let logged_in = session.operator()?;
+
+        let operator = match self.remote_client()? {
+            Some(client) => match logged_in {
+                Some(operator) => {
+                    operator.ensure_role(role)?;
+                    Some(operator)
+                }
+                None if client.status().await?.operators_required => {
+                    return Err(CommandError::NotLoggedIn)
+                }
+                None => None,
+            },
+            None => {
+                let operator_id = logged_in.map(|operator| operator.id);
+                match operators::authorize(&self.db()?, operator_id, role).await {
+                    // Deactivated since they logged in
+                    Err(CommandError::NotLoggedIn) => {
+                        session.set_operator(None)?;
+                        return Err(CommandError::NotLoggedIn);
+                    }
+                    result => result?,
+                }
+            }
+        };
+
+        Ok(Actor::operator(
+            operator.as_ref(),
+            load_terminal_settings(self)?.pos_name,
+        ))
+    }
+}
+
+/// Operator logged in on this terminal, and whether one must log in before
+/// using it.
+#[tauri::command]
+async fn get_operator_session(app: AppHandle) -> CommandResult<SessionStatus> {
+    let operator = app.state::<Session>().operator()?;
+
+    let required = match RemoteClient::from_settings(&load_terminal_settings(&app)?)? {
+        // Nobody can log in while the server can't be reached
+        Some(client) => client
+            .status()
+            .await
+            .map(|status| status.operators_required)
+            .unwrap_or(false),
+        None => operators::required(&app.db()?).await?,
+    };
+
+    Ok(SessionStatus { operator, required })
+}
+
+#[tauri::command]
+async fn login_operator(
+    app: AppHandle,
+    intl_state: State<'_, Intl>,
+    pin: String,
+) -> CommandResult<Operator> {
+    let client = RemoteClient::from_settings(&load_terminal_settings(&app)?)?;
+    let (operator, server_token) = match client {
+        Some(client) => {
+            let login = client.login(&pin).await?;
+            (login.operator, Some(login.token))
+        }
+        None => {
+            // Wrong PINs typed on this terminal, counted as the server counts
+            // the ones of each client terminal
+            let login_attempts = app.state::<LoginAttempts>();
+            let address = IpAddr::from(Ipv4Addr::LOCALHOST);
+            login_attempts.check(&intl_state, address)?;
+
+            let login = operators::login(&app.db()?, &intl_state, &pin).await;
+            login_attempts.record(address, login.is_ok())?;
+            (login?, None)
+        }
+    };
+
+    app.state::<Session>().log_in(operator.clone(), server_token)?;
+
+    Ok(operator)
+}
+
+#[tauri::command]
+async fn logout_operator(app: AppHandle) -> CommandResult<()> {
+    let session = app.state::<Session>();
+    if let Some(operator) = session.operator()? {
+        info!("Operator {} logged out", operator.name);
+    }
+
+    if session.server_token()?.is_some() {
+        if let Some(client) = app.remote_client()? {
+            // The session expires anyway, the operator must log out even
+            // when the server can't be reached
+            if let Err(err) = client.logout().await {
+                log::warn!("Failed to end the session on the server {:?}", err);
+            }
+        }
+    }
+
+    session.set_operator(None)
+}
+
+#[tauri::command]
+async fn list_operators(app: AppHandle) -> CommandResult<Vec<Operator>> {
+    app.authorize(Role::Admin).await?;
+
+    match app.remote_client()? {
+        Some(client) => client.list_operators().await,
+        None => operators::list(&app.db()?).await,
+    }
+}
+
+#[tauri::command]
+async fn create_operator(
+    app: AppHandle,
+    intl_state: State<'_, Intl>,
+    operator: UnsavedOperator,
+) -> CommandResult<Operator> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    match app.remote_client()? {
+        Some(client) => client.create_operator(&operator).await,
+        None => operators::create(&app.db()?, &intl_state, &actor, &operator).await,
+    }
+}
+
+#[tauri::command]
+async fn update_operator(
+    app: AppHandle,
+    intl_state: State<'_, Intl>,
+    operator_id: Uuid,
+    update: OperatorUpdate,
+) -> CommandResult<Operator> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    let operator = match app.remote_client()? {
+        Some(client) => client.update_operator(operator_id, &update).await?,
+        None => operators::update(&app.db()?, &intl_state, &actor, operator_id, &update).await?,
+    };
+
+    // Changes to the logged in operator, e.g. a new name, apply right away
+    let session = app.state::<Session>();
+    if session.operator()?.is_some_and(|logged_in| logged_in.id == operator.id) {
+        session.set_operator(Some(operator.clone()).filter(|operator| operator.is_active))?;
+    }
+
+    Ok(operator)
+}
+
+#[tauri::command]
+async fn list_products(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> CommandResult<Vec<Product>> {
+    app.authorize(Role::Cashier).await?;
+
+    if let Some(client) = app.remote_client()? {
+        return client.list_products().await;
+    }
+
+    fetch_products(&app_state.db).await
+}
+
+#[tauri::command]
+async fn create_product(
+    app: AppHandle,
+    product: UnsavedProduct,
+    app_state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    if let Some(client) = app.remote_client()? {
+        return client.create_product(&product).await;
+    }
+
+    insert_product(&app_state.db, &actor, &product).await?;
+
+    live::notify_catalog_changed(&app, &app_state.db).await;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_product(
+    app: AppHandle,
+    product: Product,
+    app_state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    if let Some(client) = app.remote_client()? {
+        return client.update_product(&product).await;
+    }
+
+    save_product(&app_state.db, &actor, &product).await?;
+
+    live::notify_catalog_changed(&app, &app_state.db).await;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn delete_product(
+    app: AppHandle,
+    product: Product,
+    app_state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    if let Some(client) = app.remote_client()? {
+        return client.delete_product(product.id).await;
+    }
+
+    mark_product_deleted(&app_state.db, &actor, product.id).await?;
+
+    live::notify_catalog_changed(&app, &app_state.db).await;
+
+    Ok(())
+}
+
+/// Sale recorded at the cash desk, whose tickets may still have failed to
+/// print.
+#[derive(Serialize, Debug)]
+struct ProcessedSale {
+    sale_id: Uuid,
+    /// Why the tickets weren't printed, the sale being recorded anyway
+    print_error: Option<CommandError>,
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn process_sale(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    printer_state: State<'_, PrinterState>,
+    printer_monitor: State<'_, PrinterMonitorState>,
+    intl_state: State<'_, Intl>,
+    items: Vec<CartItem>,
+    payment_method: PaymentMethod,
+    discount: Option<f64>,
+    ignore_printer_status: Option<bool>,
+) -> CommandResult<ProcessedSale> {
+    let settings = load_printer_settings(&app)?;
+    let terminal_settings = load_terminal_settings(&app)?;
+
+    let request = SaleRequest {
+        items,
+        payment_method,
+        discount,
+        pos_name: terminal_settings.pos_name.clone(),
+        open_cash_drawer: payment_method == PaymentMethod::Cash && settings.cash_drawer.enabled,
+    };
+    let actor = app.authorize(request.required_role()).await?;
+
+    // Refuse the sale before committing it when the tickets can't be printed,
+    // unless the operator chose to go on anyway. Without a printer configured
+    // the status is unknown, and the sale is still recorded.
+    if !ignore_printer_status.unwrap_or(false) {
+        match printer_monitor.current_status() {
+            Ok(Some(status)) if !status.is_ready() => {
+                return Err(CommandError::PrinterNotReady(status));
+            }
+            Ok(_) | Err(CommandError::PrinterNotConfigured) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    // Clients record the sale on the server and print the tickets on their own printer
+    let recorded = match app.remote_client()? {
+        Some(client) => client.process_sale(&request).await?,
+        None => {
+            let event_settings = load_event_settings(&app)?;
+            let recorded =
+                record_sale(&app_state.db, &intl_state, &event_settings, &actor, &request).await?;
+
+            live::notify_sales_changed(&app, &app_state.db, LiveTotalsChange::Sale).await;
+
+            recorded
+        }
+    };
+
+    // The sale is committed, a failed print must not look like a failed sale
+    // or the cashier would record it again
+    let printed = print_processed_sale(&app, &printer_state, &intl_state, &request, &recorded);
+    let print_error = match printed {
+        Ok(()) => None,
+        Err(CommandError::PrinterNotConfigured) => {
+            info!("Sale {} recorded without printing, no printer configured", recorded.sale.id);
+            None
+        }
+        Err(err) => {
+            log::error!("Sale {} recorded but not printed {:?}", recorded.sale.id, err);
+            Some(err)
+        }
+    };
+
+    Ok(ProcessedSale {
+        sale_id: recorded.sale.id,
+        print_error,
+    })
+}
+
+/// Opens the cash drawer when asked and prints the tickets of a sale.
+fn print_processed_sale(
+    app: &AppHandle,
+    printer_state: &PrinterState,
+    intl: &Intl,
+    request: &SaleRequest,
+    recorded: &RecordedSale,
+) -> CommandResult<()> {
+    let layout = load_print_layout(app)?;
+    let settings = load_printer_settings(app)?;
+
+    let mut mutex_guard = printer_state.lock()?;
+    let printer = mutex_guard
+        .as_mut()
+        .ok_or(CommandError::PrinterNotConfigured)?;
+    printer.debug_mode(Some(DebugMode::Dec)).init()?;
+
+    if request.open_cash_drawer {
+        kick_cash_drawer(printer, &settings.cash_drawer)?;
+    }
+
+    print_tickets(printer, &layout, &settings, intl, &recorded.sale, &recorded.items)
+}
+
+#[tauri::command]
+async fn open_cash_drawer(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    printer_state: State<'_, PrinterState>,
+) -> CommandResult<()> {
+    let actor = app.authorize(Role::Supervisor).await?;
+
+    let settings = load_printer_settings(&app)?;
+    if !settings.cash_drawer.enabled {
+        return Err(CommandError::CashDrawerNotConfigured);
+    }
+
+    {
+        let mut mutex_guard = printer_state.lock()?;
+        let printer = mutex_guard
+            .as_mut()
+            .ok_or(CommandError::PrinterNotConfigured)?;
+
+        kick_cash_drawer(printer, &settings.cash_drawer)?;
+    }
+
+    record_manual_cash_drawer_opening(&app_state.db, &actor).await?;
+
+    info!("Cash drawer opened manually by {:?}", actor.name);
+
+    Ok(())
+}
+
+/// Opens the cash drawer outside of a sale, recording who did it in the
+/// openings and in the audit log.
+async fn record_manual_cash_drawer_opening(db: &Db, actor: &Actor) -> CommandResult<()> {
+    let mut tx = begin_write(db).await?;
+    let id = record_cash_drawer_opening(&mut tx, actor, "Manual", None).await?;
+    audit::record(
+        &mut tx,
+        actor,
+        AuditAction::CashDrawerOpened,
+        Some(&id.to_string()),
+        None,
+        Some(serde_json::json!({ "reason": "Manual" })),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_sales_recap(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    filter: Option<ReportFilter>,
+) -> CommandResult<Vec<AggregatedSaleItem>> {
+    app.authorize(Role::Supervisor).await?;
+
+    let settings = load_event_settings(&app)?;
+
+    reports::sales_recap(&app_state.db, &filter.unwrap_or_default(), &settings).await
+}
+
+#[tauri::command]
+async fn get_today_sales(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> CommandResult<Vec<Sale>> {
+    app.authorize(Role::Cashier).await?;
+
+    if let Some(client) = app.remote_client()? {
+        return client.today_sales().await;
+    }
+
+    let settings = load_event_settings(&app)?;
+
+    list_today_sales(&app_state.db, &settings).await
+}
+
+#[tauri::command]
+async fn list_sales(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    filter: ReportFilter,
+) -> CommandResult<Vec<Sale>> {
+    app.authorize(Role::Supervisor).await?;
+
+    let settings = load_event_settings(&app)?;
+
+    reports::list_sales(&app_state.db, &filter, &settings).await
+}
+
+#[tauri::command]
+async fn get_sales_breakdown(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    filter: Option<ReportFilter>,
+    interval: BreakdownInterval,
+) -> CommandResult<SalesBreakdown> {
+    app.authorize(Role::Supervisor).await?;
+
+    let settings = load_event_settings(&app)?;
+
+    reports::sales_breakdown(&app_state.db, &filter.unwrap_or_default(), &settings, interval).await
+}
+
+#[tauri::command]
+async fn get_sales_summary(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    filter: Option<ReportFilter>,
+) -> CommandResult<SalesSummary> {
+    app.authorize(Role::Supervisor).await?;
+
+    let settings = load_event_settings(&app)?;
+    let filter = summary_filter(filter, &settings);
+
+    reports::sales_summary(&app_state.db, &filter, &settings).await
+}
+
+#[tauri::command]
+async fn get_product_performance(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    filter: Option<ReportFilter>,
+    options: Option<ProductPerformanceOptions>,
+) -> CommandResult<ProductPerformanceReport> {
+    app.authorize(Role::Supervisor).await?;
+
+    let settings = load_event_settings(&app)?;
+
+    reports::product_performance(
+        &app_state.db,
+        &filter.unwrap_or_default(),
+        &settings,
+        &options.unwrap_or_default(),
+        Local::now().naive_local(),
+    )
+    .await
+}
+
+#[tauri::command]
+async fn print_sales_summary(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    printer_state: State<'_, PrinterState>,
+    intl_state: State<'_, Intl>,
+    filter: Option<ReportFilter>,
+) -> CommandResult<()> {
+    app.authorize(Role::Supervisor).await?;
+
+    print_summary(&app, &app_state.db, &printer_state, &intl_state, filter).await
+}
+
+/// Comparisons need at least two periods to be meaningful.
+fn validate_comparison_periods(periods: &[ComparisonPeriod], intl: &Intl) -> CommandResult<()> {
+    if periods.len() < 2 {
+        return Err(CommandError::InvalidInput(
+            intl.t("reports-messages-comparison-needs-two-periods")?
+                .to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_sales_comparison(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    intl_state: State<'_, Intl>,
+    periods: Vec<ComparisonPeriod>,
+) -> CommandResult<SalesComparison> {
+    app.authorize(Role::Supervisor).await?;
+
+    validate_comparison_periods(&periods, &intl_state)?;
+    let settings = load_event_settings(&app)?;
+
+    reports::sales_comparison(&app_state.db, &periods, &settings).await
+}
+
+#[tauri::command]
+async fn export_sales_comparison(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    intl_state: State<'_, Intl>,
+    periods: Vec<ComparisonPeriod>,
+) -> CommandResult<PathBuf> {
+    app.authorize(Role::Supervisor).await?;
+
+    validate_comparison_periods(&periods, &intl_state)?;
+    let settings = load_event_settings(&app)?;
+    let export_settings = load_export_settings(&app)?;
+
+    let directory = match &export_settings.directory {
+        Some(directory) => directory.clone(),
+        None => app.path().document_dir()?,
+    };
+    std::fs::create_dir_all(&directory)?;
+
+    let file_name = format!(
+        "{}_{}",
+        intl_state.t("reports-comparison-file-name")?,
+        Local::now().format("%Y-%m-%d")
+    );
+    let report_file_path = unique_export_path(&directory, &file_name, "xlsx");
+
+    let comparison = reports::sales_comparison(&app_state.db, &periods, &settings).await?;
+    export_comparison_report(&comparison, &intl_state, &report_file_path)?;
+
+    Ok(report_file_path)
+}
+
+#[tauri::command]
+async fn void_sale(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    intl_state: State<'_, Intl>,
+    sale_id: Uuid,
+    reason: Option<String>,
+) -> CommandResult<()> {
+    let actor = app.authorize(Role::Supervisor).await?;
+
+    // The server notifies its clients of the new totals
+    if let Some(client) = app.remote_client()? {
+        return client.void_sale(sale_id, reason).await;
+    }
+
+    record_void(&app_state.db, &intl_state, &actor, sale_id, reason).await?;
+
+    live::notify_sales_changed(&app, &app_state.db, LiveTotalsChange::Void).await;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_live_totals(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> CommandResult<LiveTotals> {
+    app.authorize(Role::Supervisor).await?;
+
+    let settings = load_event_settings(&app)?;
+
+    live::live_totals(&app_state.db, &settings, None, Local::now().naive_local()).await
+}
+
+#[tauri::command]
+async fn get_report_filter_options(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> CommandResult<ReportFilterOptions> {
+    app.authorize(Role::Supervisor).await?;
+
+    reports::report_filter_options(&app_state.db).await
+}
+
+#[tauri::command]
+async fn clear_sales_data(app: AppHandle, app_state: State<'_, AppState>) -> CommandResult<()> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    info!("Clearing sales data");
+
+    let mut tx = begin_write(&app_state.db).await?;
+
+    let sales = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM sales")
+        .fetch_one(&mut *tx)
+        .await?;
+    let sale_items = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM sale_items")
+        .fetch_one(&mut *tx)
+        .await?;
+    let deleted_products =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM products WHERE is_deleted = 1")
+            .fetch_one(&mut *tx)
+            .await?;
+
+    journal::record_clear(&mut tx).await?;
+    sqlx::query!("DELETE FROM sale_items")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM sales").execute(&mut *tx).await?;
+    sqlx::query!("DELETE FROM products WHERE is_deleted = 1")
+        .execute(&mut *tx)
+        .await?;
+    sync::clear_sale_changes(&mut tx).await?;
+
+    audit::record(
+        &mut tx,
+        &actor,
+        AuditAction::SalesCleared,
+        None,
+        Some(serde_json::json!({
+            "sales": sales,
+            "sale_items": sale_items,
+            "deleted_products": deleted_products,
+        })),
+        None,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    live::notify_sales_changed(&app, &app_state.db, LiveTotalsChange::Clear).await;
+
+    Ok(())
+}
+
+/// Saves the report to the given file, or in the export directory with a
+/// templated name otherwise, and returns the path of the written file.
+#[tauri::command]
+async fn export_sales(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    intl_state: State<'_, Intl>,
+    destination: Option<PathBuf>,
+    format: Option<ExportFormat>,
+    filter: Option<ReportFilter>,
+) -> CommandResult<PathBuf> {
+    app.authorize(Role::Supervisor).await?;
+
+    let settings = load_event_settings(&app)?;
+    let export_settings = load_export_settings(&app)?;
+    let format = format.unwrap_or(ExportFormat::Xlsx);
+    let filter = filter.unwrap_or_default();
+
+    let report_file_path = match destination {
+        Some(destination) => {
+            if destination.exists() {
+                return Err(CommandError::InvalidInput(
+                    intl_state
+                        .t("reports-messages-export-file-already-exists")?
+                        .to_string(),
+                ));
+            }
+
+            destination
+        }
+        None => {
+            let directory = match &export_settings.directory {
+                Some(directory) => directory.clone(),
+                None => app.path().document_dir()?,
+            };
+            std::fs::create_dir_all(&directory)?;
+
+            let file_name =
+                export_settings.file_name(settings.name.as_deref(), Local::now().naive_local());
+
+            unique_export_path(&directory, &file_name, format.extension())
+        }
+    };
+
+    write_sales_export(
+        &app_state.db,
+        &intl_state,
+        &settings,
+        &export_settings,
+        format,
+        &filter,
+        &report_file_path,
+    )
+    .await?;
+
+    Ok(report_file_path)
+}
+
+#[tauri::command]
+async fn export_event_report(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    intl_state: State<'_, Intl>,
+    filter: Option<ReportFilter>,
+) -> CommandResult<PathBuf> {
+    app.authorize(Role::Supervisor).await?;
+
+    let settings = load_event_settings(&app)?;
+    let export_settings = load_export_settings(&app)?;
+    let now = Local::now().naive_local();
+
+    let directory = match &export_settings.directory {
+        Some(directory) => directory.clone(),
+        None => app.path().document_dir()?,
+    };
+    std::fs::create_dir_all(&directory)?;
+
+    let file_name = export_settings.file_name(settings.name.as_deref(), now);
+    let report_file_path = unique_export_path(&directory, &file_name, "pdf");
+
+    pdf_report::export_event_report(
+        &app_state.db,
+        &settings,
+        &intl_state,
+        &filter.unwrap_or_default(),
+        now,
+        &report_file_path,
+    )
+    .await?;
+
+    Ok(report_file_path)
+}
+
+#[tauri::command]
+async fn print_last_sale(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    printer_state: State<'_, PrinterState>,
+    intl_state: State<'_, Intl>,
+) -> CommandResult<()> {
+    app.authorize(Role::Cashier).await?;
+
+    let recorded = match app.remote_client()? {
+        Some(client) => client.get_last_sale().await?,
+        None => {
+            let pos_name = load_terminal_settings(&app)?.pos_name;
+            load_last_sale(&app_state.db, pos_name.as_deref()).await?
+        }
+    };
+
+    info!("Reprinting tickets of sale {}", recorded.sale.id);
+
+    print_recorded_sale(&app, &printer_state, &intl_state, &recorded)
+}
+
+#[tauri::command]
+async fn print_sale(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    printer_state: State<'_, PrinterState>,
+    intl_state: State<'_, Intl>,
+    sale_id: Uuid,
+) -> CommandResult<()> {
+    app.authorize(Role::Cashier).await?;
+
+    info!("Reprinting tickets of sale {}", sale_id);
+
+    let recorded = match app.remote_client()? {
+        Some(client) => client.get_sale(sale_id).await?,
+        None => load_recorded_sale(&app_state.db, sale_id).await?,
+    };
+
+    print_recorded_sale(&app, &printer_state, &intl_state, &recorded)
+}
+
+#[tauri::command]
+async fn find_sale_by_code(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    intl_state: State<'_, Intl>,
+    code: String,
+) -> CommandResult<Sale> {
+    app.authorize(Role::Cashier).await?;
+
+    info!("Looking up sale by scanned code {:?}", code);
+
+    match app.remote_client()? {
+        Some(client) => client.find_sale(&code).await,
+        None => {
+            let pos_name = load_terminal_settings(&app)?.pos_name;
+            find_sale(&app_state.db, &intl_state, &code, pos_name.as_deref()).await
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_event_settings(app: AppHandle) -> CommandResult<EventSettings> {
+    app.authorize(Role::Admin).await?;
+
+    load_event_settings(&app)
+}
+
+#[tauri::command]
+async fn save_event_settings(settings: EventSettings, app: AppHandle) -> CommandResult<()> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    info!("Saving event settings {:?}", settings);
+
+    audit::save_settings(&app, &actor, "event-settings", &settings).await
+}
+
+#[tauri::command]
+async fn get_export_settings(app: AppHandle) -> CommandResult<ExportSettings> {
+    app.authorize(Role::Admin).await?;
+
+    load_export_settings(&app)
+}
+
+#[tauri::command]
+async fn save_export_settings(settings: ExportSettings, app: AppHandle) -> CommandResult<()> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    info!("Saving export settings {:?}", settings);
+
+    audit::save_settings(&app, &actor, "export-settings", &settings).await
+}
+
+#[tauri::command]
+async fn get_backup_settings(app: AppHandle) -> CommandResult<BackupSettings> {
+    app.authorize(Role::Admin).await?;
+
+    load_backup_settings(&app)
+}
+
+#[tauri::command]
+async fn save_backup_settings(settings: BackupSettings, app: AppHandle) -> CommandResult<()> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    info!("Saving backup settings {:?}", settings);
+
+    audit::save_settings(&app, &actor, "backup-settings", &settings).await
+}
+
+/// Access to the terminal settings, which are for admins only. A client
+/// terminal that can't reach its server has no operator to log in, so it may
+/// still fix the server address, without seeing the token.
+enum TerminalSettingsAccess {
+    Admin(Actor),
+    ServerAddressOnly(Actor),
+}
+
+impl TerminalSettingsAccess {
+    /// Actor of the changes and settings they may apply, only the server
+    /// address of the given ones without an admin.
+    fn allowed(
+        self,
+        app: &AppHandle,
+        settings: TerminalSettings,
+    ) -> CommandResult<(Actor, TerminalSettings)> {
+        match self {
+            TerminalSettingsAccess::Admin(actor) => Ok((actor, settings)),
+            TerminalSettingsAccess::ServerAddressOnly(actor) => {
+                let allowed = TerminalSettings {
+                    server_url: settings.server_url,
+                    ..load_terminal_settings(app)?
+                };
+
+                Ok((actor, allowed))
+            }
+        }
+    }
+}
+
+async fn authorize_terminal_settings(app: &AppHandle) -> CommandResult<TerminalSettingsAccess> {
+    match app.authorize(Role::Admin).await {
+        Ok(actor) => Ok(TerminalSettingsAccess::Admin(actor)),
+        Err(CommandError::Remote(err)) => {
+            log::warn!("Server unreachable, only its address may be changed {}", err);
+
+            Ok(TerminalSettingsAccess::ServerAddressOnly(Actor::local(app)?))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[tauri::command]
+async fn get_terminal_settings(app: AppHandle) -> CommandResult<TerminalSettings> {
+    let settings = load_terminal_settings(&app)?;
+
+    match authorize_terminal_settings(&app).await? {
+        TerminalSettingsAccess::Admin(_) => Ok(settings),
+        TerminalSettingsAccess::ServerAddressOnly(_) => Ok(TerminalSettings {
+            api_token: None,
+            ..settings
+        }),
+    }
+}
+
+/// Saves the terminal settings. A new mode or server port applies at the
+/// next start.
+#[tauri::command]
+async fn save_terminal_settings(
+    settings: TerminalSettings,
+    app: AppHandle,
+    intl_state: State<'_, Intl>,
+) -> CommandResult<()> {
+    let (actor, settings) = authorize_terminal_settings(&app)
+        .await?
+        .allowed(&app, settings)?;
+
+    // Sales are numbered and found by cash desk, which synced desks tell apart
+    // by their name
+    if settings.mode == TerminalMode::Peer && settings.pos_name.is_none() {
+        return Err(CommandError::InvalidInput(
+            intl_state.t("settings-terminal-messages-pos-name-required")?
+                .to_string(),
+        ));
+    }
+
+    info!(
+        "Saving terminal settings {:?} {:?}",
+        settings.mode, settings.pos_name
+    );
+
+    audit::save_settings(&app, &actor, "terminal-settings", &settings).await
+}
+
+/// Checks that the server of the given settings answers and accepts the token.
+#[tauri::command]
+async fn test_server_connection(
+    app: AppHandle,
+    settings: TerminalSettings,
+) -> CommandResult<ServerStatus> {
+    // Without an admin, the server is tested with the saved token
+    let (_, settings) = authorize_terminal_settings(&app)
+        .await?
+        .allowed(&app, settings)?;
+    let settings = TerminalSettings {
+        mode: TerminalMode::Client,
+        ..settings
+    };
+
+    match RemoteClient::from_settings(&settings)? {
+        Some(client) => client.status().await,
+        None => Err(CommandError::Remote("Not a client terminal".to_string())),
+    }
+}
+
+#[tauri::command]
+async fn get_sync_status(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> CommandResult<SyncStatus> {
+    app.authorize(Role::Admin).await?;
+
+    sync::sync_status(&app_state.db).await
+}
+
+/// Syncs with the peers right away, instead of waiting for the next round.
+#[tauri::command]
+async fn sync_now(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    intl_state: State<'_, Intl>,
+) -> CommandResult<SyncStatus> {
+    app.authorize(Role::Admin).await?;
+
+    if load_terminal_settings(&app)?.mode != TerminalMode::Peer {
+        return Err(CommandError::InvalidInput(
+            intl_state.t("settings-sync-messages-not-peer")?.to_string(),
+        ));
+    }
+
+    sync::sync_with_peers(&app).await?;
+
+    sync::sync_status(&app_state.db).await
+}
+
+#[tauri::command]
+async fn list_backups(app: AppHandle) -> CommandResult<Vec<BackupFile>> {
+    app.authorize(Role::Admin).await?;
+
+    let settings = load_backup_settings(&app)?;
+
+    backup::list_backups(&backup_directory(&app, &settings)?)
+}
+
+#[tauri::command]
+async fn backup_now(app: AppHandle) -> CommandResult<PathBuf> {
+    app.authorize(Role::Admin).await?;
+
+    backup_app_database(&app, BackupReason::Manual).await
+}
+
+/// Replaces the database with the given backup and restarts the application.
+/// The file is checked to be a database with a schema this version can
+/// migrate, and the current database is backed up before being replaced.
+#[tauri::command]
+async fn restore_backup(
+    app: AppHandle,
+    intl_state: State<'_, Intl>,
+    path: PathBuf,
+) -> CommandResult<()> {
+    app.authorize(Role::Admin).await?;
+
+    let schema_version = backup::validate_backup(&path, &MIGRATOR, &intl_state).await?;
+    info!(
+        "Restoring backup {:?} with schema version {}",
+        path, schema_version
+    );
+
+    backup_app_database(&app, BackupReason::Restore).await?;
+    backup::stage_restore(&path, &database_path(&app)?)?;
+
+    app.restart()
+}
+
+/// Merges the sales and products of another terminal's database, or of one of
+/// its backups, into this one after a backup.
+#[tauri::command]
+async fn merge_database(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    intl_state: State<'_, Intl>,
+    path: PathBuf,
+) -> CommandResult<MergeReport> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    backup::validate_backup(&path, &MIGRATOR, &intl_state).await?;
+
+    let db_path = database_path(&app)?;
+    if path.canonicalize()? == db_path.canonicalize()? {
+        return Err(CommandError::InvalidInput(
+            intl_state.t("settings-merge-messages-same-database")?.to_string(),
+        ));
+    }
+
+    backup_app_database(&app, BackupReason::Merge).await?;
+
+    let report = merge::merge_database(
+        &app_state.db,
+        &actor,
+        &path,
+        &db_path.with_extension("db.merging"),
+        &MIGRATOR,
+    )
+    .await?;
+
+    live::notify_catalog_changed(&app, &app_state.db).await;
+
+    Ok(report)
+}
+
+/// Checks that the recorded sales weren't changed or removed, and that the
+/// given hash, printed on a previous recap, is in the journal.
+#[tauri::command]
+async fn verify_journal(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    known_hash: Option<String>,
+) -> CommandResult<JournalVerification> {
+    app.authorize(Role::Admin).await?;
+
+    journal::verify(&app_state.db, known_hash.as_deref()).await
+}
+
+#[tauri::command]
+async fn list_audit_log(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    filter: Option<AuditFilter>,
+) -> CommandResult<Vec<AuditEntry>> {
+    app.authorize(Role::Admin).await?;
+
+    audit::list_entries(&app_state.db, &filter.unwrap_or_default()).await
+}
+
+/// Saves the entries of the audit log matching the filter in the export
+/// directory, and returns the path of the written file.
+#[tauri::command]
+async fn export_audit_log(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    intl_state: State<'_, Intl>,
+    format: ExportFormat,
+    filter: Option<AuditFilter>,
+) -> CommandResult<PathBuf> {
+    app.authorize(Role::Admin).await?;
+
+    let extension = match format {
+        ExportFormat::Csv => "csv",
+        ExportFormat::Json => "json",
+        ExportFormat::Xlsx => {
+            return Err(CommandError::InvalidInput(
+                intl_state
+                    .t("settings-audit-messages-unsupported-format")?
+                    .to_string(),
+            ))
+        }
+    };
+
+    let export_settings = load_export_settings(&app)?;
+    let directory = match &export_settings.directory {
+        Some(directory) => directory.clone(),
+        None => app.path().document_dir()?,
+    };
+    std::fs::create_dir_all(&directory)?;
+
+    let file_name = format!("registro_modifiche_{}", Local::now().format("%Y-%m-%d"));
+    let path = unique_export_path(&directory, &file_name, extension);
+
+    let entries = audit::list_entries(&app_state.db, &filter.unwrap_or_default()).await?;
+    write_audit_log(&entries, &path, format)?;
+
+    info!("Audit log exported to {:?}", path);
+
+    Ok(path)
+}
+
+#[tauri::command]
+async fn get_print_layout(app: AppHandle) -> CommandResult<PrintingLayout> {
+    app.authorize(Role::Admin).await?;
+
+    load_print_layout(&app)
+}
+
+#[tauri::command]
+async fn save_print_layout(layout: PrintingLayout, app: AppHandle) -> CommandResult<()> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    info!("Saving updated layout {:?}", layout);
+
+    audit::save_settings(&app, &actor, "ticket-layout", &layout).await
+}
+
+#[tauri::command]
+async fn save_printer_device(
+    app: AppHandle,
+    printer_state: State<'_, PrinterState>,
+    printer_monitor: State<'_, PrinterMonitorState>,
+    device: UsbDevice,
+) -> CommandResult<()> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    info!("Saving printer device {:?}", device);
+
+    #[cfg(debug_assertions)]
+    let driver = ConsoleDriver::open(true);
+    #[cfg(not(debug_assertions))]
+    let driver = UsbDriver::open(device.vendor_id, device.product_id, None)?;
+
+    printer_monitor.set_driver(Some(driver.clone()))?;
+
+    let new_printer = Printer::new(driver, Protocol::default(), None);
+    *printer_state.lock()? = Some(new_printer);
+
+    audit::save_settings(&app, &actor, "printer-device", &device).await
+}
+
+#[tauri::command]
+async fn get_printer_status(
+    app: AppHandle,
+    printer_monitor: State<'_, PrinterMonitorState>,
+) -> CommandResult<Option<PrinterStatus>> {
+    app.authorize(Role::Cashier).await?;
+
+    printer_monitor.current_status()
+}
+
+#[tauri::command]
+async fn get_printer_settings(app: AppHandle) -> CommandResult<PrinterSettings> {
+    app.authorize(Role::Admin).await?;
+
+    load_printer_settings(&app)
+}
+
+#[tauri::command]
+async fn save_printer_settings(settings: PrinterSettings, app: AppHandle) -> CommandResult<()> {
+    let actor = app.authorize(Role::Admin).await?;
+
+    info!("Saving printer settings {:?}", settings);
+
+    audit::save_settings(&app, &actor, "printer-settings", &settings).await
+}
+
+#[tauri::command]
+async fn print_printer_test_page(
+    app: AppHandle,
+    printer_state: State<'_, PrinterState>,
+    settings: PrinterSettings,
+) -> CommandResult<()> {
+    app.authorize(Role::Admin).await?;
+
+    let mut mutex_guard = printer_state.lock()?;
+    let printer = mutex_guard
+        .as_mut()
+        .ok_or(CommandError::PrinterNotConfigured)?;
+
+    print_test_page(printer, &settings)
+}
+
+#[tauri::command]
+async fn list_usb_devices(
+    app: AppHandle,
+) -> CommandResult<Vec<UsbDevice>> {
+    app.authorize(Role::Admin).await?;
+
+    usb_devices()
+}
+
+#[tauri::command]
+async fn test_print_raw_file(
+    app: AppHandle,
+    device: UsbDevice,
+    text_to_print: String,
+) -> CommandResult<()> {
+    app.authorize(Role::Admin).await?;
+
+    info!("Attempting to print {:?} on {:?}", text_to_print, device);
+
+    let driver = UsbDriver::open(device.vendor_id, device.product_id, None)?;
+    let mut printer = Printer::new(driver, Protocol::default(), None);
+
+    println!();
+    printer
+        .debug_mode(Some(DebugMode::Dec))
+        .init()?
+        .writeln(&text_to_print)?
+        .feed()?
+        .print_cut()?;
+    println!();
+
+    Ok(())
+}
+
+/// Why the database couldn't be opened, shown by the recovery screen.
+#[derive(Serialize, Debug, Clone)]
+struct StartupError {
+    message: String,
+    data_dir: Option<PathBuf>,
+    latest_backup: Option<BackupFile>,
+}
+
+type StartupState = Mutex<Option<StartupError>>;
+
+/// Opens the database and starts what depends on it.
+async fn start_database(app: &AppHandle) -> CommandResult<()> {
+    let db = setup_db(app).await?;
+    app.manage(AppState { db });
+
+    tauri::async_runtime::spawn(run_scheduled_backups(app.clone()));
+
+    let terminal_settings = load_terminal_settings(app)?;
+    match terminal_settings.mode {
+        TerminalMode::Standalone => {}
+        TerminalMode::Server => {
+            tauri::async_runtime::spawn(server::serve(app.clone(), terminal_settings));
+        }
+        TerminalMode::Client => {
+            tauri::async_runtime::spawn(terminal::watch_server_events(app.clone()));
+        }
+        TerminalMode::Peer => {
+            tauri::async_runtime::spawn(server::serve(app.clone(), terminal_settings));
+            tauri::async_runtime::spawn(sync::watch_peers(app.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+fn startup_error(app: &AppHandle, err: &CommandError) -> StartupError {
+    log::error!("Failed to open the database {:?}", err);
+
+    let latest_backup = load_backup_settings(app)
+        .and_then(|settings| backup_directory(app, &settings))
+        .and_then(|directory| backup::list_backups(&directory))
+        .ok()
+        .and_then(|backups| backups.into_iter().next());
+
+    StartupError {
+        message: err.to_string(),
+        data_dir: app.path().app_data_dir().ok(),
+        latest_backup,
+    }
+}
+
+/// Tries to open the database again, returning the new error if it still fails.
+async fn retry_start_database(
+    app: &AppHandle,
+    startup_state: &StartupState,
+) -> CommandResult<Option<StartupError>> {
+    let error = match start_database(app).await {
+        Ok(()) => None,
+        Err(err) => Some(startup_error(app, &err)),
+    };
+
+    *startup_state.lock()? = error.clone();
+
+    Ok(error)
+}
+
+/// Recovery actions replace the database file, so they're only allowed while
+/// the database isn't open.
+fn ensure_database_not_started(app: &AppHandle, intl: &Intl) -> CommandResult<()> {
+    if app.try_state::<AppState>().is_some() {
+        return Err(CommandError::InvalidInput(
+            intl.t("startup-messages-already-started")?.to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_startup_error(
+    startup_state: State<'_, StartupState>,
+) -> CommandResult<Option<StartupError>> {
+    Ok(startup_state.lock()?.clone())
+}
+
+#[tauri::command]
+async fn retry_startup(
+    app: AppHandle,
+    startup_state: State<'_, StartupState>,
+) -> CommandResult<Option<StartupError>> {
+    if app.try_state::<AppState>().is_some() {
+        return Ok(None);
+    }
+
+    retry_start_database(&app, &startup_state).await
+}
+
+#[tauri::command]
+async fn open_data_folder(app: AppHandle) -> CommandResult<()> {
+    let directory = app.path().app_data_dir()?;
+
+    app.opener()
+        .open_path(directory.to_string_lossy(), None::<&str>)
+        .map_err(|err| CommandError::Io(err.to_string()))
+}
+
+/// Replaces the database with the newest valid backup and opens it.
+#[tauri::command]
+async fn restore_latest_backup(
+    app: AppHandle,
+    intl_state: State<'_, Intl>,
+    startup_state: State<'_, StartupState>,
+) -> CommandResult<Option<StartupError>> {
+    ensure_database_not_started(&app, &intl_state)?;
+
+    let settings = load_backup_settings(&app)?;
+    let backups = backup::list_backups(&backup_directory(&app, &settings)?)?;
+
+    let mut latest_valid = None;
+    for backup_file in backups {
+        match backup::validate_backup(&backup_file.path, &MIGRATOR, &intl_state).await {
+            Ok(_) => {
+                latest_valid = Some(backup_file);
+                break;
+            }
+            Err(err) => log::error!("Skipping backup {:?}: {:?}", backup_file.path, err),
+        }
+    }
+
+    let Some(backup_file) = latest_valid else {
+        return Err(CommandError::InvalidInput(
+            intl_state.t("startup-messages-no-valid-backup")?.to_string(),
+        ));
+    };
+
+    info!("Restoring backup {:?} at startup", backup_file.path);
+
+    let db_path = database_path(&app)?;
+    backup::move_database_aside(&db_path, Local::now().naive_local())?;
+    backup::stage_restore(&backup_file.path, &db_path)?;
+
+    retry_start_database(&app, &startup_state).await
+}
+
+/// Moves the unusable database aside, keeping it for a later recovery, and
+/// starts with an empty one.
+#[tauri::command]
+async fn start_with_new_database(
+    app: AppHandle,
+    intl_state: State<'_, Intl>,
+    startup_state: State<'_, StartupState>,
+) -> CommandResult<Option<StartupError>> {
+    ensure_database_not_started(&app, &intl_state)?;
+
+    backup::move_database_aside(&database_path(&app)?, Local::now().naive_local())?;
+
+    retry_start_database(&app, &startup_state).await
+}
+
+fn backup_on_close(app: &AppHandle) {
+    if app.try_state::<AppState>().is_none() {
+        return;
+    }
+
+    match load_backup_settings(app) {
+        Ok(settings) if !settings.on_close => {}
+        Ok(_) => {
+            if let Err(err) =
+                tauri::async_runtime::block_on(backup_app_database(app, BackupReason::Close))
+            {
+                log::error!("Backup on close failed {:?}", err);
+            }
+        }
+        Err(err) => log::error!("Failed to load the backup settings {:?}", err),
+    }
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_log::Builder::new().build())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_store::Builder::new().build())
+        .invoke_handler(tauri::generate_handler![
+            list_products,
+            create_product,
+            update_product,
+            delete_product,
+            clear_sales_data,
+            process_sale,
+            open_cash_drawer,
+            get_sales_recap,
+            get_today_sales,
+            list_sales,
+            get_sales_breakdown,
+            get_sales_summary,
+            get_product_performance,
+            print_sales_summary,
+            void_sale,
+            get_report_filter_options,
+            get_live_totals,
+            get_event_settings,
+            save_event_settings,
+            get_export_settings,
+            save_export_settings,
+            export_sales,
+            export_event_report,
+            get_sales_comparison,
+            export_sales_comparison,
+            print_last_sale,
+            print_sale,
+            find_sale_by_code,
+            get_print_layout,
+            save_print_layout,
+            save_printer_device,
+            get_printer_status,
+            get_printer_settings,
+            save_printer_settings,
+            print_printer_test_page,
+            list_usb_devices,
+            test_print_raw_file,
+            get_backup_settings,
+            save_backup_settings,
+            list_backups,
+            backup_now,
+            restore_backup,
+            merge_database,
+            list_audit_log,
+            verify_journal,
+            export_audit_log,
+            get_startup_error,
+            retry_startup,
+            open_data_folder,
+            restore_latest_backup,
+            start_with_new_database,
+            get_terminal_settings,
+            save_terminal_settings,
+            test_server_connection,
+            get_sync_status,
+            sync_now,
+            get_operator_session,
+            login_operator,
+            logout_operator,
+            list_operators,
+            create_operator,
+            update_operator,
+        ])
+        .setup(|app| {
+            app.store("store.json")?;
+
+            let langid_it = langid!("it");
+            let intl = Intl::try_new(langid_it).expect("Failed to load localization");
+            app.manage(intl);
+            app.manage(Session::default());
+            app.manage(LoginAttempts::default());
+
+            let (printer_state, printer_monitor) = setup_printer_state(app.handle());
+            app.manage(printer_state);
+            app.manage(printer_monitor);
+
+            let app_handle = app.handle().clone();
+            std::thread::spawn(move || watch_printer_status(app_handle));
+
+            // Errors are shown by the recovery screen instead of closing the application
+            let app_handle = app.handle().clone();
+            let startup_error = tauri::async_runtime::block_on(start_database(&app_handle))
+                .err()
+                .map(|err| startup_error(&app_handle, &err));
+            app.manage::<StartupState>(Mutex::new(startup_error));
+
+            Ok(())
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                backup_on_close(app);
+            }
+        });
+}
@@ -0,0 +1,275 @@
+#[cfg(feature = "gui")]
+use std::path::Path;
+
+use chrono::Local;
+#[cfg(feature = "gui")]
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+#[cfg(feature = "gui")]
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqliteConnection;
+#[cfg(feature = "gui")]
+use sqlx::{FromRow, QueryBuilder, Sqlite};
+use uuid::Uuid;
+
+#[cfg(feature = "gui")]
+use crate::{host::Host, CommandError, Db};
+use crate::{host::SettingsStore, load_terminal_settings, operators::Operator, CommandResult};
+
+/// Fields of the settings never written to the audit log.
+#[cfg(feature = "gui")]
+const SECRET_SETTINGS: [&str; 1] = ["api_token"];
+
+/// Change recorded in the audit log.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+pub(crate) enum AuditAction {
+    ProductCreated,
+    ProductUpdated,
+    /// Update of a product changing its price, kept apart to find them quickly
+    PriceChanged,
+    ProductDeleted,
+    SettingsChanged,
+    SaleVoided,
+    SalesCleared,
+    DatabaseMerged,
+    OperatorCreated,
+    /// Update of an operator, their PIN included
+    OperatorUpdated,
+    /// Cash drawer opened outside of a sale
+    CashDrawerOpened,
+}
+
+/// Who makes a change, recorded with it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Actor {
+    pub name: Option<String>,
+    /// Account of the operator, when logged in
+    pub operator_id: Option<Uuid>,
+    /// Cash desk the change was made from
+    pub pos_name: Option<String>,
+}
+
+impl Actor {
+    /// Changes made on this terminal.
+    pub(crate) fn local(store: &impl SettingsStore) -> CommandResult<Self> {
+        Ok(Actor {
+            pos_name: load_terminal_settings(store)?.pos_name,
+            ..Actor::default()
+        })
+    }
+
+    /// Changes made by the operator, anonymous ones when nobody is logged in.
+    pub(crate) fn operator(operator: Option<&Operator>, pos_name: Option<String>) -> Self {
+        Actor {
+            name: operator.map(|operator| operator.name.clone()),
+            operator_id: operator.map(|operator| operator.id),
+            pos_name,
+        }
+    }
+
+    /// Changes received from another terminal, named by its origin in the
+    /// change logs as they may be relayed by a third one.
+    pub(crate) fn sync(origin: &str) -> Self {
+        Actor {
+            name: Some(format!("sync {}", origin)),
+            ..Actor::default()
+        }
+    }
+
+    /// Changes brought by a database merged into this one.
+    #[cfg(feature = "gui")]
+    pub(crate) fn merge(source: &Path) -> Self {
+        Actor {
+            name: Some(format!("merge {}", source.display())),
+            ..Actor::default()
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct AuditEntry {
+    pub id: i64,
+    pub recorded_at: NaiveDateTime,
+    pub action: AuditAction,
+    /// Product or sale id, or key of the settings
+    pub entity_id: Option<String>,
+    pub actor: Option<String>,
+    pub operator_id: Option<Uuid>,
+    pub pos_name: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+#[cfg(feature = "gui")]
+#[derive(FromRow)]
+struct AuditRow {
+    id: i64,
+    recorded_at: NaiveDateTime,
+    action: AuditAction,
+    entity_id: Option<String>,
+    actor: Option<String>,
+    operator_id: Option<Uuid>,
+    pos_name: Option<String>,
+    before_value: Option<String>,
+    after_value: Option<String>,
+}
+
+#[cfg(feature = "gui")]
+impl TryFrom<AuditRow> for AuditEntry {
+    type Error = CommandError;
+
+    fn try_from(row: AuditRow) -> CommandResult<Self> {
+        let parse = |value: Option<String>| -> CommandResult<Option<Value>> {
+            Ok(value
+                .map(|value| serde_json::from_str(&value))
+                .transpose()?)
+        };
+
+        Ok(AuditEntry {
+            id: row.id,
+            recorded_at: row.recorded_at,
+            action: row.action,
+            entity_id: row.entity_id,
+            actor: row.actor,
+            operator_id: row.operator_id,
+            pos_name: row.pos_name,
+            before: parse(row.before_value)?,
+            after: parse(row.after_value)?,
+        })
+    }
+}
+
+/// Entries to list or export, by the day they were recorded.
+#[cfg(feature = "gui")]
+#[derive(Deserialize, Debug, Clone, Default)]
+pub(crate) struct AuditFilter {
+    pub from_day: Option<NaiveDate>,
+    pub to_day: Option<NaiveDate>,
+    pub action: Option<AuditAction>,
+}
+
+/// Appends the change to the audit log, in the transaction making it.
+pub(crate) async fn record(
+    conn: &mut SqliteConnection,
+    actor: &Actor,
+    action: AuditAction,
+    entity_id: Option<&str>,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> CommandResult<()> {
+    let before = before.map(|value| value.to_string());
+    let after = after.map(|value| value.to_string());
+
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (recorded_at, action, entity_id, actor, operator_id, pos_name,
+            before_value, after_value)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(Local::now().naive_local())
+    .bind(action)
+    .bind(entity_id)
+    .bind(&actor.name)
+    .bind(actor.operator_id)
+    .bind(&actor.pos_name)
+    .bind(before)
+    .bind(after)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Saves the settings of the key and records the change, when there is one.
+/// The settings can be saved while the database is unavailable, e.g. to
+/// point the backups to another folder, the change is only logged then.
+#[cfg(feature = "gui")]
+pub(crate) async fn save_settings<T: Serialize>(
+    host: &impl Host,
+    actor: &Actor,
+    key: &str,
+    settings: &T,
+) -> CommandResult<()> {
+    let before = host.get_setting(key)?;
+    let after = serde_json::to_value(settings).or(Err(CommandError::StoreSettings))?;
+
+    host.set_setting(key, after.clone())?;
+
+    if before.as_ref() == Some(&after) {
+        return Ok(());
+    }
+
+    let db = match host.db() {
+        Ok(db) => db,
+        Err(_) => {
+            warn!(
+                "Change of the {} settings not audited, the database is unavailable",
+                key
+            );
+            return Ok(());
+        }
+    };
+
+    let mut conn = db.acquire().await?;
+    record(
+        &mut conn,
+        actor,
+        AuditAction::SettingsChanged,
+        Some(key),
+        before.map(without_secrets),
+        Some(without_secrets(after)),
+    )
+    .await
+}
+
+#[cfg(feature = "gui")]
+fn without_secrets(mut settings: Value) -> Value {
+    if let Some(fields) = settings.as_object_mut() {
+        for field in SECRET_SETTINGS {
+            if let Some(value) = fields.get_mut(field).filter(|value| !value.is_null()) {
+                *value = Value::String("***".to_string());
+            }
+        }
+    }
+
+    settings
+}
+
+/// Entries matching the filter, the latest first.
+#[cfg(feature = "gui")]
+pub(crate) async fn list_entries(db: &Db, filter: &AuditFilter) -> CommandResult<Vec<AuditEntry>> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT id, recorded_at, action, entity_id, actor, operator_id, pos_name, before_value,
+            after_value
+        FROM audit_log
+        WHERE 1 = 1
+        "#,
+    );
+
+    if let Some(from_day) = filter.from_day {
+        query
+            .push(" AND recorded_at >= ")
+            .push_bind(from_day.and_time(NaiveTime::MIN));
+    }
+    if let Some(to_day) = filter.to_day.and_then(|day| day.succ_opt()) {
+        query
+            .push(" AND recorded_at < ")
+            .push_bind(to_day.and_time(NaiveTime::MIN));
+    }
+    if let Some(action) = filter.action {
+        query.push(" AND action = ").push_bind(action);
+    }
+    query.push(" ORDER BY id DESC");
+
+    query
+        .build_query_as::<AuditRow>()
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(AuditEntry::try_from)
+        .collect()
+}
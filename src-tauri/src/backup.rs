@@ -0,0 +1,432 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use fluent_bundle::FluentArgs;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqliteConnection},
+    Connection, SqlitePool,
+};
+
+use crate::{CommandError, CommandResult, Intl};
+
+/// Prefix of the backup file names, so that rotation only touches our files.
+const BACKUP_FILE_PREFIX: &str = "app-";
+const BACKUP_FILE_EXTENSION: &str = "db";
+
+/// Tables a database must have to be restored.
+const REQUIRED_TABLES: [&str; 3] = ["products", "sales", "sale_items"];
+
+/// Automatic database backups, stored in the "backup-settings" key.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct BackupSettings {
+    /// Defaults to the "backups" folder in the application data folder,
+    /// can point to a USB stick so the sales survive a broken PC
+    pub directory: Option<PathBuf>,
+    /// Minutes between the automatic backups, 0 turns them off
+    #[serde(default = "default_interval_minutes")]
+    pub interval_minutes: u32,
+    /// Number of backups kept, older ones are deleted
+    #[serde(default = "default_keep")]
+    pub keep: u32,
+    /// Backs up the database when the application is closed
+    #[serde(default = "default_on_close")]
+    pub on_close: bool,
+}
+
+fn default_interval_minutes() -> u32 {
+    15
+}
+
+fn default_keep() -> u32 {
+    20
+}
+
+fn default_on_close() -> bool {
+    true
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            interval_minutes: default_interval_minutes(),
+            keep: default_keep(),
+            on_close: default_on_close(),
+        }
+    }
+}
+
+/// Why a backup was made, saved at the end of the file name.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum BackupReason {
+    Scheduled,
+    Manual,
+    Close,
+    /// Before the migrations of a new version are applied
+    Migration,
+    /// Before a backup replaces the current database
+    Restore,
+    /// Before the database of another terminal is merged in
+    Merge,
+}
+
+impl BackupReason {
+    const ALL: [BackupReason; 6] = [
+        BackupReason::Scheduled,
+        BackupReason::Manual,
+        BackupReason::Close,
+        BackupReason::Migration,
+        BackupReason::Restore,
+        BackupReason::Merge,
+    ];
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            BackupReason::Scheduled => "scheduled",
+            BackupReason::Manual => "manual",
+            BackupReason::Close => "close",
+            BackupReason::Migration => "pre-migration",
+            BackupReason::Restore => "pre-restore",
+            BackupReason::Merge => "pre-merge",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct BackupFile {
+    pub path: PathBuf,
+    pub file_name: String,
+    pub reason: Option<BackupReason>,
+    pub size: u64,
+    pub created_at: NaiveDateTime,
+}
+
+/// Copies the database in the directory with `VACUUM INTO`, which writes a
+/// consistent and compacted copy while the application keeps running, then
+/// deletes the backups exceeding `keep` for each reason.
+pub(crate) async fn create_backup(
+    db: &SqlitePool,
+    directory: &Path,
+    reason: BackupReason,
+    keep: u32,
+    now: NaiveDateTime,
+) -> CommandResult<PathBuf> {
+    fs::create_dir_all(directory)?;
+
+    let stem = format!(
+        "{}{}-{}",
+        BACKUP_FILE_PREFIX,
+        now.format("%Y-%m-%d_%H%M%S"),
+        reason.suffix()
+    );
+    let mut path = directory.join(format!("{}.{}", stem, BACKUP_FILE_EXTENSION));
+    let mut counter = 1;
+    while path.exists() {
+        counter += 1;
+        path = directory.join(format!("{}-{}.{}", stem, counter, BACKUP_FILE_EXTENSION));
+    }
+
+    // Written under another name first, so that an unplugged USB stick never
+    // leaves a truncated file looking like a valid backup
+    let partial_path = path.with_extension("partial");
+    if partial_path.exists() {
+        fs::remove_file(&partial_path)?;
+    }
+
+    copy_database(db, &partial_path).await?;
+    fs::rename(&partial_path, &path)?;
+
+    info!("Database backed up to {:?}", path);
+
+    if let Err(err) = rotate_backups(directory, keep) {
+        error!("Failed to delete the old backups {:?}", err);
+    }
+
+    Ok(path)
+}
+
+/// Writes a copy of the database to a file that must not exist yet.
+pub(crate) async fn copy_database(db: &SqlitePool, path: &Path) -> CommandResult<()> {
+    sqlx::query("VACUUM INTO ?")
+        .bind(path.to_string_lossy().into_owned())
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Backups in the directory, newest first.
+pub(crate) fn list_backups(directory: &Path) -> CommandResult<Vec<BackupFile>> {
+    if !directory.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut backups = vec![];
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+
+        if !file_name.starts_with(BACKUP_FILE_PREFIX)
+            || path.extension().and_then(|ext| ext.to_str()) != Some(BACKUP_FILE_EXTENSION)
+        {
+            continue;
+        }
+
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let stem = file_name.trim_end_matches(".db");
+        let reason = BackupReason::ALL.into_iter().find(|reason| {
+            stem.ends_with(reason.suffix())
+                || stem
+                    .rsplit_once('-')
+                    .is_some_and(|(rest, _)| rest.ends_with(reason.suffix()))
+        });
+
+        backups.push(BackupFile {
+            path,
+            file_name,
+            reason,
+            size: metadata.len(),
+            created_at: DateTime::<Local>::from(metadata.modified()?).naive_local(),
+        });
+    }
+
+    // The names start with the backup time, so they sort chronologically
+    backups.sort_by(|a, b| b.file_name.cmp(&a.file_name));
+
+    Ok(backups)
+}
+
+/// Keeps the latest backups of each reason, so that frequent scheduled
+/// backups don't push out the ones taken before a migration, restore or merge.
+fn rotate_backups(directory: &Path, keep: u32) -> CommandResult<()> {
+    let backups = list_backups(directory)?;
+
+    let mut kept: HashMap<Option<BackupReason>, usize> = HashMap::new();
+    for backup in backups {
+        let count = kept.entry(backup.reason).or_default();
+        *count += 1;
+        if *count <= keep.max(1) as usize {
+            continue;
+        }
+
+        info!("Deleting old backup {:?}", backup.path);
+        fs::remove_file(&backup.path)?;
+    }
+
+    Ok(())
+}
+
+/// Whether the database has migrations still to apply. A new database has
+/// nothing worth backing up, so it has none.
+pub(crate) async fn has_pending_migrations(
+    db: &SqlitePool,
+    migrator: &Migrator,
+) -> CommandResult<bool> {
+    let has_migrations_table = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'",
+    )
+    .fetch_one(db)
+    .await?
+        > 0;
+
+    if !has_migrations_table {
+        return Ok(false);
+    }
+
+    let applied: HashSet<i64> =
+        sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations WHERE success = 1")
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .collect();
+
+    Ok(migrator
+        .iter()
+        .any(|migration| !applied.contains(&migration.version)))
+}
+
+/// Checks that the file is a database of this application, with a schema
+/// this version can open, and returns its schema version. Older schemas are
+/// accepted, as the migrations bring them up to date after the restore.
+pub(crate) async fn validate_backup(
+    path: &Path,
+    migrator: &Migrator,
+    intl: &Intl,
+) -> CommandResult<i64> {
+    let invalid_file = || -> CommandResult<CommandError> {
+        Ok(CommandError::InvalidInput(
+            intl.t("settings-backup-messages-invalid-file")?.to_string(),
+        ))
+    };
+
+    if !path.is_file() {
+        return Err(invalid_file()?);
+    }
+
+    let options = SqliteConnectOptions::new().filename(path).read_only(true);
+
+    let versions = match read_schema_versions(&options).await {
+        Ok(Some(versions)) => versions,
+        Ok(None) => return Err(invalid_file()?),
+        Err(err) => {
+            error!("Failed to read the backup {:?}: {:?}", path, err);
+
+            return Err(invalid_file()?);
+        }
+    };
+
+    let known: HashSet<i64> = migrator.iter().map(|migration| migration.version).collect();
+    let schema_version = versions.iter().copied().max().unwrap_or_default();
+
+    if versions.iter().any(|version| !known.contains(version)) {
+        let mut args = FluentArgs::new();
+        args.set("version", schema_version.to_string());
+
+        return Err(CommandError::InvalidInput(
+            intl.t_args("settings-backup-messages-newer-schema", Some(&args))?
+                .to_string(),
+        ));
+    }
+
+    Ok(schema_version)
+}
+
+/// Applied migration versions, or `None` when the file isn't a sound
+/// database of this application.
+async fn read_schema_versions(options: &SqliteConnectOptions) -> CommandResult<Option<Vec<i64>>> {
+    let mut conn = SqliteConnection::connect_with(options).await?;
+
+    let check = sqlx::query_scalar::<_, String>("PRAGMA quick_check")
+        .fetch_one(&mut conn)
+        .await?;
+    if check != "ok" {
+        error!("Backup integrity check failed: {}", check);
+
+        return Ok(None);
+    }
+
+    let tables: HashSet<String> =
+        sqlx::query_scalar::<_, String>("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .fetch_all(&mut conn)
+            .await?
+            .into_iter()
+            .collect();
+    if !tables.contains("_sqlx_migrations")
+        || REQUIRED_TABLES.iter().any(|table| !tables.contains(*table))
+    {
+        return Ok(None);
+    }
+
+    let failed =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM _sqlx_migrations WHERE success = 0")
+            .fetch_one(&mut conn)
+            .await?;
+    if failed > 0 {
+        return Ok(None);
+    }
+
+    let versions = sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations")
+        .fetch_all(&mut conn)
+        .await?;
+
+    conn.close().await?;
+
+    Ok(Some(versions))
+}
+
+/// Where a validated backup waits for the restart to replace the database,
+/// as the open connections keep the current file in use.
+pub(crate) fn restore_staging_path(db_path: &Path) -> PathBuf {
+    db_path.with_extension("db.restore")
+}
+
+/// Copies the validated backup next to the database, to be swapped in by
+/// `apply_pending_restore` at the next start.
+pub(crate) fn stage_restore(backup_path: &Path, db_path: &Path) -> CommandResult<()> {
+    let staging_path = restore_staging_path(db_path);
+    let partial_path = staging_path.with_extension("restore.partial");
+
+    fs::copy(backup_path, &partial_path)?;
+    fs::rename(&partial_path, &staging_path)?;
+
+    Ok(())
+}
+
+/// Replaces the database with the staged backup, if any. Must run before the
+/// database is opened.
+pub(crate) fn apply_pending_restore(db_path: &Path) -> CommandResult<bool> {
+    let staging_path = restore_staging_path(db_path);
+    if !staging_path.exists() {
+        return Ok(false);
+    }
+
+    info!("Restoring the backup staged in {:?}", staging_path);
+    replace_database(&staging_path, db_path)?;
+
+    Ok(true)
+}
+
+/// Moves `source` in place of the closed database.
+pub(crate) fn replace_database(source: &Path, db_path: &Path) -> CommandResult<()> {
+    // A journal left over from the replaced database would be applied to the
+    // new one and corrupt it
+    for journal in journal_paths(db_path) {
+        if journal.exists() {
+            fs::remove_file(journal)?;
+        }
+    }
+
+    fs::rename(source, db_path)?;
+
+    Ok(())
+}
+
+/// Renames the closed database and its journals, so that a new database can
+/// be created in its place, and returns the new path of the database.
+#[cfg(feature = "gui")]
+pub(crate) fn move_database_aside(db_path: &Path, now: NaiveDateTime) -> CommandResult<PathBuf> {
+    let suffix = format!("unusable-{}", now.format("%Y-%m-%d_%H%M%S"));
+    let moved_path = path_with_suffix(db_path, &format!(".{}", suffix));
+
+    for journal in journal_paths(db_path) {
+        if journal.exists() {
+            fs::rename(
+                &journal,
+                path_with_suffix(&journal, &format!(".{}", suffix)),
+            )?;
+        }
+    }
+    if db_path.exists() {
+        fs::rename(db_path, &moved_path)?;
+    }
+
+    info!("Database moved to {:?}", moved_path);
+
+    Ok(moved_path)
+}
+
+fn journal_paths(db_path: &Path) -> Vec<PathBuf> {
+    ["-wal", "-shm", "-journal"]
+        .into_iter()
+        .map(|suffix| path_with_suffix(db_path, suffix))
+        .collect()
+}
+
+fn path_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+
+    PathBuf::from(path)
+}
@@ -0,0 +1,7 @@
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    dotenvy::dotenv().ok();
+
+    fulcitt_pos_lib::run_admin()
+}
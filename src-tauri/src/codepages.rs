@@ -0,0 +1,153 @@
+use escpos::utils::PageCode;
+use serde::{Deserialize, Serialize};
+
+/// Code pages supported by the printer settings, named after the escpos ones.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CodePage {
+    PC437,
+    PC850,
+    PC858,
+    WPC1252,
+    ISO8859_15,
+}
+
+// Characters of bytes 0x80-0xFF, `\0` marks bytes left unused.
+const PC437: &str = concat!(
+    "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒ",
+    "áíóúñÑªº¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀",
+    "αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}",
+);
+const PC850: &str = concat!(
+    "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜø£Ø×ƒ",
+    "áíóúñÑªº¿®¬½¼¡«»░▒▓│┤ÁÂÀ©╣║╗╝¢¥┐",
+    "└┴┬├─┼ãÃ╚╔╩╦╠═╬¤ðÐÊËÈıÍÎÏ┘┌█▄¦Ì▀",
+    "ÓßÔÒõÕµþÞÚÛÙýÝ¯´\u{ad}±‗¾¶§÷¸°¨·¹³²■\u{a0}",
+);
+const PC858: &str = concat!(
+    "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜø£Ø×ƒ",
+    "áíóúñÑªº¿®¬½¼¡«»░▒▓│┤ÁÂÀ©╣║╗╝¢¥┐",
+    "└┴┬├─┼ãÃ╚╔╩╦╠═╬¤ðÐÊËÈ€ÍÎÏ┘┌█▄¦Ì▀",
+    "ÓßÔÒõÕµþÞÚÛÙýÝ¯´\u{ad}±‗¾¶§÷¸°¨·¹³²■\u{a0}",
+);
+const WPC1252: &str = concat!(
+    "€\0‚ƒ„…†‡ˆ‰Š‹Œ\0Ž\0\0‘’“”•–—˜™š›œ\0žŸ",
+    "\u{a0}¡¢£¤¥¦§¨©ª«¬\u{ad}®¯°±²³´µ¶·¸¹º»¼½¾¿",
+    "ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔÕÖ×ØÙÚÛÜÝÞß",
+    "àáâãäåæçèéêëìíîïðñòóôõö÷øùúûüýþÿ",
+);
+const ISO8859_15: &str = concat!(
+    "\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0",
+    "\u{a0}¡¢£€¥Š§š©ª«¬\u{ad}®¯°±²³Žµ¶·ž¹º»ŒœŸ¿",
+    "ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔÕÖ×ØÙÚÛÜÝÞß",
+    "àáâãäåæçèéêëìíîïðñòóôõö÷øùúûüýþÿ",
+);
+
+impl CodePage {
+    fn table(&self) -> &'static str {
+        match self {
+            CodePage::PC437 => PC437,
+            CodePage::PC850 => PC850,
+            CodePage::PC858 => PC858,
+            CodePage::WPC1252 => WPC1252,
+            CodePage::ISO8859_15 => ISO8859_15,
+        }
+    }
+
+    fn byte(&self, c: char) -> Option<u8> {
+        if c.is_ascii() {
+            return Some(c as u8);
+        }
+
+        self.table()
+            .chars()
+            .position(|t| t == c)
+            .map(|i| 0x80 + i as u8)
+    }
+
+    /// Characters printable with this code page, in byte order from 0x80.
+    pub(crate) fn charset(&self) -> impl Iterator<Item = (u8, char)> {
+        self.table()
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| *c != '\0' && !c.is_whitespace() && *c != '\u{ad}')
+            .map(|(i, c)| (0x80 + i as u8, c))
+    }
+}
+
+impl From<CodePage> for PageCode {
+    fn from(value: CodePage) -> Self {
+        match value {
+            CodePage::PC437 => PageCode::PC437,
+            CodePage::PC850 => PageCode::PC850,
+            CodePage::PC858 => PageCode::PC858,
+            CodePage::WPC1252 => PageCode::WPC1252,
+            CodePage::ISO8859_15 => PageCode::ISO8859_15,
+        }
+    }
+}
+
+/// Replaces the characters missing from the code page with the closest
+/// printable ones, so that every char of the result maps to a single byte.
+pub(crate) fn transcode(text: &str, code_page: CodePage) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if c.is_ascii_control() && c != '\t' {
+            continue;
+        }
+
+        if code_page.byte(c).is_some() {
+            result.push(c);
+            continue;
+        }
+
+        match fallback(c) {
+            Some(replacement) => {
+                for r in replacement.chars() {
+                    result.push(if code_page.byte(r).is_some() { r } else { '?' });
+                }
+            }
+            None => result.push('?'),
+        }
+    }
+
+    result
+}
+
+/// Encodes text previously passed through [`transcode`].
+pub(crate) fn encode(text: &str, code_page: CodePage) -> Vec<u8> {
+    text.chars()
+        .map(|c| code_page.byte(c).unwrap_or(b'?'))
+        .collect()
+}
+
+fn fallback(c: char) -> Option<&'static str> {
+    let replacement = match c {
+        '€' => "EUR",
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => "a",
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => "A",
+        'è' | 'é' | 'ê' | 'ë' => "e",
+        'È' | 'É' | 'Ê' | 'Ë' => "E",
+        'ì' | 'í' | 'î' | 'ï' => "i",
+        'Ì' | 'Í' | 'Î' | 'Ï' => "I",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' => "o",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' => "O",
+        'ù' | 'ú' | 'û' | 'ü' => "u",
+        'Ù' | 'Ú' | 'Û' | 'Ü' => "U",
+        'ç' => "c",
+        'Ç' => "C",
+        'ñ' => "n",
+        'Ñ' => "N",
+        '°' | 'º' => "o",
+        '‘' | '’' | '´' | '`' => "'",
+        '“' | '”' | '«' | '»' => "\"",
+        '–' | '—' => "-",
+        '…' => "...",
+        '\u{a0}' => " ",
+        _ => return None,
+    };
+
+    Some(replacement)
+}
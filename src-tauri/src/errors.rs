@@ -1,6 +1,8 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(thiserror::Error, Debug, Serialize)]
+use crate::status::PrinterStatus;
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
 #[serde(tag = "type", content = "message")]
 pub(crate) enum CommandError {
     #[error("Database error {0}")]
@@ -21,26 +23,53 @@ pub(crate) enum CommandError {
     #[error("Failed to load settings")]
     LoadSettings,
 
+    #[error("Cash drawer not configured")]
+    CashDrawerNotConfigured,
+
     #[error("Concurrency resource access error")]
     MutexPoison,
 
     #[error("Printer not configured")]
     PrinterNotConfigured,
 
+    #[error("Printer not ready {0:?}")]
+    PrinterNotReady(PrinterStatus),
+
+    #[error("PDF error {0}")]
+    Pdf(String),
+
     #[error("Printer error {0}")]
     Printer(String),
 
     #[error("Failed to save settings")]
     StoreSettings,
 
+    #[error("Server error {0}")]
+    Remote(String),
+
     #[error("Sale data not found")]
     SaleNotFound,
 
+    #[error("Database not available")]
+    DatabaseUnavailable,
+
+    #[error("Terminal not authorized")]
+    Unauthorized,
+
+    #[error("Operator not logged in")]
+    NotLoggedIn,
+
+    #[error("Operation not allowed to the operator")]
+    Forbidden,
+
     #[error("Usb error {0}")]
     Usb(String),
 
     #[error("XLSX error {0}")]
     Xlsx(String),
+
+    #[error("PIN hashing error {0}")]
+    PinHash(String),
 }
 
 pub(crate) type CommandResult<T> = std::result::Result<T, CommandError>;
@@ -69,6 +98,22 @@ impl From<rust_xlsxwriter::XlsxError> for CommandError {
     }
 }
 
+impl From<printpdf::Error> for CommandError {
+    fn from(err: printpdf::Error) -> Self {
+        log::error!("PDF generation error occurred {:?}", err);
+
+        CommandError::Pdf(err.to_string())
+    }
+}
+
+impl From<argon2::password_hash::Error> for CommandError {
+    fn from(err: argon2::password_hash::Error) -> Self {
+        log::error!("PIN hashing error occurred {:?}", err);
+
+        CommandError::PinHash(err.to_string())
+    }
+}
+
 impl From<serde_json::Error> for CommandError {
     fn from(err: serde_json::Error) -> Self {
         log::error!("Serializtion error occurred {:?}", err);
@@ -85,6 +130,15 @@ impl From<std::io::Error> for CommandError {
     }
 }
 
+#[cfg(feature = "gui")]
+impl From<tauri::Error> for CommandError {
+    fn from(err: tauri::Error) -> Self {
+        log::error!("Tauri error occurred {:?}", err);
+
+        CommandError::Io(err.to_string())
+    }
+}
+
 impl<D> From<std::sync::PoisonError<D>> for CommandError {
     fn from(err: std::sync::PoisonError<D>) -> Self {
         log::error!("Mutex poisoning error occurred {:?}", err);
@@ -100,3 +154,27 @@ impl From<sqlx::Error> for CommandError {
         CommandError::Database(err.to_string())
     }
 }
+
+impl From<reqwest::Error> for CommandError {
+    fn from(err: reqwest::Error) -> Self {
+        log::error!("Server request error occurred {:?}", err);
+
+        CommandError::Remote(err.to_string())
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for CommandError {
+    fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
+        log::error!("Server events error occurred {:?}", err);
+
+        CommandError::Remote(err.to_string())
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for CommandError {
+    fn from(err: sqlx::migrate::MigrateError) -> Self {
+        log::error!("SQLx migration error occurred {:?}", err);
+
+        CommandError::Database(err.to_string())
+    }
+}
@@ -1,93 +1,1220 @@
-use rust_xlsxwriter::{workbook::Workbook, worksheet::Worksheet, Format};
-use sqlx::{Pool, Sqlite};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
 
-use crate::{CommandResult, Sale, SaleItem};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+#[cfg(feature = "gui")]
+use fluent_bundle::FluentArgs;
+use rust_xlsxwriter::{workbook::Workbook, worksheet::Worksheet, Format, FormatBorder};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, QueryBuilder, Sqlite};
+use uuid::Uuid;
 
+#[cfg(feature = "gui")]
+use crate::{
+    audit::AuditEntry,
+    reports::{ProductTrend, SalesComparison},
+};
+use crate::{
+    reports::{
+        list_sales, sales_breakdown, BreakdownInterval, EventSettings, FilterScope, ReportFilter,
+    },
+    CommandError, CommandResult, Intl, Sale, UnsavedProduct,
+};
+
+/// Where the reports are saved, stored in the "export-settings" key.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ExportSettings {
+    /// Defaults to the documents folder
+    pub directory: Option<PathBuf>,
+    /// File name without extension, where `{event}`, `{date}` and `{time}`
+    /// are replaced with the event name and the export date and time
+    #[serde(default = "default_file_name_template")]
+    pub file_name_template: String,
+    /// Writes the CSV amounts as "12,50", as the Italian accounting software expects
+    #[serde(default = "default_csv_decimal_comma")]
+    pub csv_decimal_comma: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub(crate) enum ExportFormat {
+    Xlsx,
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// CSV files are written in a folder, one per table
+    #[cfg(feature = "gui")]
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Csv => "",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+fn default_csv_decimal_comma() -> bool {
+    true
+}
+
+fn default_file_name_template() -> String {
+    "{event}_{date}".to_string()
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            file_name_template: default_file_name_template(),
+            csv_decimal_comma: default_csv_decimal_comma(),
+        }
+    }
+}
+
+impl ExportSettings {
+    #[cfg(feature = "gui")]
+    pub(crate) fn file_name(&self, event_name: Option<&str>, now: NaiveDateTime) -> String {
+        let name = self
+            .file_name_template
+            .replace("{event}", event_name.unwrap_or("vendite"))
+            .replace("{date}", &now.format("%Y-%m-%d").to_string())
+            .replace("{time}", &now.format("%H%M%S").to_string());
+
+        let name: String = name
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect();
+        let name = name.trim().trim_matches('.');
+
+        if name.is_empty() {
+            "vendite".to_string()
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+/// Returns a path in the directory that doesn't exist yet, numbering the file
+/// name as "name (2).ext" when needed, so that no export is overwritten.
+/// An empty extension gives a folder name.
+#[cfg(feature = "gui")]
+pub(crate) fn unique_export_path(directory: &Path, file_name: &str, extension: &str) -> PathBuf {
+    let with_extension = |name: String| {
+        if extension.is_empty() {
+            name
+        } else {
+            format!("{}.{}", name, extension)
+        }
+    };
+
+    let mut path = directory.join(with_extension(file_name.to_string()));
+    let mut counter = 2;
+
+    while path.exists() {
+        path = directory.join(with_extension(format!("{} ({})", file_name, counter)));
+        counter += 1;
+    }
+
+    path
+}
+
+#[derive(FromRow)]
+struct ExportedSaleItem {
+    order_number: i64,
+    sale_id: Uuid,
+    sale_time: NaiveDateTime,
+    product_name: String,
+    category: Option<String>,
+    quantity: i64,
+    price_at_sale: f64,
+}
+
+/// Formats shared by the worksheets, read from the translations.
+struct ExportFormats {
+    header: Format,
+    total: Format,
+    currency: Format,
+    currency_total: Format,
+    date_time: Format,
+    #[cfg(feature = "gui")]
+    date: Format,
+}
+
+impl ExportFormats {
+    fn new(intl: &Intl) -> CommandResult<Self> {
+        let currency_format = intl.t("reports-export-xlsx-currency-format")?;
+        let date_time_format = intl.t("reports-export-xlsx-date-time-format")?;
+
+        Ok(Self {
+            header: Format::new().set_bold(),
+            total: Format::new().set_bold().set_border_top(FormatBorder::Thin),
+            currency: Format::new().set_num_format(currency_format.as_ref()),
+            currency_total: Format::new()
+                .set_bold()
+                .set_border_top(FormatBorder::Thin)
+                .set_num_format(currency_format.as_ref()),
+            date_time: Format::new().set_num_format(date_time_format.as_ref()),
+            #[cfg(feature = "gui")]
+            date: Format::new()
+                .set_num_format(intl.t("reports-export-xlsx-date-format")?.as_ref()),
+        })
+    }
+}
+
+/// Writes the translated headers, freezes them and adds the autofilter over
+/// the data rows.
+fn write_table_header(
+    worksheet: &mut Worksheet,
+    intl: &Intl,
+    formats: &ExportFormats,
+    headers: &[&str],
+    rows: u32,
+) -> CommandResult<()> {
+    let headers = headers
+        .iter()
+        .map(|header| intl.t(header).map(|header| header.to_string()))
+        .collect::<CommandResult<Vec<_>>>()?;
+
+    write_header_row(worksheet, formats, &headers, rows)
+}
+
+/// As `write_table_header`, with headers already translated.
+fn write_header_row(
+    worksheet: &mut Worksheet,
+    formats: &ExportFormats,
+    headers: &[String],
+    rows: u32,
+) -> CommandResult<()> {
+    for (column, header) in (0u16..).zip(headers.iter()) {
+        worksheet.write_with_format(0, column, header, &formats.header)?;
+    }
+
+    let last_column = headers.len().saturating_sub(1) as u16;
+    worksheet.set_freeze_panes(1, 0)?;
+    worksheet.autofilter(0, 0, rows.max(1), last_column)?;
+
+    Ok(())
+}
+
+/// Exports the sales matching the filter, voided ones included and marked,
+/// with the detail of the items of the valid sales and the hourly breakdown.
 pub(crate) async fn export_sales_report(
-    db: Pool<Sqlite>,
-    report_file_path: &str,
+    db: &Pool<Sqlite>,
+    settings: &EventSettings,
+    filter: &ReportFilter,
+    intl: &Intl,
+    report_file_path: &Path,
 ) -> CommandResult<()> {
-    let mut invoices_worksheet = Worksheet::new();
-    invoices_worksheet.set_name("reports-export-xslx-invoices-tab-title")?;
-    let mut products_worksheet = Worksheet::new();
-    products_worksheet.set_name("reports-export-xslx-invoices-details-tab-title")?;
+    let formats = ExportFormats::new(intl)?;
+
+    let sales_filter = ReportFilter {
+        include_voided: true,
+        ..filter.clone()
+    };
+    let sales = list_sales(db, &sales_filter, settings).await?;
 
-    let sales = sqlx::query_as!(
-        Sale,
+    let items_filter = ReportFilter {
+        include_voided: false,
+        ..filter.clone()
+    };
+    let mut query = QueryBuilder::new(
         r#"
-        SELECT id as "id: uuid::Uuid", sale_time, total_amount, payment_method
-        FROM sales
-        ORDER BY sale_time DESC
-        "#,
-    )
-        .fetch_all(&db)
+        SELECT s.order_number, si.sale_id, s.sale_time, si.product_name, p.category,
+            si.quantity, si.price_at_sale
+        FROM sale_items si
+        JOIN sales s ON s.id = si.sale_id
+        LEFT JOIN products p ON p.id = si.product_id
+        WHERE 1 = 1"#,
+    );
+    items_filter.push_conditions(&mut query, settings, FilterScope::Items);
+    query.push(" ORDER BY s.sale_time DESC, si.product_name");
+
+    let items = query
+        .build_query_as::<ExportedSaleItem>()
+        .fetch_all(db)
         .await?;
 
-    invoices_worksheet.write_row(0, 0, vec!["ID", "Data", "Metodo di pagamento", "Importo"])?;
-    products_worksheet.write_row(
-        0,
-        0,
-        vec![
-            "ID scontrino",
-            "Prodotto",
-            "Q.tà",
-            "Costo unitario",
-            "Totale",
+    let mut workbook = Workbook::new();
+    workbook.push_worksheet(invoices_worksheet(intl, &formats, &sales)?);
+    workbook.push_worksheet(details_worksheet(intl, &formats, &items)?);
+    workbook.push_worksheet(
+        hourly_breakdown_worksheet(db, settings, &items_filter, intl, &formats).await?,
+    );
+    workbook.save(report_file_path)?;
+
+    Ok(())
+}
+
+fn invoices_worksheet(
+    intl: &Intl,
+    formats: &ExportFormats,
+    sales: &[Sale],
+) -> CommandResult<Worksheet> {
+    let mut worksheet = Worksheet::new();
+    worksheet.set_name(intl.t("reports-export-xlsx-invoices-tab-title")?.as_ref())?;
+
+    let rows = sales.len() as u32;
+    write_table_header(
+        &mut worksheet,
+        intl,
+        formats,
+        &[
+            "reports-export-order-number",
+            "reports-export-sale-id",
+            "reports-export-sale-time",
+            "reports-export-payment-method",
+            "reports-export-discount",
+            "reports-export-amount",
+            "reports-export-voided-at",
         ],
+        rows,
     )?;
 
-    let currency_format = Format::new().set_num_format("#,##0.00 €");
+    for (row, sale) in (1u32..).zip(sales.iter()) {
+        let payment_method = match sale.payment_method.as_deref() {
+            Some("Cash") => intl.t("reports-payment-method-cash")?.to_string(),
+            Some("Card") => intl.t("reports-payment-method-card")?.to_string(),
+            other => other.unwrap_or_default().to_string(),
+        };
 
-    for (i, sale) in sales.into_iter().enumerate() {
-        let i: u32 = i.try_into().unwrap();
+        worksheet.write(row, 0, sale.order_number)?;
+        worksheet.write(row, 1, sale.id.to_string())?;
+        worksheet.write_datetime_with_format(row, 2, sale.sale_time, &formats.date_time)?;
+        worksheet.write(row, 3, payment_method)?;
+        worksheet.write_with_format(row, 4, sale.discount_amount, &formats.currency)?;
+        worksheet.write_with_format(row, 5, sale.total_amount, &formats.currency)?;
+        if let Some(voided_at) = sale.voided_at {
+            worksheet.write_datetime_with_format(row, 6, voided_at, &formats.date_time)?;
+        }
+    }
 
-        invoices_worksheet.write(i + 1, 0, sale.id.to_string())?;
-        invoices_worksheet.write(
-            i + 1,
-            1,
-            sale.sale_time.format("%Y-%M-%d %H:%m:%S").to_string(),
+    // Voided sales are left out of the totals
+    let total_row = rows + 1;
+    worksheet.write_with_format(total_row, 0, intl.t("reports-export-total")?.as_ref(), &formats.total)?;
+    for (column, letter) in [(4u16, "E"), (5u16, "F")] {
+        worksheet.write_formula_with_format(
+            total_row,
+            column,
+            format!("=SUMIFS({0}2:{0}{1},G2:G{1},\"\")", letter, rows + 1).as_str(),
+            &formats.currency_total,
         )?;
-        invoices_worksheet.write(i + 1, 3, sale.payment_method)?;
-        invoices_worksheet.write_with_format(i + 1, 2, sale.total_amount, &currency_format)?;
-
-        let item_sales = sqlx::query_as!(
-            SaleItem,
-            r#"
-            SELECT id as "id: uuid::Uuid",
-                sale_id as "sale_id: uuid::Uuid",
-                product_id as "product_id: uuid::Uuid",
-                product_name,
-                quantity,
-                price_at_sale
-            FROM sale_items
-            WHERE sale_id = ?
-            "#,
-            sale.id
-        )
-        .fetch_all(&db)
-        .await?;
+    }
 
-        let mut j = 1;
-        for item in item_sales.into_iter() {
-            products_worksheet.write(j, 0, item.sale_id.to_string())?;
-            products_worksheet.write(j, 1, item.product_name)?;
-            products_worksheet.write(j, 2, item.quantity)?;
-            products_worksheet.write_with_format(j, 3, item.price_at_sale, &currency_format)?;
-            products_worksheet.write_formula_with_format(
-                j,
-                4,
-                format!("=C{}*D{}", j + 1, j + 1).as_str(),
-                &currency_format,
-            )?;
+    worksheet.set_column_width(1, 38)?;
+    worksheet.set_column_width(2, 20)?;
+    worksheet.set_column_width(3, 20)?;
+    worksheet.set_column_width(4, 14)?;
+    worksheet.set_column_width(5, 14)?;
+    worksheet.set_column_width(6, 20)?;
 
-            j += 1;
+    Ok(worksheet)
+}
+
+fn details_worksheet(
+    intl: &Intl,
+    formats: &ExportFormats,
+    items: &[ExportedSaleItem],
+) -> CommandResult<Worksheet> {
+    let mut worksheet = Worksheet::new();
+    worksheet.set_name(intl.t("reports-export-xlsx-invoices-details-tab-title")?.as_ref())?;
+
+    let rows = items.len() as u32;
+    write_table_header(
+        &mut worksheet,
+        intl,
+        formats,
+        &[
+            "reports-export-order-number",
+            "reports-export-sale-id",
+            "reports-export-sale-time",
+            "reports-export-product-name",
+            "reports-export-category",
+            "reports-export-quantity",
+            "reports-export-xlsx-unit-cost",
+            "reports-export-amount",
+        ],
+        rows,
+    )?;
+
+    for (row, item) in (1u32..).zip(items.iter()) {
+        worksheet.write(row, 0, item.order_number)?;
+        worksheet.write(row, 1, item.sale_id.to_string())?;
+        worksheet.write_datetime_with_format(row, 2, item.sale_time, &formats.date_time)?;
+        worksheet.write(row, 3, &item.product_name)?;
+        worksheet.write(row, 4, item.category.as_deref().unwrap_or_default())?;
+        worksheet.write(row, 5, item.quantity)?;
+        worksheet.write_with_format(row, 6, item.price_at_sale, &formats.currency)?;
+        worksheet.write_formula_with_format(
+            row,
+            7,
+            format!("=F{0}*G{0}", row + 1).as_str(),
+            &formats.currency,
+        )?;
+    }
+
+    let total_row = rows + 1;
+    worksheet.write_with_format(total_row, 0, intl.t("reports-export-total")?.as_ref(), &formats.total)?;
+    worksheet.write_formula_with_format(
+        total_row,
+        5,
+        format!("=SUBTOTAL(9,F2:F{})", rows + 1).as_str(),
+        &formats.total,
+    )?;
+    worksheet.write_formula_with_format(
+        total_row,
+        7,
+        format!("=SUBTOTAL(9,H2:H{})", rows + 1).as_str(),
+        &formats.currency_total,
+    )?;
+
+    worksheet.set_column_width(1, 38)?;
+    worksheet.set_column_width(2, 20)?;
+    worksheet.set_column_width(3, 30)?;
+    worksheet.set_column_width(4, 16)?;
+    worksheet.set_column_width(6, 14)?;
+    worksheet.set_column_width(7, 14)?;
+
+    Ok(worksheet)
+}
+
+/// Sales per hour, with the items sold per category side by side.
+async fn hourly_breakdown_worksheet(
+    db: &Pool<Sqlite>,
+    settings: &EventSettings,
+    filter: &ReportFilter,
+    intl: &Intl,
+    formats: &ExportFormats,
+) -> CommandResult<Worksheet> {
+    let breakdown = sales_breakdown(db, filter, settings, BreakdownInterval::Hour).await?;
+
+    let mut worksheet = Worksheet::new();
+    worksheet.set_name(intl.t("reports-export-xlsx-breakdown-tab-title")?.as_ref())?;
+
+    let rows = breakdown.buckets.len() as u32;
+    write_table_header(
+        &mut worksheet,
+        intl,
+        formats,
+        &[
+            "reports-export-hour",
+            "reports-export-sales-count",
+            "reports-export-items-sold",
+            "reports-export-amount",
+        ],
+        rows,
+    )?;
+    for (column, series) in (4u16..).zip(breakdown.by_category.iter()) {
+        worksheet.write_with_format(0, column, &series.name, &formats.header)?;
+    }
+    let last_column = 3 + breakdown.by_category.len() as u16;
+    worksheet.autofilter(0, 0, rows.max(1), last_column)?;
+
+    for (row, bucket) in (1u32..).zip(breakdown.buckets.iter()) {
+        let i = (row - 1) as usize;
+
+        match NaiveDateTime::parse_from_str(bucket, "%Y-%m-%d %H:%M") {
+            Ok(hour) => worksheet.write_datetime_with_format(row, 0, hour, &formats.date_time)?,
+            Err(_) => worksheet.write(row, 0, bucket)?,
+        };
+        worksheet.write(row, 1, breakdown.sales_count[i])?;
+        worksheet.write(row, 2, breakdown.items_sold[i])?;
+        worksheet.write_with_format(row, 3, breakdown.total_value[i], &formats.currency)?;
+        for (column, series) in (4u16..).zip(breakdown.by_category.iter()) {
+            worksheet.write(row, column, series.quantity[i])?;
         }
     }
 
+    let total_row = rows + 1;
+    worksheet.write_with_format(total_row, 0, intl.t("reports-export-total")?.as_ref(), &formats.total)?;
+    for column in 1..=last_column {
+        let letter = column_letter(column);
+        let format = if column == 3 {
+            &formats.currency_total
+        } else {
+            &formats.total
+        };
+
+        worksheet.write_formula_with_format(
+            total_row,
+            column,
+            format!("=SUBTOTAL(9,{0}2:{0}{1})", letter, rows + 1).as_str(),
+            format,
+        )?;
+    }
+
+    worksheet.set_column_width(0, 20)?;
+    worksheet.set_column_width(3, 14)?;
+
+    Ok(worksheet)
+}
+
+fn column_letter(column: u16) -> String {
+    let mut column = column as u32 + 1;
+    let mut letters = String::new();
+
+    while column > 0 {
+        let remainder = (column - 1) % 26;
+        letters.insert(0, (b'A' + remainder as u8) as char);
+        column = (column - 1) / 26;
+    }
+
+    letters
+}
+
+/// Exports the comparison of the periods: their totals, the products side by
+/// side with the change from the previous period, and the days lined up.
+#[cfg(feature = "gui")]
+pub(crate) fn export_comparison_report(
+    comparison: &SalesComparison,
+    intl: &Intl,
+    report_file_path: &Path,
+) -> CommandResult<()> {
+    let formats = ExportFormats::new(intl)?;
+
     let mut workbook = Workbook::new();
-    workbook.push_worksheet(invoices_worksheet);
-    workbook.push_worksheet(products_worksheet);
+    workbook.push_worksheet(comparison_periods_worksheet(intl, &formats, comparison)?);
+    workbook.push_worksheet(comparison_products_worksheet(intl, &formats, comparison)?);
+    workbook.push_worksheet(comparison_days_worksheet(intl, &formats, comparison)?);
     workbook.save(report_file_path)?;
 
     Ok(())
 }
+
+#[cfg(feature = "gui")]
+fn comparison_periods_worksheet(
+    intl: &Intl,
+    formats: &ExportFormats,
+    comparison: &SalesComparison,
+) -> CommandResult<Worksheet> {
+    let mut worksheet = Worksheet::new();
+    worksheet.set_name(intl.t("reports-comparison-xlsx-periods-tab-title")?.as_ref())?;
+
+    write_table_header(
+        &mut worksheet,
+        intl,
+        formats,
+        &[
+            "reports-comparison-period",
+            "reports-comparison-from",
+            "reports-comparison-to",
+            "reports-export-sales-count",
+            "reports-export-items-sold",
+            "reports-export-total",
+            "reports-summary-average-ticket",
+        ],
+        comparison.periods.len() as u32,
+    )?;
+
+    for (row, period) in (1u32..).zip(comparison.periods.iter()) {
+        worksheet.write(row, 0, &period.label)?;
+        if let Some(from) = period.from {
+            worksheet.write_datetime_with_format(row, 1, from, &formats.date_time)?;
+        }
+        if let Some(to) = period.to {
+            worksheet.write_datetime_with_format(row, 2, to, &formats.date_time)?;
+        }
+        worksheet.write(row, 3, period.sales_count)?;
+        worksheet.write(row, 4, period.items_sold)?;
+        worksheet.write_with_format(row, 5, period.net_total, &formats.currency)?;
+        worksheet.write_with_format(row, 6, period.average_ticket, &formats.currency)?;
+    }
+
+    worksheet.set_column_width(0, 24)?;
+    for column in 1..=6 {
+        worksheet.set_column_width(column, 18)?;
+    }
+
+    Ok(worksheet)
+}
+
+#[cfg(feature = "gui")]
+fn comparison_products_worksheet(
+    intl: &Intl,
+    formats: &ExportFormats,
+    comparison: &SalesComparison,
+) -> CommandResult<Worksheet> {
+    let mut worksheet = Worksheet::new();
+    worksheet.set_name(intl.t("reports-comparison-xlsx-products-tab-title")?.as_ref())?;
+
+    // Quantity and value for each period, then their change from the previous one
+    let mut headers = vec![
+        intl.t("reports-export-product-name")?.to_string(),
+        intl.t("reports-export-category")?.to_string(),
+        intl.t("reports-comparison-trend")?.to_string(),
+    ];
+    for (i, period) in comparison.periods.iter().enumerate() {
+        let mut args = FluentArgs::new();
+        args.set("period", period.label.clone());
+        headers.push(intl.t_args("reports-comparison-quantity", Some(&args))?.to_string());
+        headers.push(intl.t_args("reports-comparison-value", Some(&args))?.to_string());
+        if i > 0 {
+            headers.push(intl.t_args("reports-comparison-quantity-delta", Some(&args))?.to_string());
+            headers.push(intl.t_args("reports-comparison-value-delta", Some(&args))?.to_string());
+        }
+    }
+
+    let rows = comparison.products.len() as u32;
+    write_header_row(&mut worksheet, formats, &headers, rows)?;
+
+    for (row, product) in (1u32..).zip(comparison.products.iter()) {
+        let trend = match product.trend {
+            ProductTrend::Kept => "",
+            ProductTrend::New => "reports-comparison-trend-new",
+            ProductTrend::Discontinued => "reports-comparison-trend-discontinued",
+        };
+
+        worksheet.write(row, 0, &product.product_name)?;
+        worksheet.write(row, 1, &product.category)?;
+        if !trend.is_empty() {
+            worksheet.write(row, 2, intl.t(trend)?.as_ref())?;
+        }
+
+        let mut column = 3u16;
+        for i in 0..comparison.periods.len() {
+            worksheet.write(row, column, product.quantity[i])?;
+            worksheet.write_with_format(row, column + 1, product.value[i], &formats.currency)?;
+            column += 2;
+            if i > 0 {
+                worksheet.write(row, column, product.quantity_delta[i])?;
+                worksheet.write_with_format(
+                    row,
+                    column + 1,
+                    product.value_delta[i],
+                    &formats.currency,
+                )?;
+                column += 2;
+            }
+        }
+    }
+
+    let total_row = rows + 1;
+    worksheet.write_with_format(total_row, 0, intl.t("reports-export-total")?.as_ref(), &formats.total)?;
+    for column in 3..headers.len() as u16 {
+        let letter = column_letter(column);
+        // Values and their deltas alternate in pairs after the quantity
+        let format = if (column - 3) % 2 == 1 {
+            &formats.currency_total
+        } else {
+            &formats.total
+        };
+        worksheet.write_formula_with_format(
+            total_row,
+            column,
+            format!("=SUBTOTAL(9,{letter}2:{letter}{})", rows + 1).as_str(),
+            format,
+        )?;
+    }
+
+    worksheet.set_column_width(0, 30)?;
+    worksheet.set_column_width(1, 20)?;
+    for column in 2..headers.len() as u16 {
+        worksheet.set_column_width(column, 16)?;
+    }
+
+    Ok(worksheet)
+}
+
+#[cfg(feature = "gui")]
+fn comparison_days_worksheet(
+    intl: &Intl,
+    formats: &ExportFormats,
+    comparison: &SalesComparison,
+) -> CommandResult<Worksheet> {
+    let mut worksheet = Worksheet::new();
+    worksheet.set_name(intl.t("reports-comparison-xlsx-days-tab-title")?.as_ref())?;
+
+    let mut headers = vec![intl.t("reports-comparison-day-number")?.to_string()];
+    for period in &comparison.periods {
+        let mut args = FluentArgs::new();
+        args.set("period", period.label.clone());
+        headers.push(intl.t_args("reports-comparison-day", Some(&args))?.to_string());
+        headers.push(intl.t_args("reports-comparison-sales-count", Some(&args))?.to_string());
+        headers.push(intl.t_args("reports-comparison-value", Some(&args))?.to_string());
+    }
+
+    let rows = comparison.days.len() as u32;
+    write_header_row(&mut worksheet, formats, &headers, rows)?;
+
+    for (row, day) in (1u32..).zip(comparison.days.iter()) {
+        worksheet.write(row, 0, day.day_number as u32)?;
+
+        for i in 0..comparison.periods.len() {
+            let column = 1 + 3 * i as u16;
+            if let Some(date) = day.days[i] {
+                worksheet.write_date_with_format(row, column, date, &formats.date)?;
+            }
+            worksheet.write(row, column + 1, day.sales_count[i])?;
+            worksheet.write_with_format(row, column + 2, day.total_value[i], &formats.currency)?;
+        }
+    }
+
+    let total_row = rows + 1;
+    worksheet.write_with_format(total_row, 0, intl.t("reports-export-total")?.as_ref(), &formats.total)?;
+    for i in 0..comparison.periods.len() as u16 {
+        for (column, format) in [
+            (2 + 3 * i, &formats.total),
+            (3 + 3 * i, &formats.currency_total),
+        ] {
+            let letter = column_letter(column);
+            worksheet.write_formula_with_format(
+                total_row,
+                column,
+                format!("=SUBTOTAL(9,{letter}2:{letter}{})", rows + 1).as_str(),
+                format,
+            )?;
+        }
+    }
+
+    for column in 0..headers.len() as u16 {
+        worksheet.set_column_width(column, 16)?;
+    }
+
+    Ok(worksheet)
+}
+
+/// Version of the CSV and JSON layout documented in EXPORT-SCHEMA.md. Bump it
+/// on any change that could break the import scripts: renamed or removed
+/// fields, different formats. New fields at the end don't require it.
+pub(crate) const ACCOUNTING_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Debug, FromRow)]
+pub(crate) struct AccountingSale {
+    pub sale_id: Uuid,
+    pub order_number: i64,
+    pub sale_time: NaiveDateTime,
+    #[sqlx(skip)]
+    pub business_day: NaiveDate,
+    pub event: Option<String>,
+    pub shift: Option<String>,
+    pub pos_name: Option<String>,
+    pub payment_method: Option<String>,
+    #[sqlx(skip)]
+    pub gross_amount: f64,
+    pub discount_amount: f64,
+    pub total_amount: f64,
+    pub voided_at: Option<NaiveDateTime>,
+    pub void_reason: Option<String>,
+    pub operator_id: Option<Uuid>,
+    pub operator_name: Option<String>,
+}
+
+#[derive(Serialize, Debug, FromRow)]
+pub(crate) struct AccountingSaleItem {
+    pub item_id: Uuid,
+    pub sale_id: Uuid,
+    pub order_number: i64,
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub category: Option<String>,
+    pub quantity: i64,
+    pub unit_price: f64,
+    pub total_amount: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct AccountingPayment {
+    pub sale_id: Uuid,
+    pub order_number: i64,
+    pub payment_time: NaiveDateTime,
+    pub business_day: NaiveDate,
+    pub payment_method: String,
+    pub amount: f64,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct AccountingDailyTotal {
+    pub business_day: NaiveDate,
+    pub sales_count: i64,
+    pub items_sold: i64,
+    pub gross_amount: f64,
+    pub discount_amount: f64,
+    pub total_amount: f64,
+    pub cash_amount: f64,
+    pub card_amount: f64,
+    pub voids_count: i64,
+    pub voids_amount: f64,
+}
+
+/// Everything the treasurer imports, with the layout of EXPORT-SCHEMA.md.
+#[derive(Serialize, Debug)]
+pub(crate) struct AccountingExport {
+    pub schema_version: u32,
+    pub generated_at: NaiveDateTime,
+    pub sales: Vec<AccountingSale>,
+    pub sale_items: Vec<AccountingSaleItem>,
+    pub payments: Vec<AccountingPayment>,
+    pub daily_totals: Vec<AccountingDailyTotal>,
+}
+
+/// Loads the sales matching the filter, voided ones included, with their
+/// items and the payments and daily totals of the valid sales.
+pub(crate) async fn load_accounting_export(
+    db: &Pool<Sqlite>,
+    filter: &ReportFilter,
+    settings: &EventSettings,
+) -> CommandResult<AccountingExport> {
+    let filter = ReportFilter {
+        include_voided: true,
+        ..filter.clone()
+    };
+
+    let mut query = QueryBuilder::new(
+        r#"
+        SELECT s.id AS sale_id, s.order_number, s.sale_time, s.event_name AS event,
+            s.shift_name AS shift, s.pos_name, s.payment_method, s.discount_amount,
+            s.total_amount, s.voided_at, s.void_reason, s.operator_id, s.operator_name
+        FROM sales s
+        WHERE 1 = 1"#,
+    );
+    filter.push_conditions(&mut query, settings, FilterScope::Sales);
+    query.push(" ORDER BY s.sale_time, s.order_number");
+
+    let mut sales = query
+        .build_query_as::<AccountingSale>()
+        .fetch_all(db)
+        .await?;
+    for sale in sales.iter_mut() {
+        sale.business_day = settings.business_day_of(sale.sale_time);
+        sale.gross_amount = sale.total_amount + sale.discount_amount;
+    }
+
+    let mut query = QueryBuilder::new(
+        r#"
+        SELECT si.id AS item_id, si.sale_id, s.order_number, si.product_id, si.product_name,
+            p.category, si.quantity, si.price_at_sale AS unit_price,
+            si.quantity * si.price_at_sale AS total_amount
+        FROM sale_items si
+        JOIN sales s ON s.id = si.sale_id
+        LEFT JOIN products p ON p.id = si.product_id
+        WHERE 1 = 1"#,
+    );
+    filter.push_conditions(&mut query, settings, FilterScope::Sales);
+    query.push(" ORDER BY s.sale_time, s.order_number, si.product_name");
+
+    let sale_items = query
+        .build_query_as::<AccountingSaleItem>()
+        .fetch_all(db)
+        .await?;
+
+    let payments = sales
+        .iter()
+        .filter(|sale| sale.voided_at.is_none())
+        .map(|sale| AccountingPayment {
+            sale_id: sale.sale_id,
+            order_number: sale.order_number,
+            payment_time: sale.sale_time,
+            business_day: sale.business_day,
+            payment_method: sale.payment_method.clone().unwrap_or_default(),
+            amount: sale.total_amount,
+        })
+        .collect();
+
+    let mut items_per_sale: BTreeMap<Uuid, i64> = BTreeMap::new();
+    for item in &sale_items {
+        *items_per_sale.entry(item.sale_id).or_default() += item.quantity;
+    }
+
+    let mut daily_totals: BTreeMap<NaiveDate, AccountingDailyTotal> = BTreeMap::new();
+    for sale in &sales {
+        let total = daily_totals
+            .entry(sale.business_day)
+            .or_insert_with(|| AccountingDailyTotal {
+                business_day: sale.business_day,
+                ..Default::default()
+            });
+
+        if sale.voided_at.is_some() {
+            total.voids_count += 1;
+            total.voids_amount += sale.total_amount;
+            continue;
+        }
+
+        total.sales_count += 1;
+        total.items_sold += items_per_sale.get(&sale.sale_id).copied().unwrap_or_default();
+        total.gross_amount += sale.gross_amount;
+        total.discount_amount += sale.discount_amount;
+        total.total_amount += sale.total_amount;
+        match sale.payment_method.as_deref() {
+            Some("Card") => total.card_amount += sale.total_amount,
+            _ => total.cash_amount += sale.total_amount,
+        }
+    }
+
+    Ok(AccountingExport {
+        schema_version: ACCOUNTING_SCHEMA_VERSION,
+        generated_at: Local::now().naive_local(),
+        sales,
+        sale_items,
+        payments,
+        daily_totals: daily_totals.into_values().collect(),
+    })
+}
+
+pub(crate) fn write_accounting_json(export: &AccountingExport, path: &Path) -> CommandResult<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(writer, export)?;
+
+    Ok(())
+}
+
+/// Writes one CSV file per table in the directory, separated by semicolons.
+pub(crate) fn write_accounting_csv(
+    export: &AccountingExport,
+    directory: &Path,
+    decimal_comma: bool,
+) -> CommandResult<()> {
+    std::fs::create_dir_all(directory)?;
+
+    let csv = CsvFormat { decimal_comma };
+
+    csv.write_file(
+        &directory.join("sales.csv"),
+        &[
+            "sale_id",
+            "order_number",
+            "sale_time",
+            "business_day",
+            "event",
+            "shift",
+            "pos_name",
+            "payment_method",
+            "gross_amount",
+            "discount_amount",
+            "total_amount",
+            "voided_at",
+            "void_reason",
+            "operator_id",
+            "operator_name",
+        ],
+        export.sales.iter().map(|sale| {
+            vec![
+                sale.sale_id.to_string(),
+                sale.order_number.to_string(),
+                csv.date_time(Some(sale.sale_time)),
+                sale.business_day.to_string(),
+                sale.event.clone().unwrap_or_default(),
+                sale.shift.clone().unwrap_or_default(),
+                sale.pos_name.clone().unwrap_or_default(),
+                sale.payment_method.clone().unwrap_or_default(),
+                csv.amount(sale.gross_amount),
+                csv.amount(sale.discount_amount),
+                csv.amount(sale.total_amount),
+                csv.date_time(sale.voided_at),
+                sale.void_reason.clone().unwrap_or_default(),
+                sale.operator_id.map(|id| id.to_string()).unwrap_or_default(),
+                sale.operator_name.clone().unwrap_or_default(),
+            ]
+        }),
+    )?;
+
+    csv.write_file(
+        &directory.join("sale_items.csv"),
+        &[
+            "item_id",
+            "sale_id",
+            "order_number",
+            "product_id",
+            "product_name",
+            "category",
+            "quantity",
+            "unit_price",
+            "total_amount",
+        ],
+        export.sale_items.iter().map(|item| {
+            vec![
+                item.item_id.to_string(),
+                item.sale_id.to_string(),
+                item.order_number.to_string(),
+                item.product_id.to_string(),
+                item.product_name.clone(),
+                item.category.clone().unwrap_or_default(),
+                item.quantity.to_string(),
+                csv.amount(item.unit_price),
+                csv.amount(item.total_amount),
+            ]
+        }),
+    )?;
+
+    csv.write_file(
+        &directory.join("payments.csv"),
+        &[
+            "sale_id",
+            "order_number",
+            "payment_time",
+            "business_day",
+            "payment_method",
+            "amount",
+        ],
+        export.payments.iter().map(|payment| {
+            vec![
+                payment.sale_id.to_string(),
+                payment.order_number.to_string(),
+                csv.date_time(Some(payment.payment_time)),
+                payment.business_day.to_string(),
+                payment.payment_method.clone(),
+                csv.amount(payment.amount),
+            ]
+        }),
+    )?;
+
+    csv.write_file(
+        &directory.join("daily_totals.csv"),
+        &[
+            "business_day",
+            "sales_count",
+            "items_sold",
+            "gross_amount",
+            "discount_amount",
+            "total_amount",
+            "cash_amount",
+            "card_amount",
+            "voids_count",
+            "voids_amount",
+        ],
+        export.daily_totals.iter().map(|total| {
+            vec![
+                total.business_day.to_string(),
+                total.sales_count.to_string(),
+                total.items_sold.to_string(),
+                csv.amount(total.gross_amount),
+                csv.amount(total.discount_amount),
+                csv.amount(total.total_amount),
+                csv.amount(total.cash_amount),
+                csv.amount(total.card_amount),
+                total.voids_count.to_string(),
+                csv.amount(total.voids_amount),
+            ]
+        }),
+    )?;
+
+    Ok(())
+}
+
+/// Columns of the catalog CSV files.
+const CATALOG_CSV_HEADER: [&str; 4] = ["name", "category", "price", "ticket_copies"];
+
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"))
+}
+
+/// Writes the products to a CSV file separated by semicolons when the path
+/// ends with `.csv`, to a JSON file otherwise.
+pub(crate) fn write_catalog(
+    products: &[UnsavedProduct],
+    path: &Path,
+    decimal_comma: bool,
+) -> CommandResult<()> {
+    if !is_csv(path) {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, products)?;
+
+        return Ok(());
+    }
+
+    let csv = CsvFormat { decimal_comma };
+
+    csv.write_file(
+        path,
+        &CATALOG_CSV_HEADER,
+        products.iter().map(|product| {
+            vec![
+                product.name.clone(),
+                product.category.clone(),
+                csv.amount(product.price),
+                product
+                    .ticket_copies
+                    .map(|copies| copies.to_string())
+                    .unwrap_or_default(),
+            ]
+        }),
+    )
+}
+
+/// Reads the products of a file written by `write_catalog`, or edited by
+/// hand, CSV prices can have either a decimal comma or point.
+pub(crate) fn read_catalog(path: &Path) -> CommandResult<Vec<UnsavedProduct>> {
+    if !is_csv(path) {
+        return Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?);
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    let mut lines = (1..).zip(content.lines()).filter(|(_, line)| !line.trim().is_empty());
+
+    let header = lines.next().map(|(_, line)| CsvFormat::parse_line(line));
+    if header.as_deref() != Some(CATALOG_CSV_HEADER.map(String::from).as_slice()) {
+        return Err(CommandError::InvalidInput(format!(
+            "The first line must be the header {}",
+            CATALOG_CSV_HEADER.join(";")
+        )));
+    }
+
+    let mut products = Vec::new();
+    for (number, line) in lines {
+        let invalid = |what: &str| CommandError::InvalidInput(format!("Line {}: {}", number, what));
+
+        let [name, category, price, ticket_copies] = CsvFormat::parse_line(line)
+            .try_into()
+            .map_err(|_| invalid("expected 4 fields"))?;
+
+        if name.trim().is_empty() {
+            return Err(invalid("missing name"));
+        }
+        let price = price
+            .trim()
+            .replace(',', ".")
+            .parse::<f64>()
+            .map_err(|_| invalid("invalid price"))?;
+        let ticket_copies = match ticket_copies.trim() {
+            "" => None,
+            copies => Some(
+                copies
+                    .parse::<i64>()
+                    .map_err(|_| invalid("invalid ticket copies"))?,
+            ),
+        };
+
+        products.push(UnsavedProduct {
+            name: name.trim().to_string(),
+            category: category.trim().to_string(),
+            price,
+            ticket_copies,
+        });
+    }
+
+    Ok(products)
+}
+
+/// Writes the entries of the audit log as JSON, or as CSV with the values
+/// before and after the change as JSON fields.
+#[cfg(feature = "gui")]
+pub(crate) fn write_audit_log(
+    entries: &[AuditEntry],
+    path: &Path,
+    format: ExportFormat,
+) -> CommandResult<()> {
+    if format == ExportFormat::Json {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, entries)?;
+
+        return Ok(());
+    }
+
+    let csv = CsvFormat {
+        decimal_comma: false,
+    };
+    let json = |value: &Option<serde_json::Value>| {
+        value
+            .as_ref()
+            .map(|value| value.to_string())
+            .unwrap_or_default()
+    };
+
+    csv.write_file(
+        path,
+        &[
+            "id",
+            "recorded_at",
+            "action",
+            "entity_id",
+            "actor",
+            "operator_id",
+            "pos_name",
+            "before",
+            "after",
+        ],
+        entries.iter().map(|entry| {
+            vec![
+                entry.id.to_string(),
+                csv.date_time(Some(entry.recorded_at)),
+                format!("{:?}", entry.action),
+                entry.entity_id.clone().unwrap_or_default(),
+                entry.actor.clone().unwrap_or_default(),
+                entry
+                    .operator_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                entry.pos_name.clone().unwrap_or_default(),
+                json(&entry.before),
+                json(&entry.after),
+            ]
+        }),
+    )
+}
+
+struct CsvFormat {
+    decimal_comma: bool,
+}
+
+impl CsvFormat {
+    fn amount(&self, amount: f64) -> String {
+        let amount = format!("{:.2}", amount);
+
+        if self.decimal_comma {
+            amount.replace('.', ",")
+        } else {
+            amount
+        }
+    }
+
+    fn date_time(&self, time: Option<NaiveDateTime>) -> String {
+        time.map(|time| time.format("%Y-%m-%dT%H:%M:%S").to_string())
+            .unwrap_or_default()
+    }
+
+    fn field(value: &str) -> String {
+        if value.contains([';', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// Splits a line written by `write_file`, unquoting the fields.
+    fn parse_line(line: &str) -> Vec<String> {
+        let mut fields = vec![String::new()];
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let field = fields.last_mut().expect("There is always a field");
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = !quoted,
+                ';' if !quoted => fields.push(String::new()),
+                c => field.push(c),
+            }
+        }
+
+        fields
+    }
+
+    fn write_file<I>(&self, path: &Path, header: &[&str], rows: I) -> CommandResult<()>
+    where
+        I: Iterator<Item = Vec<String>>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writeln!(writer, "{}", header.join(";"))?;
+        for row in rows {
+            let fields: Vec<String> = row.iter().map(|value| Self::field(value)).collect();
+            writeln!(writer, "{}", fields.join(";"))?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
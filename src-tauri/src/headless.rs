@@ -0,0 +1,195 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+use chrono::Local;
+use clap::Parser;
+use log::{error, info, LevelFilter};
+use serde::Serialize;
+use serde_json::Value;
+use unic_langid::langid;
+
+use crate::{
+    backup::BackupReason,
+    backup_app_database,
+    host::{FileSettings, Host, SettingsStore},
+    load_backup_settings, load_terminal_settings, run_scheduled_backups,
+    server::{self, ServerEvents},
+    setup_db, setup_printer_state, sync,
+    terminal::TerminalMode,
+    watch_printer_status, CommandError, CommandResult, Db, Intl, PrinterMonitorState, PrinterState,
+};
+
+/// Serves the API of the cash desk without the user interface, e.g. on an
+/// always-on box connected to the printers.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+    /// JSON file of the settings, with the same keys as the `store.json` of
+    /// the application
+    #[arg(long, env = "FULCITT_POS_SETTINGS", default_value = "settings.json")]
+    settings: PathBuf,
+
+    /// Directory of the database and of the default backups, the one of the
+    /// settings file when not given
+    #[arg(long, env = "FULCITT_POS_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Logs the debug messages too
+    #[arg(long)]
+    verbose: bool,
+}
+
+/// Host of the commands when running without Tauri, the settings come from a
+/// file and the events only go to the client terminals.
+#[derive(Clone)]
+struct HeadlessHost(Arc<HeadlessState>);
+
+struct HeadlessState {
+    settings: FileSettings,
+    data_dir: PathBuf,
+    /// Set once the database is open and migrated
+    db: OnceLock<Db>,
+    intl: Intl,
+    printer: PrinterState,
+    printer_monitor: PrinterMonitorState,
+    events: ServerEvents,
+}
+
+impl SettingsStore for HeadlessHost {
+    fn get_setting(&self, key: &str) -> CommandResult<Option<Value>> {
+        self.0.settings.get_setting(key)
+    }
+
+    #[cfg(feature = "gui")]
+    fn set_setting(&self, key: &str, value: Value) -> CommandResult<()> {
+        self.0.settings.set_setting(key, value)
+    }
+}
+
+impl Host for HeadlessHost {
+    fn db(&self) -> CommandResult<Db> {
+        self.0
+            .db
+            .get()
+            .cloned()
+            .ok_or(CommandError::DatabaseUnavailable)
+    }
+
+    fn intl(&self) -> &Intl {
+        &self.0.intl
+    }
+
+    fn printer(&self) -> &PrinterState {
+        &self.0.printer
+    }
+
+    fn printer_monitor(&self) -> &PrinterMonitorState {
+        &self.0.printer_monitor
+    }
+
+    fn data_dir(&self) -> CommandResult<PathBuf> {
+        Ok(self.0.data_dir.clone())
+    }
+
+    fn emit_event<T: Serialize + Clone>(&self, _event: &str, _payload: &T) {}
+
+    fn server_events(&self) -> Option<&ServerEvents> {
+        Some(&self.0.events)
+    }
+}
+
+/// Starts the headless server, returning only when it fails to start or it
+/// is stopped with Ctrl+C.
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    setup_logging(if args.verbose {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Info
+    })?;
+
+    let data_dir = match args.data_dir {
+        Some(data_dir) => data_dir,
+        None => std::path::absolute(&args.settings)?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+    };
+    info!("Using the data directory {:?}", data_dir);
+
+    let settings = FileSettings::open(&args.settings)?;
+    let (printer, printer_monitor) = setup_printer_state(&settings);
+
+    let host = HeadlessHost(Arc::new(HeadlessState {
+        settings,
+        data_dir,
+        db: OnceLock::new(),
+        intl: Intl::try_new(langid!("it"))?,
+        printer,
+        printer_monitor,
+        events: ServerEvents::default(),
+    }));
+
+    tokio::runtime::Runtime::new()?.block_on(serve(host))
+}
+
+/// Logs to the standard output, as the binaries without Tauri have no log plugin.
+pub(crate) fn setup_logging(level: LevelFilter) -> Result<(), Box<dyn Error>> {
+    fern::Dispatch::new()
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "[{}][{}][{}] {}",
+                Local::now().format("%Y-%m-%d %H:%M:%S"),
+                record.level(),
+                record.target(),
+                message
+            ))
+        })
+        .level(level)
+        .chain(std::io::stdout())
+        .apply()?;
+
+    Ok(())
+}
+
+async fn serve(host: HeadlessHost) -> Result<(), Box<dyn Error>> {
+    let settings = load_terminal_settings(&host)?;
+    if settings.mode == TerminalMode::Client {
+        return Err("A client terminal has no database to serve, change the terminal mode".into());
+    }
+    let token = server::api_token(&settings)
+        .ok_or("The API token of the terminal settings is not configured")?;
+
+    let db = setup_db(&host).await?;
+    host.0.db.get_or_init(|| db);
+
+    let printer_host = host.clone();
+    std::thread::spawn(move || watch_printer_status(printer_host));
+    tokio::spawn(run_scheduled_backups(host.clone()));
+    if settings.mode == TerminalMode::Peer {
+        tokio::spawn(sync::watch_peers(host.clone()));
+    }
+
+    tokio::select! {
+        served = server::listen(host.clone(), token, settings.server_port) => served?,
+        stopped = tokio::signal::ctrl_c() => {
+            stopped?;
+            info!("Stopping the headless server");
+        }
+    }
+
+    match load_backup_settings(&host) {
+        Ok(settings) if !settings.on_close => {}
+        Ok(_) => {
+            if let Err(err) = backup_app_database(&host, BackupReason::Close).await {
+                error!("Backup on close failed {:?}", err);
+            }
+        }
+        Err(err) => error!("Failed to load the backup settings {:?}", err),
+    }
+
+    Ok(())
+}
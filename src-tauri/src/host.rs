@@ -0,0 +1,163 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use log::info;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+#[cfg(feature = "gui")]
+use tauri::{AppHandle, Emitter, Manager};
+#[cfg(feature = "gui")]
+use tauri_plugin_store::StoreExt;
+
+#[cfg(feature = "gui")]
+use crate::app::AppState;
+use crate::{
+    server::ServerEvents, CommandError, CommandResult, Db, Intl, PrinterMonitorState, PrinterState,
+};
+
+/// Settings saved as JSON values, one key per settings group.
+pub(crate) trait SettingsStore {
+    fn get_setting(&self, key: &str) -> CommandResult<Option<Value>>;
+
+    #[cfg(feature = "gui")]
+    fn set_setting(&self, key: &str, value: Value) -> CommandResult<()>;
+}
+
+/// Reads the settings of the key, or their defaults when never saved.
+pub(crate) fn load_settings<T: DeserializeOwned + Default>(
+    store: &impl SettingsStore,
+    key: &str,
+) -> CommandResult<T> {
+    match store.get_setting(key)? {
+        Some(value) => serde_json::from_value::<T>(value).map_err(Into::<CommandError>::into),
+        None => Ok(T::default()),
+    }
+}
+
+/// Application running the commands, either the Tauri application or the
+/// headless server, so that both share the same implementations.
+pub(crate) trait Host: SettingsStore + Clone + Send + Sync + 'static {
+    fn db(&self) -> CommandResult<Db>;
+
+    fn intl(&self) -> &Intl;
+
+    fn printer(&self) -> &PrinterState;
+
+    fn printer_monitor(&self) -> &PrinterMonitorState;
+
+    /// Directory of the database and of the default backups.
+    fn data_dir(&self) -> CommandResult<PathBuf>;
+
+    /// Emits the event to the screens of this application, if it has any.
+    fn emit_event<T: Serialize + Clone>(&self, event: &str, payload: &T);
+
+    /// Events sent to the client terminals, while the API is served.
+    fn server_events(&self) -> Option<&ServerEvents>;
+}
+
+#[cfg(feature = "gui")]
+impl SettingsStore for AppHandle {
+    fn get_setting(&self, key: &str) -> CommandResult<Option<Value>> {
+        let store = self
+            .get_store("store.json")
+            .ok_or(CommandError::LoadSettings)?;
+
+        Ok(store.get(key))
+    }
+
+    fn set_setting(&self, key: &str, value: Value) -> CommandResult<()> {
+        let store = self
+            .get_store("store.json")
+            .ok_or(CommandError::StoreSettings)?;
+
+        store.set(key, value);
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "gui")]
+impl Host for AppHandle {
+    fn db(&self) -> CommandResult<Db> {
+        self.try_state::<AppState>()
+            .map(|app_state| app_state.db.clone())
+            .ok_or(CommandError::DatabaseUnavailable)
+    }
+
+    fn intl(&self) -> &Intl {
+        self.state::<Intl>().inner()
+    }
+
+    fn printer(&self) -> &PrinterState {
+        self.state::<PrinterState>().inner()
+    }
+
+    fn printer_monitor(&self) -> &PrinterMonitorState {
+        self.state::<PrinterMonitorState>().inner()
+    }
+
+    fn data_dir(&self) -> CommandResult<PathBuf> {
+        Ok(self.path().app_data_dir()?)
+    }
+
+    fn emit_event<T: Serialize + Clone>(&self, event: &str, payload: &T) {
+        if let Err(err) = self.emit(event, payload) {
+            log::error!("Failed to emit {} {:?}", event, err);
+        }
+    }
+
+    fn server_events(&self) -> Option<&ServerEvents> {
+        self.try_state::<ServerEvents>()
+            .map(|events| events.inner())
+    }
+}
+
+/// Settings read from a JSON file with the same keys as the store of the
+/// Tauri application, so that its `store.json` can be used as is.
+pub(crate) struct FileSettings {
+    #[cfg(feature = "gui")]
+    path: PathBuf,
+    values: Mutex<Map<String, Value>>,
+}
+
+impl FileSettings {
+    /// Reads the file, a missing one means that every setting has its default.
+    pub(crate) fn open(path: &Path) -> CommandResult<Self> {
+        let values = if path.exists() {
+            info!("Reading the settings from {:?}", path);
+            serde_json::from_str::<Map<String, Value>>(&fs::read_to_string(path)?)?
+        } else {
+            info!("Settings file {:?} not found, using the defaults", path);
+            Map::new()
+        };
+
+        Ok(FileSettings {
+            #[cfg(feature = "gui")]
+            path: path.to_path_buf(),
+            values: Mutex::new(values),
+        })
+    }
+}
+
+impl SettingsStore for FileSettings {
+    fn get_setting(&self, key: &str) -> CommandResult<Option<Value>> {
+        Ok(self.values.lock()?.get(key).cloned())
+    }
+
+    /// Saves the whole file, through a temporary one so that a crash can't
+    /// leave it half written.
+    #[cfg(feature = "gui")]
+    fn set_setting(&self, key: &str, value: Value) -> CommandResult<()> {
+        let mut values = self.values.lock()?;
+        values.insert(key.to_string(), value);
+
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&*values)?)?;
+        fs::rename(&temp_path, &self.path)?;
+
+        Ok(())
+    }
+}
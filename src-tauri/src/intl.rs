@@ -1,18 +1,21 @@
 use std::borrow::Cow;
 
-use fluent_bundle::{concurrent::FluentBundle, FluentResource};
+use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource};
 use log::error;
 use unic_langid::LanguageIdentifier;
 
 use crate::{CommandError, CommandResult};
 
+/// Same messages the frontend loads, so the backend texts stay in sync with the UI.
+const IT_MESSAGES: &str = include_str!("../../locales/it/app.ftl");
+
 pub(crate) struct Intl {
     bundle: FluentBundle<FluentResource>,
 }
 
 impl Intl {
     pub(crate) fn try_new(langid: LanguageIdentifier) -> CommandResult<Self> {
-        let ftl_string = "hello-world = Ciao, cane!".to_owned();
+        let ftl_string = IT_MESSAGES.to_owned();
         let res = FluentResource::try_new(ftl_string).or_else(|(res, errors)| {
             error!(
                 "Errors occurred while trying to create Fluent resource {:?}",
@@ -23,21 +26,37 @@ impl Intl {
         })?;
 
         let mut bundle: FluentBundle<FluentResource> = FluentBundle::new_concurrent(vec![langid]);
+        // Isolation marks around placeables can't be printed by the thermal printers
+        bundle.set_use_isolating(false);
 
-        bundle
-            .add_resource(res)
-            .expect("Failed to load Fluent resource");
+        if let Err(errors) = bundle.add_resource(res) {
+            error!("Errors occurred while adding Fluent resource {:?}", errors);
+        }
 
         Ok(Self { bundle })
     }
 
     pub(crate) fn t(&self, id: &str) -> CommandResult<Cow<'_, str>> {
-        let msg = self.bundle.get_message(id).expect("Message doesn't exist.");
+        self.t_args(id, None)
+    }
 
-        let pattern = msg.value().expect("Message has no value");
+    pub(crate) fn t_args(
+        &self,
+        id: &str,
+        args: Option<&FluentArgs>,
+    ) -> CommandResult<Cow<'_, str>> {
+        let Some(pattern) = self.bundle.get_message(id).and_then(|msg| msg.value()) else {
+            error!("Missing message {}", id);
+
+            return Ok(Cow::Owned(id.to_string()));
+        };
 
         let mut errors = vec![];
-        let value = self.bundle.format_pattern(pattern, None, &mut errors);
+        let value = self.bundle.format_pattern(pattern, args, &mut errors);
+
+        if !errors.is_empty() {
+            error!("Errors occurred while formatting message {} {:?}", id, errors);
+        }
 
         Ok(value)
     }
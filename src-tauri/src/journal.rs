@@ -0,0 +1,633 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Local, NaiveDateTime};
+use log::info;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, SqliteConnection};
+use uuid::Uuid;
+
+use crate::{begin_write, CommandResult, Db};
+
+/// Previous hash of the first record of the journal.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Key of the state noting when the journal was started, so that it's never
+/// started again over an emptied one.
+const OPENED_KEY: &str = "journal_opened_at";
+
+/// Record of the sales journal, each one chained to the previous one by its
+/// hash, so that changing or removing a record breaks the chain.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+pub(crate) enum JournalKind {
+    /// First record, followed by the sales recorded before the journal existed
+    Opening,
+    Sale,
+    Void,
+    /// Sales history cleared, the sales of the previous records were deleted
+    Clear,
+}
+
+#[derive(FromRow)]
+struct JournalRecord {
+    sequence: i64,
+    kind: JournalKind,
+    sale_id: Option<Uuid>,
+    recorded_at: NaiveDateTime,
+    content_hash: String,
+    previous_hash: String,
+    hash: String,
+}
+
+impl JournalRecord {
+    fn computed_hash(&self) -> String {
+        record_hash(
+            self.sequence,
+            self.kind,
+            self.sale_id,
+            self.recorded_at,
+            &self.content_hash,
+            &self.previous_hash,
+        )
+    }
+}
+
+/// Fields of a sale covered by its hash, the ones that never change once
+/// the sale is recorded.
+#[derive(Serialize, FromRow)]
+struct JournaledSale {
+    id: Uuid,
+    order_number: Option<i64>,
+    sale_time: NaiveDateTime,
+    total_amount: f64,
+    discount_amount: f64,
+    payment_method: Option<String>,
+    event_name: Option<String>,
+    shift_name: Option<String>,
+    /// Left out when missing, so that the sales recorded before operators
+    /// existed keep their hash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operator_id: Option<Uuid>,
+    operator_name: Option<String>,
+    pos_name: Option<String>,
+    #[sqlx(skip)]
+    items: Vec<JournaledSaleItem>,
+}
+
+#[derive(Serialize, FromRow)]
+struct JournaledSaleItem {
+    id: Uuid,
+    #[serde(skip)]
+    sale_id: Uuid,
+    product_id: Uuid,
+    product_name: String,
+    quantity: i64,
+    price_at_sale: f64,
+}
+
+#[derive(Serialize, FromRow)]
+struct JournaledVoid {
+    sale_id: Uuid,
+    voided_at: Option<NaiveDateTime>,
+    void_reason: Option<String>,
+}
+
+/// Outcome of the check of the journal against the sales.
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct JournalVerification {
+    pub records: i64,
+    /// Hash of the last record, printed on the end of day recap
+    pub head_hash: Option<String>,
+    /// Whether the given hash, e.g. printed on a previous recap, is in the chain
+    pub known_hash_found: Option<bool>,
+    pub issues: Vec<JournalIssue>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub(crate) enum JournalIssue {
+    /// Record changed, or records removed before it
+    BrokenChain { sequence: i64 },
+    /// Sale changed after it was recorded
+    SaleModified {
+        sequence: i64,
+        sale_id: Uuid,
+        order_number: Option<i64>,
+    },
+    /// Recorded sale deleted without clearing the sales history
+    SaleMissing { sequence: i64, sale_id: Uuid },
+    /// Sale added to the database without being recorded
+    SaleNotRecorded {
+        sale_id: Uuid,
+        order_number: Option<i64>,
+    },
+    /// Sale voided, or its void changed, without being recorded
+    VoidModified {
+        sale_id: Uuid,
+        order_number: Option<i64>,
+    },
+    /// Records all removed, while the database has sales
+    JournalEmpty { sales: i64 },
+}
+
+fn sha256(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+fn content_hash<T: Serialize>(content: &T) -> CommandResult<String> {
+    Ok(sha256(&serde_json::to_string(content)?))
+}
+
+fn record_hash(
+    sequence: i64,
+    kind: JournalKind,
+    sale_id: Option<Uuid>,
+    recorded_at: NaiveDateTime,
+    content_hash: &str,
+    previous_hash: &str,
+) -> String {
+    sha256(&format!(
+        "{}|{:?}|{}|{}|{}|{}",
+        sequence,
+        kind,
+        sale_id.map(|id| id.to_string()).unwrap_or_default(),
+        recorded_at.format("%Y-%m-%dT%H:%M:%S%.9f"),
+        content_hash,
+        previous_hash
+    ))
+}
+
+/// Appends a record after the last one, in the transaction changing the
+/// sales, which holds the write lock so that no other record comes between.
+async fn append(
+    conn: &mut SqliteConnection,
+    kind: JournalKind,
+    sale_id: Option<Uuid>,
+    content_hash: String,
+) -> CommandResult<()> {
+    let head = sqlx::query_as::<_, (i64, String)>(
+        "SELECT sequence, hash FROM sales_journal ORDER BY sequence DESC LIMIT 1",
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    let (sequence, previous_hash) = match head {
+        Some((sequence, hash)) => (sequence + 1, hash),
+        None => (1, GENESIS_HASH.to_string()),
+    };
+
+    let recorded_at = Local::now().naive_local();
+    let hash = record_hash(
+        sequence,
+        kind,
+        sale_id,
+        recorded_at,
+        &content_hash,
+        &previous_hash,
+    );
+
+    sqlx::query(
+        r#"
+        INSERT INTO sales_journal (sequence, kind, sale_id, recorded_at, content_hash, previous_hash, hash)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(sequence)
+    .bind(kind)
+    .bind(sale_id)
+    .bind(recorded_at)
+    .bind(content_hash)
+    .bind(previous_hash)
+    .bind(hash)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+const SALE_COLUMNS: &str = r#"
+    SELECT id, order_number, sale_time, total_amount, discount_amount, payment_method,
+        event_name, shift_name, operator_id, operator_name, pos_name
+    FROM sales
+"#;
+
+const SALE_ITEM_COLUMNS: &str = r#"
+    SELECT id, sale_id, product_id, product_name, quantity, price_at_sale
+    FROM sale_items
+"#;
+
+async fn load_sale(conn: &mut SqliteConnection, sale_id: Uuid) -> CommandResult<JournaledSale> {
+    let mut sale = sqlx::query_as::<_, JournaledSale>(&format!("{} WHERE id = ?", SALE_COLUMNS))
+        .bind(sale_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    sale.items =
+        sqlx::query_as::<_, JournaledSaleItem>(&format!("{} WHERE sale_id = ?", SALE_ITEM_COLUMNS))
+            .bind(sale_id)
+            .fetch_all(&mut *conn)
+            .await?;
+    sale.items.sort_by_key(|item| item.id);
+
+    Ok(sale)
+}
+
+async fn load_void(conn: &mut SqliteConnection, sale_id: Uuid) -> CommandResult<JournaledVoid> {
+    Ok(sqlx::query_as::<_, JournaledVoid>(
+        "SELECT id AS sale_id, voided_at, void_reason FROM sales WHERE id = ?",
+    )
+    .bind(sale_id)
+    .fetch_one(&mut *conn)
+    .await?)
+}
+
+/// Records the sale with its items and payment, in the transaction saving it.
+pub(crate) async fn record_sale(conn: &mut SqliteConnection, sale_id: Uuid) -> CommandResult<()> {
+    let sale = load_sale(conn, sale_id).await?;
+
+    append(conn, JournalKind::Sale, Some(sale_id), content_hash(&sale)?).await
+}
+
+/// Records the void of the sale, in the transaction voiding it.
+pub(crate) async fn record_void(conn: &mut SqliteConnection, sale_id: Uuid) -> CommandResult<()> {
+    let void = load_void(conn, sale_id).await?;
+
+    append(conn, JournalKind::Void, Some(sale_id), content_hash(&void)?).await
+}
+
+/// Records the clear of the sales history, before the sales are deleted in
+/// the same transaction.
+#[cfg(feature = "gui")]
+pub(crate) async fn record_clear(conn: &mut SqliteConnection) -> CommandResult<()> {
+    let (sales, total): (i64, f64) =
+        sqlx::query_as("SELECT COUNT(*), COALESCE(SUM(total_amount), 0.0) FROM sales")
+            .fetch_one(&mut *conn)
+            .await?;
+
+    let content = serde_json::json!({ "sales": sales, "total_amount": total });
+    append(conn, JournalKind::Clear, None, content_hash(&content)?).await
+}
+
+/// Starts the journal of a database that never had one, recording the sales
+/// saved before it. A journal emptied afterwards stays empty, for `verify`
+/// to report it.
+pub(crate) async fn open(db: &Db) -> CommandResult<()> {
+    let mut tx = begin_write(db).await?;
+
+    let opened: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sync_state WHERE key = ?")
+        .bind(OPENED_KEY)
+        .fetch_one(&mut *tx)
+        .await?;
+    if opened > 0 {
+        return Ok(());
+    }
+
+    sqlx::query("INSERT INTO sync_state (key, value) VALUES (?, ?)")
+        .bind(OPENED_KEY)
+        .bind(Local::now().naive_local().to_string())
+        .execute(&mut *tx)
+        .await?;
+
+    // Started before the state was kept
+    let records: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sales_journal")
+        .fetch_one(&mut *tx)
+        .await?;
+    if records > 0 {
+        tx.commit().await?;
+        return Ok(());
+    }
+
+    let sales: Vec<(Uuid, bool)> = sqlx::query_as(
+        "SELECT id, voided_at IS NOT NULL FROM sales ORDER BY sale_time, order_number",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let content = serde_json::json!({ "sales": sales.len() });
+    append(&mut tx, JournalKind::Opening, None, content_hash(&content)?).await?;
+    for (sale_id, voided) in &sales {
+        record_sale(&mut tx, *sale_id).await?;
+        if *voided {
+            record_void(&mut tx, *sale_id).await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    info!("Sales journal started with {} sales", sales.len());
+
+    Ok(())
+}
+
+/// Hash of the last record, none before the journal is started.
+pub(crate) async fn head_hash(db: &Db) -> CommandResult<Option<String>> {
+    Ok(
+        sqlx::query_scalar("SELECT hash FROM sales_journal ORDER BY sequence DESC LIMIT 1")
+            .fetch_optional(db)
+            .await?,
+    )
+}
+
+/// Checks the chain of the records, then that the sales and voids of the
+/// database are the recorded ones.
+pub(crate) async fn verify(
+    db: &Db,
+    known_hash: Option<&str>,
+) -> CommandResult<JournalVerification> {
+    let mut conn = db.acquire().await?;
+
+    let records = sqlx::query_as::<_, JournalRecord>(
+        r#"
+        SELECT sequence, kind, sale_id, recorded_at, content_hash, previous_hash, hash
+        FROM sales_journal
+        ORDER BY sequence
+        "#,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut issues = Vec::new();
+    let mut previous_hash = GENESIS_HASH;
+    for (expected_sequence, record) in (1..).zip(&records) {
+        if record.sequence != expected_sequence
+            || record.previous_hash != previous_hash
+            || record.computed_hash() != record.hash
+        {
+            issues.push(JournalIssue::BrokenChain {
+                sequence: record.sequence,
+            });
+        }
+        previous_hash = &record.hash;
+    }
+
+    // The sales recorded before the last clear were deleted with it
+    let last_clear = records
+        .iter()
+        .filter(|record| record.kind == JournalKind::Clear)
+        .map(|record| record.sequence)
+        .max()
+        .unwrap_or(0);
+    let mut recorded_sales = HashMap::new();
+    let mut recorded_voids = HashMap::new();
+    for record in records.iter().filter(|record| record.sequence > last_clear) {
+        match (record.kind, record.sale_id) {
+            (JournalKind::Sale, Some(sale_id)) => {
+                recorded_sales.insert(sale_id, record);
+            }
+            (JournalKind::Void, Some(sale_id)) => {
+                recorded_voids.insert(sale_id, record);
+            }
+            _ => {}
+        }
+    }
+
+    let mut sales = sqlx::query_as::<_, JournaledSale>(SALE_COLUMNS)
+        .fetch_all(&mut *conn)
+        .await?;
+    let mut items: HashMap<Uuid, Vec<JournaledSaleItem>> = HashMap::new();
+    for item in sqlx::query_as::<_, JournaledSaleItem>(SALE_ITEM_COLUMNS)
+        .fetch_all(&mut *conn)
+        .await?
+    {
+        items.entry(item.sale_id).or_default().push(item);
+    }
+    let voids: HashMap<Uuid, JournaledVoid> = sqlx::query_as::<_, JournaledVoid>(
+        "SELECT id AS sale_id, voided_at, void_reason FROM sales",
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|void| (void.sale_id, void))
+    .collect();
+
+    if records.is_empty() && !sales.is_empty() {
+        issues.push(JournalIssue::JournalEmpty {
+            sales: sales.len() as i64,
+        });
+    }
+
+    let mut present = HashSet::new();
+    for sale in &mut sales {
+        sale.items = items.remove(&sale.id).unwrap_or_default();
+        sale.items.sort_by_key(|item| item.id);
+        present.insert(sale.id);
+
+        match recorded_sales.get(&sale.id) {
+            None => issues.push(JournalIssue::SaleNotRecorded {
+                sale_id: sale.id,
+                order_number: sale.order_number,
+            }),
+            Some(record) if record.content_hash != content_hash(&*sale)? => {
+                issues.push(JournalIssue::SaleModified {
+                    sequence: record.sequence,
+                    sale_id: sale.id,
+                    order_number: sale.order_number,
+                })
+            }
+            Some(_) => {}
+        }
+
+        let void_matches = match (voids.get(&sale.id), recorded_voids.get(&sale.id)) {
+            (Some(void), Some(record)) if void.voided_at.is_some() => {
+                record.content_hash == content_hash(void)?
+            }
+            (Some(void), None) => void.voided_at.is_none(),
+            _ => false,
+        };
+        if !void_matches {
+            issues.push(JournalIssue::VoidModified {
+                sale_id: sale.id,
+                order_number: sale.order_number,
+            });
+        }
+    }
+
+    for record in records.iter().filter(|record| record.sequence > last_clear) {
+        if let (JournalKind::Sale, Some(sale_id)) = (record.kind, record.sale_id) {
+            if !present.contains(&sale_id) {
+                issues.push(JournalIssue::SaleMissing {
+                    sequence: record.sequence,
+                    sale_id,
+                });
+            }
+        }
+    }
+
+    let known_hash = known_hash.map(str::trim).filter(|hash| !hash.is_empty());
+
+    Ok(JournalVerification {
+        records: records.len() as i64,
+        head_hash: records.last().map(|record| record.hash.clone()),
+        known_hash_found: known_hash.map(|known_hash| {
+            records
+                .iter()
+                .any(|record| record.hash.eq_ignore_ascii_case(known_hash))
+        }),
+        issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::MIGRATOR;
+
+    async fn test_db() -> Db {
+        // One connection, every connection to memory has its own database
+        let db = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        MIGRATOR.run(&db).await.unwrap();
+
+        db
+    }
+
+    /// Saves a sale of one product and records it, as a sale of the POS.
+    async fn create_sale(db: &Db, order_number: i64) -> Uuid {
+        let product_id = Uuid::new_v4();
+        let sale_id = Uuid::new_v4();
+        let mut tx = begin_write(db).await.unwrap();
+        sqlx::query("INSERT INTO products (id, name, category, price) VALUES (?, ?, 'Food', 3.0)")
+            .bind(product_id)
+            .bind(format!("Panino {}", order_number))
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO sales (id, order_number, sale_time, total_amount, payment_method)
+            VALUES (?, ?, ?, 6.0, 'Cash')
+            "#,
+        )
+        .bind(sale_id)
+        .bind(order_number)
+        .bind(Local::now().naive_local())
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO sale_items (id, sale_id, product_id, product_name, quantity, price_at_sale)
+            VALUES (?, ?, ?, 'Panino', 2, 3.0)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(sale_id)
+        .bind(product_id)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+        record_sale(&mut tx, sale_id).await.unwrap();
+        tx.commit().await.unwrap();
+
+        sale_id
+    }
+
+    /// Lifts the append-only guard, as someone editing the file would.
+    async fn allow_journal_deletes(db: &Db) {
+        sqlx::query("DROP TRIGGER sales_journal_no_delete")
+            .execute(db)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn untouched_journal_has_no_issues() {
+        let db = test_db().await;
+        create_sale(&db, 1).await;
+        create_sale(&db, 2).await;
+
+        let verification = verify(&db, None).await.unwrap();
+
+        assert_eq!(verification.records, 2);
+        assert!(verification.issues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn changed_sale_total_is_reported() {
+        let db = test_db().await;
+        let sale_id = create_sale(&db, 1).await;
+
+        sqlx::query("UPDATE sales SET total_amount = 1.0 WHERE id = ?")
+            .bind(sale_id)
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let issues = verify(&db, None).await.unwrap().issues;
+        assert!(matches!(
+            issues.as_slice(),
+            [JournalIssue::SaleModified {
+                sequence: 1,
+                sale_id: modified,
+                order_number: Some(1),
+            }] if *modified == sale_id
+        ));
+    }
+
+    #[tokio::test]
+    async fn deleted_sale_is_reported() {
+        let db = test_db().await;
+        create_sale(&db, 1).await;
+        let sale_id = create_sale(&db, 2).await;
+
+        sqlx::query("DELETE FROM sales WHERE id = ?")
+            .bind(sale_id)
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let issues = verify(&db, None).await.unwrap().issues;
+        assert!(matches!(
+            issues.as_slice(),
+            [JournalIssue::SaleMissing {
+                sequence: 2,
+                sale_id: missing,
+            }] if *missing == sale_id
+        ));
+    }
+
+    #[tokio::test]
+    async fn deleted_record_breaks_the_chain() {
+        let db = test_db().await;
+        create_sale(&db, 1).await;
+        create_sale(&db, 2).await;
+        create_sale(&db, 3).await;
+
+        allow_journal_deletes(&db).await;
+        sqlx::query("DELETE FROM sales_journal WHERE sequence = 2")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let issues = verify(&db, None).await.unwrap().issues;
+        assert!(issues
+            .iter()
+            .any(|issue| matches!(issue, JournalIssue::BrokenChain { sequence: 3 })));
+        assert!(issues
+            .iter()
+            .all(|issue| !matches!(issue, JournalIssue::BrokenChain { sequence: 1 })));
+    }
+
+    #[tokio::test]
+    async fn emptied_journal_is_reported() {
+        let db = test_db().await;
+        create_sale(&db, 1).await;
+        create_sale(&db, 2).await;
+
+        allow_journal_deletes(&db).await;
+        sqlx::query("DELETE FROM sales_journal")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let verification = verify(&db, None).await.unwrap();
+        assert_eq!(verification.records, 0);
+        assert!(verification
+            .issues
+            .iter()
+            .any(|issue| matches!(issue, JournalIssue::JournalEmpty { sales: 2 })));
+    }
+}
@@ -1,19 +1,27 @@
-use std::sync::{Arc, Mutex};
-
-use chrono::{Datelike, Local, NaiveDate, Utc};
-use escpos::{
-    driver::UsbDriver,
-    printer::Printer,
-    utils::{DebugMode, Protocol},
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
 };
+
+use backup::{BackupReason, BackupSettings};
+use chrono::Local;
+use escpos::{printer::Printer, utils::Protocol};
+use fluent_bundle::FluentArgs;
+use audit::{Actor, AuditAction};
+use host::{load_settings, Host, SettingsStore};
 use log::info;
-use printing::{print_tickets, PrintingLayout};
+use printing::{print_tickets, PrinterSettings, PrintingLayout, SaleCode};
+use reports::{EventSettings, ReportFilter};
 use rusb::{Context, DeviceList};
 use serde::{Deserialize, Serialize};
-use sqlx::{migrate::MigrateDatabase, sqlite::SqlitePoolOptions, Sqlite, SqlitePool};
-use tauri::{App, AppHandle, Manager, State};
-use tauri_plugin_store::StoreExt;
-use unic_langid::langid;
+use sqlx::{
+    migrate::{MigrateDatabase, Migrator},
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    Sqlite, SqliteConnection, SqlitePool, Transaction,
+};
+use status::PrinterMonitor;
+use terminal::TerminalSettings;
 
 use errors::*;
 use exports::*;
@@ -24,25 +32,57 @@ use uuid::Uuid;
 #[cfg(debug_assertions)]
 use escpos::driver::ConsoleDriver;
 #[cfg(not(debug_assertions))]
+use escpos::driver::UsbDriver;
+#[cfg(not(debug_assertions))]
 use log::debug;
 
+#[cfg(feature = "gui")]
+mod app;
+mod audit;
+mod backup;
+mod codepages;
 mod errors;
+mod admin;
 mod exports;
+mod headless;
+mod host;
 mod intl;
+mod journal;
+mod live;
+#[cfg(feature = "gui")]
+mod merge;
 mod models;
+mod operators;
+#[cfg(feature = "gui")]
+mod pdf_report;
 mod printing;
+mod reports;
+mod server;
+mod status;
+mod sync;
+mod terminal;
+
+pub use admin::run as run_admin;
+#[cfg(feature = "gui")]
+pub use app::run;
+pub use headless::run as run_headless;
 
 type Db = SqlitePool;
-#[derive(Clone)]
-struct AppState {
-    db: Db,
-}
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
 #[cfg(debug_assertions)]
-type PrinterState = Arc<Mutex<Option<Printer<ConsoleDriver>>>>;
+type PrinterDriver = ConsoleDriver;
 
 #[cfg(not(debug_assertions))]
-type PrinterState = Arc<Mutex<Option<Printer<UsbDriver>>>>;
+type PrinterDriver = UsbDriver;
+
+type PrinterState = Arc<Mutex<Option<Printer<PrinterDriver>>>>;
+type PrinterMonitorState = Arc<PrinterMonitor<PrinterDriver>>;
+
+const PRINTER_STATUS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// How often the backup thread checks whether a scheduled backup is due
+const BACKUP_SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Serialize, Deserialize)]
 struct UsbDevice {
@@ -52,67 +92,139 @@ struct UsbDevice {
     product_name: String,
 }
 
-#[tauri::command]
-async fn list_products(app_state: State<'_, AppState>) -> CommandResult<Vec<Product>> {
+async fn fetch_products(db: &Db) -> CommandResult<Vec<Product>> {
     let products = sqlx::query_as!(
         Product,
         r#"
-        SELECT id as "id: uuid::Uuid", name, category, price, is_deleted
+        SELECT id as "id: uuid::Uuid", name, category, price, is_deleted, ticket_copies
         FROM products
         WHERE is_deleted = 0
     "#
     )
-    .fetch_all(&app_state.db)
+    .fetch_all(db)
     .await?;
 
     Ok(products)
 }
 
-#[tauri::command]
-async fn create_product(
-    product: UnsavedProduct,
-    app_state: State<'_, AppState>,
+async fn insert_product(db: &Db, actor: &Actor, product: &UnsavedProduct) -> CommandResult<()> {
+    let mut tx = begin_write(db).await?;
+    upsert_product(&mut tx, actor, product).await?;
+    tx.commit().await?;
+
+    info!("Product {} created", product.name);
+
+    Ok(())
+}
+
+/// Adds the product, or updates the one with the same name, in the
+/// transaction of the caller.
+async fn upsert_product(
+    conn: &mut SqliteConnection,
+    actor: &Actor,
+    product: &UnsavedProduct,
 ) -> CommandResult<()> {
-    sqlx::query(
+    let before = sqlx::query_as::<_, Product>(
+        "SELECT id, name, category, price, is_deleted, ticket_copies FROM products WHERE name = ?",
+    )
+    .bind(&product.name)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let product_id: Uuid = sqlx::query_scalar(
         r#"
-        INSERT INTO products(id, name, price, category, is_deleted)
-        VALUES ($1, $2, $3, $4, 0)
-        ON CONFLICT(name) DO UPDATE SET price = $3, category = $4, is_deleted = 0
+        INSERT INTO products(id, name, price, category, ticket_copies, is_deleted)
+        VALUES ($1, $2, $3, $4, $5, 0)
+        ON CONFLICT(name) DO UPDATE SET price = $3, category = $4, ticket_copies = $5, is_deleted = 0
+        RETURNING id
     "#,
     )
     .bind(Uuid::new_v4())
     .bind(&product.name)
     .bind(product.price)
     .bind(&product.category)
-    .execute(&app_state.db)
+    .bind(product.ticket_copies)
+    .fetch_one(&mut *conn)
     .await?;
 
-    info!("Product {} created", product.name);
+    // Adding a product with the name of a deleted one brings it back
+    let before = before.filter(|before| !before.is_deleted);
+    audit_product_change(conn, actor, product_id, before).await?;
+    sync::record_product_change(conn, product_id).await
+}
 
-    Ok(())
+/// Product as saved, deleted ones included.
+async fn load_product(conn: &mut SqliteConnection, product_id: Uuid) -> CommandResult<Product> {
+    Ok(sqlx::query_as::<_, Product>(
+        "SELECT id, name, category, price, is_deleted, ticket_copies FROM products WHERE id = ?",
+    )
+    .bind(product_id)
+    .fetch_one(&mut *conn)
+    .await?)
+}
+
+/// Records the change of the product in the audit log, from its state before
+/// the change, none for a new product.
+async fn audit_product_change(
+    conn: &mut SqliteConnection,
+    actor: &Actor,
+    product_id: Uuid,
+    before: Option<Product>,
+) -> CommandResult<()> {
+    let after = load_product(conn, product_id).await?;
+    let action = match &before {
+        None => AuditAction::ProductCreated,
+        Some(_) if after.is_deleted => AuditAction::ProductDeleted,
+        Some(before) if before.price != after.price => AuditAction::PriceChanged,
+        Some(_) => AuditAction::ProductUpdated,
+    };
+
+    let before = before.map(serde_json::to_value).transpose()?;
+    let after = serde_json::to_value(&after)?;
+    // Saving a product as it is, e.g. importing the same catalog again
+    if before.as_ref() == Some(&after) {
+        return Ok(());
+    }
+
+    audit::record(
+        conn,
+        actor,
+        action,
+        Some(&product_id.to_string()),
+        before,
+        Some(after),
+    )
+    .await
 }
 
-#[tauri::command]
-async fn update_product(product: Product, app_state: State<'_, AppState>) -> CommandResult<()> {
+async fn save_product(db: &Db, actor: &Actor, product: &Product) -> CommandResult<()> {
+    let mut tx = begin_write(db).await?;
+    let before = load_product(&mut tx, product.id).await?;
     sqlx::query(
         r#"
         UPDATE products
-        SET name = ?, price = ?, category = ?
+        SET name = ?, price = ?, category = ?, ticket_copies = ?
         WHERE id = ?
     "#,
     )
-    .bind(product.name)
+    .bind(&product.name)
     .bind(product.price)
-    .bind(product.category)
+    .bind(&product.category)
+    .bind(product.ticket_copies)
     .bind(product.id)
-    .execute(&app_state.db)
+    .execute(&mut *tx)
     .await?;
 
+    audit_product_change(&mut tx, actor, product.id, Some(before)).await?;
+    sync::record_product_change(&mut tx, product.id).await?;
+    tx.commit().await?;
+
     Ok(())
 }
 
-#[tauri::command]
-async fn delete_product(product: Product, app_state: State<'_, AppState>) -> CommandResult<()> {
+async fn mark_product_deleted(db: &Db, actor: &Actor, product_id: Uuid) -> CommandResult<()> {
+    let mut tx = begin_write(db).await?;
+    let before = load_product(&mut tx, product_id).await?;
     sqlx::query(
         r#"
         UPDATE products
@@ -120,87 +232,149 @@ async fn delete_product(product: Product, app_state: State<'_, AppState>) -> Com
         WHERE id = ?
     "#,
     )
-    .bind(product.id)
-    .execute(&app_state.db)
+    .bind(product_id)
+    .execute(&mut *tx)
     .await?;
 
+    audit_product_change(&mut tx, actor, product_id, Some(before)).await?;
+    sync::record_product_change(&mut tx, product_id).await?;
+    tx.commit().await?;
+
     Ok(())
 }
 
-#[tauri::command]
-async fn process_sale(
-    app: AppHandle,
-    app_state: State<'_, AppState>,
-    printer_state: State<'_, PrinterState>,
-    intl_state: State<'_, Intl>,
-    items: Vec<CartItem>,
-) -> CommandResult<Uuid> {
-    if items.is_empty() {
+/// Validates and saves the sale, tagged with the cash desk and the operator
+/// that made it.
+async fn record_sale(
+    db: &Db,
+    intl: &Intl,
+    event_settings: &EventSettings,
+    actor: &Actor,
+    request: &SaleRequest,
+) -> CommandResult<RecordedSale> {
+    if request.items.is_empty() {
         return Err(CommandError::InvalidInput(
-            intl_state
-                .t("pos-messages-cannot-process-sale-with-no-items")?
+            intl.t("pos-messages-cannot-process-sale-with-no-items")?
                 .to_string(),
         ));
     }
 
     let sale_time = Local::now().naive_local();
 
-    let total_amount: f64 = items
-        .iter()
-        .map(|item| item.price * item.quantity as f64)
-        .sum();
-
-    let mut tx = app_state.db.begin().await?;
-    let sale_id: uuid::Uuid = sqlx::query_scalar(
-        r#"
-        INSERT INTO sales (id, sale_time, total_amount) VALUES (?, ?, ?) RETURNING id as "id: uuid::Uuid";
-        "#,
-    )
-    .bind(Uuid::new_v4())
-    .bind(sale_time)
-    .bind(total_amount)
-    .fetch_one(&mut *tx)
-    .await?;
-
-    let sale = Sale {
-        id: sale_id,
-        payment_method: None,
-        sale_time,
-        total_amount,
-    };
+    let mut tx = begin_write(db).await?;
 
+    // Names and prices are the ones of the catalog, the cash desk sending
+    // them only to check that its own catalog is up to date
     let mut items_with_products: Vec<(CartItem, Product)> = vec!();
-    for item in items {
+    for item in &request.items {
+        let mut args = FluentArgs::new();
+        args.set("product", item.name.clone());
+
         let product = sqlx::query_as!(
             Product,
             r#"
-            SELECT id as "id: uuid::Uuid", name, category, price, is_deleted
+            SELECT id as "id: uuid::Uuid", name, category, price, is_deleted, ticket_copies
             FROM products
             WHERE id = ?
             "#,
             item.product_id
         )
-            .fetch_one(&mut *tx)
-            .await?;
+            .fetch_optional(&mut *tx)
+            .await?
+            .filter(|product| !product.is_deleted);
+        let Some(product) = product else {
+            return Err(CommandError::InvalidInput(
+                intl.t_args("pos-messages-product-not-available", Some(&args))?
+                    .to_string(),
+            ));
+        };
 
         let quantity = item.quantity;
-        let price_at_sale = item.price;
+        let price_at_sale = product.price;
 
         if quantity <= 0 {
+            args.set("quantity", quantity);
+
             return Err(CommandError::InvalidInput(
-                intl_state
-                    .t("pos-messages-invalid-quantity-for-product")?
-                    .to_string(), // quantity, item.product_id
+                intl.t_args("pos-messages-invalid-quantity-for-product", Some(&args))?
+                    .to_string(),
             ));
         }
         if price_at_sale < 0.0 {
+            args.set("price", price_at_sale);
+
+            return Err(CommandError::InvalidInput(
+                intl.t_args("pos-messages-invalid-price-for-product", Some(&args))?
+                    .to_string(),
+            ));
+        }
+        if item.price != price_at_sale {
             return Err(CommandError::InvalidInput(
-                intl_state
-                    .t("pos-messages-invalid-price-for-product")?
-                    .to_string(), // price_at_sale, item.product_id
+                intl.t_args("pos-messages-price-changed-for-product", Some(&args))?
+                    .to_string(),
             ));
         }
 
+        let item = CartItem {
+            product_id: product.id,
+            name: product.name.clone(),
+            price: price_at_sale,
+            quantity,
+        };
+        items_with_products.push((item, product));
+    }
+
+    let gross_amount: f64 = items_with_products
+        .iter()
+        .map(|(item, _)| item.price * item.quantity as f64)
+        .sum();
+    let discount_amount = request.discount.unwrap_or(0.0);
+    if !(0.0..=gross_amount).contains(&discount_amount) {
+        return Err(CommandError::InvalidInput(
+            intl.t("pos-messages-invalid-discount")?.to_string(),
+        ));
+    }
+    let total_amount = gross_amount - discount_amount;
+
+    // Each cash desk numbers its own sales, so that synced desks don't hand
+    // out the same numbers
+    let order_number: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(order_number), 0) + 1 FROM sales WHERE pos_name IS ?",
+    )
+    .bind(&request.pos_name)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let sale_id: uuid::Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO sales (id, order_number, sale_time, total_amount, discount_amount, payment_method, event_name, shift_name, operator_id, operator_name, pos_name) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id as "id: uuid::Uuid";
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(order_number)
+    .bind(sale_time)
+    .bind(total_amount)
+    .bind(discount_amount)
+    .bind(request.payment_method.as_str())
+    .bind(&event_settings.name)
+    .bind(&event_settings.shift)
+    .bind(actor.operator_id)
+    .bind(&actor.name)
+    .bind(&request.pos_name)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let sale = Sale {
+        id: sale_id,
+        order_number,
+        payment_method: Some(request.payment_method.as_str().to_string()),
+        sale_time,
+        total_amount,
+        discount_amount,
+        voided_at: None,
+    };
+
+    for (item, _) in &items_with_products {
         sqlx::query(
             r#"
             INSERT INTO sale_items (id, sale_id, product_id, product_name, quantity, price_at_sale)
@@ -211,184 +385,207 @@ async fn process_sale(
         .bind(sale_id)
         .bind(item.product_id)
         .bind(&item.name)
-        .bind(quantity)
-        .bind(price_at_sale)
+        .bind(item.quantity)
+        .bind(item.price)
         .execute(&mut *tx)
         .await?;
+    }
 
-        items_with_products.push((item, product));
+    if request.open_cash_drawer {
+        record_cash_drawer_opening(&mut tx, actor, "Sale", Some(sale_id)).await?;
     }
 
+    journal::record_sale(&mut tx, sale_id).await?;
+    sync::record_sale_change(&mut tx, sale_id).await?;
     tx.commit().await?;
 
-    info!("Created new sale {}", sale_id);
-
-    let mut mutex_guard = printer_state.lock()?;
-    let printer = mutex_guard
-        .as_mut()
-        .ok_or(CommandError::PrinterNotConfigured)?;
-    printer.debug_mode(Some(DebugMode::Dec)).init()?;
-
-    let store = app.get_store("store.json").unwrap();
-    let some_store = store.get("ticket-layout");
-    let layout = if let Some(store) = some_store {
-        serde_json::from_value::<PrintingLayout>(store)?
-    } else {
-        PrintingLayout::default()
-    };
-
-    print_tickets(printer, &layout, &sale, &items_with_products)?;
+    info!(
+        "Created new sale {} from {:?} by {:?}",
+        sale_id, request.pos_name, actor.name
+    );
 
-    Ok(sale_id)
+    Ok(RecordedSale {
+        sale,
+        items: items_with_products,
+    })
 }
 
-#[tauri::command]
-async fn get_sales_recap(app_state: State<'_, AppState>) -> CommandResult<Vec<AggregatedSaleItem>> {
-    let item_sales = sqlx::query_as::<_, AggregatedSaleItem>(
+/// Records an opening of the cash drawer by the actor, returning its id.
+async fn record_cash_drawer_opening(
+    conn: &mut SqliteConnection,
+    actor: &Actor,
+    reason: &str,
+    sale_id: Option<Uuid>,
+) -> CommandResult<Uuid> {
+    let id = Uuid::new_v4();
+    sqlx::query(
         r#"
-            SELECT id,
-                product_id,
-                product_name,
-                SUM(quantity) AS total_quantity_sold,
-                SUM(quantity * price_at_sale) AS total_value_sold
-            FROM sale_items
-            GROUP BY product_id;
+        INSERT INTO cash_drawer_openings (id, opened_at, reason, sale_id, operator_id, operator_name, pos_name)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
         "#,
     )
-    .fetch_all(&app_state.db)
+    .bind(id)
+    .bind(Local::now().naive_local())
+    .bind(reason)
+    .bind(sale_id)
+    .bind(actor.operator_id)
+    .bind(&actor.name)
+    .bind(&actor.pos_name)
+    .execute(&mut *conn)
     .await?;
 
-    Ok(item_sales)
+    Ok(id)
 }
 
-#[tauri::command]
-async fn get_today_sales(app_state: State<'_, AppState>) -> CommandResult<Vec<Sale>> {
-    let now = Utc::now().naive_local();
-    let start_of_day = NaiveDate::from_ymd_opt(now.year(), now.month(), now.day())
-        .ok_or(CommandError::SaleNotFound)?;
-
-    let sales = sqlx::query_as!(
-        Sale,
-        r#"
-        SELECT id as "id: uuid::Uuid", sale_time, total_amount, payment_method
-        FROM sales
-        WHERE sale_time >= ?
-    "#,
-        start_of_day
-    )
-    .fetch_all(&app_state.db)
-    .await?;
+/// Sales of the current business day, voided ones included.
+async fn list_today_sales(db: &Db, settings: &EventSettings) -> CommandResult<Vec<Sale>> {
+    let today = settings.business_day_of(Local::now().naive_local());
+    let filter = ReportFilter {
+        include_voided: true,
+        ..ReportFilter::business_day(today)
+    };
 
-    Ok(sales)
+    reports::list_sales(db, &filter, settings).await
 }
 
-#[tauri::command]
-async fn clear_sales_data(app_state: State<'_, AppState>) -> CommandResult<()> {
-    info!("Clearing sales data");
-
-    let mut tx = app_state.db.begin().await?;
-
-    sqlx::query!("DELETE FROM sale_items")
-        .execute(&mut *tx)
-        .await?;
-    sqlx::query!("DELETE FROM sales").execute(&mut *tx).await?;
-    sqlx::query!("DELETE FROM products WHERE is_deleted = 1")
-        .execute(&mut *tx)
-        .await?;
+/// Defaults to the current business day, as the end of day recap.
+fn summary_filter(filter: Option<ReportFilter>, settings: &EventSettings) -> ReportFilter {
+    filter.unwrap_or_else(|| {
+        ReportFilter::business_day(settings.business_day_of(Local::now().naive_local()))
+    })
+}
 
-    tx.commit().await?;
+async fn print_summary(
+    store: &impl SettingsStore,
+    db: &Db,
+    printer_state: &PrinterState,
+    intl: &Intl,
+    filter: Option<ReportFilter>,
+) -> CommandResult<()> {
+    let settings = load_event_settings(store)?;
+    let printer_settings = load_printer_settings(store)?;
+    let filter = summary_filter(filter, &settings);
 
-    Ok(())
-}
+    let summary = reports::sales_summary(db, &filter, &settings).await?;
+    let journal_head = journal::head_hash(db).await?;
 
-#[tauri::command]
-async fn export_sales(app_state: State<'_, AppState>) -> CommandResult<()> {
-    info!("Exporting to XLSX");
+    let mut mutex_guard = printer_state.lock()?;
+    let printer = mutex_guard
+        .as_mut()
+        .ok_or(CommandError::PrinterNotConfigured)?;
 
-    export_sales_report(app_state.db.clone(), "/home/mikol/export.xlsx").await
+    printing::print_sales_summary(
+        printer,
+        &printer_settings,
+        intl,
+        &summary,
+        journal_head.as_deref(),
+        Local::now().naive_local(),
+    )
 }
 
-#[tauri::command]
-async fn print_last_sale(
-    app: AppHandle,
-    app_state: State<'_, AppState>,
-    printer_state: State<'_, PrinterState>,
+/// Marks the sale as voided, refusing the sales voided already.
+async fn record_void(
+    db: &Db,
+    intl: &Intl,
+    actor: &Actor,
+    sale_id: Uuid,
+    reason: Option<String>,
 ) -> CommandResult<()> {
-    let last_sale = sqlx::query_as!(
-        Sale,
-        r#"
-        SELECT id as "id: uuid::Uuid", sale_time, total_amount, payment_method
-        FROM sales
-        ORDER BY sale_time DESC
-        LIMIT 1"#
+    info!("Voiding sale {} ({:?})", sale_id, reason);
+
+    let voided_at = Local::now().naive_local();
+    let mut tx = begin_write(db).await?;
+    let result = sqlx::query!(
+        "UPDATE sales SET voided_at = ?, void_reason = ? WHERE id = ? AND voided_at IS NULL",
+        voided_at,
+        reason,
+        sale_id
     )
-        .fetch_optional(&app_state.db)
-        .await?
-        .ok_or_else(|| CommandError::InvalidInput("No sales recorded yet".to_string()))?;
-
-    info!("Reprinting tickets of sale {}", last_sale.id);
+    .execute(&mut *tx)
+    .await?;
 
-    let items: Vec<(CartItem, Product)> = sqlx::query_as!(
-        CartItemWithProduct,
-    r#"
-        SELECT
-                sale_items.product_name AS 'name_at_sale',
-                sale_items.price_at_sale,
-                sale_items.quantity,
-                products.id AS 'product_id: uuid::Uuid',
-                products.category,
-                products.name,
-                products.price,
-                products.is_deleted AS 'is_product_deleted'
-        FROM sale_items
-            JOIN products ON sale_items.product_id = products.id
-        WHERE sale_id = ?
-    "#,
-        last_sale.id
-    )
-        .fetch_all(&app_state.db)
-        .await?
-        .into_iter()
-        .map(|i| i.into())
-        .collect();
+    if result.rows_affected() == 0 {
+        let exists = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM sales WHERE id = ?")
+            .bind(sale_id)
+            .fetch_one(&mut *tx)
+            .await?;
 
-    let mut mutex_guard = printer_state.lock()?;
-    let printer = mutex_guard
-        .as_mut()
-        .ok_or(CommandError::PrinterNotConfigured)?;
+        if exists == 0 {
+            return Err(CommandError::SaleNotFound);
+        }
 
-    let store = app.get_store("store.json").unwrap();
-    let some_store = store.get("ticket-layout");
-    let layout = if let Some(store) = some_store {
-        serde_json::from_value::<PrintingLayout>(store)?
-    } else {
-        PrintingLayout::default()
-    };
+        return Err(CommandError::InvalidInput(
+            intl.t("reports-messages-sale-already-voided")?.to_string(),
+        ));
+    }
 
-    print_tickets(printer, &layout, &last_sale, &items)?;
+    audit::record(
+        &mut tx,
+        actor,
+        AuditAction::SaleVoided,
+        Some(&sale_id.to_string()),
+        None,
+        Some(serde_json::json!({ "voided_at": voided_at, "void_reason": reason })),
+    )
+    .await?;
+    journal::record_void(&mut tx, sale_id).await?;
+    sync::record_void_change(&mut tx, sale_id).await?;
+    tx.commit().await?;
 
     Ok(())
 }
 
-#[tauri::command]
-async fn print_sale(
-    app: AppHandle,
-    app_state: State<'_, AppState>,
-    printer_state: State<'_, PrinterState>,
-    sale_id: i64,
+/// Writes the sales matching the filter, CSV exports being a folder of files.
+async fn write_sales_export(
+    db: &Db,
+    intl: &Intl,
+    settings: &EventSettings,
+    export_settings: &ExportSettings,
+    format: ExportFormat,
+    filter: &ReportFilter,
+    path: &Path,
 ) -> CommandResult<()> {
-    info!("Reprinting tickets of sale {}", sale_id);
+    info!("Exporting to {:?} {:?}", format, path);
+
+    match format {
+        ExportFormat::Xlsx => export_sales_report(db, settings, filter, intl, path).await,
+        ExportFormat::Csv | ExportFormat::Json => {
+            let export = load_accounting_export(db, filter, settings).await?;
+
+            if format == ExportFormat::Csv {
+                write_accounting_csv(&export, path, export_settings.csv_decimal_comma)
+            } else {
+                write_accounting_json(&export, path)
+            }
+        }
+    }
+}
 
+/// Last sale of the cash desk, leaving out the ones synced from the others.
+async fn load_last_sale(db: &Db, pos_name: Option<&str>) -> CommandResult<RecordedSale> {
+    let last_sale_id = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM sales WHERE pos_name IS ? ORDER BY order_number DESC LIMIT 1",
+    )
+    .bind(pos_name)
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| CommandError::InvalidInput("No sales recorded yet".to_string()))?;
+
+    load_recorded_sale(db, last_sale_id).await
+}
+
+async fn load_recorded_sale(db: &Db, sale_id: Uuid) -> CommandResult<RecordedSale> {
     let sale = sqlx::query_as!(
         Sale,
         r#"
-        SELECT id as "id: uuid::Uuid", sale_time, total_amount, payment_method
+        SELECT id as "id: uuid::Uuid", order_number as "order_number!", sale_time, total_amount, payment_method, discount_amount, voided_at
         FROM sales
         WHERE id = ?
         "#,
         sale_id
     )
-    .fetch_optional(&app_state.db)
+    .fetch_optional(db)
     .await?
     .ok_or(CommandError::SaleNotFound)?;
 
@@ -403,92 +600,124 @@ async fn print_sale(
                 products.category,
                 products.name,
                 products.price,
-                products.is_deleted AS 'is_product_deleted'
+                products.is_deleted AS 'is_product_deleted',
+                products.ticket_copies
             FROM sale_items
                 JOIN products ON sale_items.product_id = products.id
             WHERE sale_id = ?
     "#,
         sale_id
     )
-        .fetch_all(&app_state.db)
+        .fetch_all(db)
         .await?
         .into_iter()
         .map(|i| i.into())
         .collect();
 
+    Ok(RecordedSale { sale, items })
+}
 
+fn print_recorded_sale(
+    store: &impl SettingsStore,
+    printer_state: &PrinterState,
+    intl: &Intl,
+    recorded: &RecordedSale,
+) -> CommandResult<()> {
     let mut mutex_guard = printer_state.lock()?;
     let printer = mutex_guard
         .as_mut()
         .ok_or(CommandError::PrinterNotConfigured)?;
 
-    let store = app.get_store("store.json").unwrap();
-    let some_store = store.get("ticket-layout");
-    let layout = if let Some(store) = some_store {
-        serde_json::from_value::<PrintingLayout>(store)?
-    } else {
-        PrintingLayout::default()
-    };
+    let layout = load_print_layout(store)?;
+    let settings = load_printer_settings(store)?;
 
-    print_tickets(printer, &layout, &sale, &items)?;
+    print_tickets(printer, &layout, &settings, intl, &recorded.sale, &recorded.items)?;
 
     Ok(())
 }
 
-#[tauri::command]
-async fn get_print_layout(app: AppHandle) -> CommandResult<PrintingLayout> {
-    let store = app
-        .get_store("store.json")
-        .ok_or(CommandError::LoadSettings)?;
+/// Sale of the code, a bare order number being one of the given cash desk as
+/// every desk numbers its sales.
+async fn find_sale(
+    db: &Db,
+    intl: &Intl,
+    code: &str,
+    pos_name: Option<&str>,
+) -> CommandResult<Sale> {
+    let Some(sale_code) = SaleCode::parse(code) else {
+        return Err(CommandError::InvalidInput(
+            intl.t("pos-messages-invalid-sale-code")?.to_string(),
+        ));
+    };
 
-    let some_store = store.get("ticket-layout");
+    let sale = sqlx::query_as::<_, Sale>(
+        r#"
+        SELECT id, order_number, sale_time, total_amount, payment_method, discount_amount, voided_at
+        FROM sales
+        WHERE ($1 IS NULL OR order_number = $1)
+            AND ($2 IS NULL OR hex(id) LIKE $2 || '%')
+            AND ($2 IS NOT NULL OR pos_name IS $3)
+        ORDER BY sale_time DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(sale_code.order_number)
+    .bind(sale_code.id_prefix)
+    .bind(pos_name)
+    .fetch_optional(db)
+    .await?
+    .ok_or(CommandError::SaleNotFound)?;
 
-    if let Some(store) = some_store {
-        serde_json::from_value::<PrintingLayout>(store).map_err(Into::<CommandError>::into)
-    } else {
-        Ok(PrintingLayout::default())
-    }
+    Ok(sale)
 }
 
-#[tauri::command]
-async fn save_print_layout(layout: PrintingLayout, app: AppHandle) -> CommandResult<()> {
-    info!("Saving updated layout {:?}", layout);
+fn load_print_layout(store: &impl SettingsStore) -> CommandResult<PrintingLayout> {
+    load_settings(store, "ticket-layout")
+}
 
-    let store = app
-        .get_store("store.json")
-        .ok_or(CommandError::StoreSettings)?;
+fn load_printer_settings(store: &impl SettingsStore) -> CommandResult<PrinterSettings> {
+    load_settings(store, "printer-settings")
+}
 
-    let layout_value = serde_json::to_value(layout).or(Err(CommandError::StoreSettings))?;
-    store.set("ticket-layout", layout_value);
+fn load_event_settings(store: &impl SettingsStore) -> CommandResult<EventSettings> {
+    load_settings(store, "event-settings")
+}
 
-    Ok(())
+fn load_export_settings(store: &impl SettingsStore) -> CommandResult<ExportSettings> {
+    load_settings(store, "export-settings")
 }
 
-#[tauri::command]
-async fn save_printer_device(
-    app: AppHandle,
-    printer_state: State<'_, PrinterState>,
-    device: UsbDevice,
-) -> CommandResult<()> {
-    info!("Saving printer device {:?}", device);
+fn load_backup_settings(store: &impl SettingsStore) -> CommandResult<BackupSettings> {
+    load_settings(store, "backup-settings")
+}
 
-    #[cfg(debug_assertions)]
-    let driver = ConsoleDriver::open(true);
-    #[cfg(not(debug_assertions))]
-    let driver = UsbDriver::open(device.vendor_id, device.product_id, None)?;
+fn load_terminal_settings(store: &impl SettingsStore) -> CommandResult<TerminalSettings> {
+    load_settings(store, "terminal-settings")
+}
 
-    let new_printer = Printer::new(driver, Protocol::default(), None);
-    let mut mutex_guard = printer_state.lock()?;
-    *mutex_guard = Some(new_printer);
+fn backup_directory(host: &impl Host, settings: &BackupSettings) -> CommandResult<PathBuf> {
+    match &settings.directory {
+        Some(directory) => Ok(directory.clone()),
+        None => Ok(host.data_dir()?.join("backups")),
+    }
+}
 
-    let store = app
-        .get_store("store.json")
-        .ok_or(CommandError::StoreSettings)?;
+fn database_path(host: &impl Host) -> CommandResult<PathBuf> {
+    Ok(host.data_dir()?.join("app.db"))
+}
 
-    let value = serde_json::to_value(device)?;
-    store.set("printer-device", value);
+async fn backup_app_database(host: &impl Host, reason: BackupReason) -> CommandResult<PathBuf> {
+    let settings = load_backup_settings(host)?;
+    let directory = backup_directory(host, &settings)?;
 
-    Ok(())
+    backup::create_backup(
+        &host.db()?,
+        &directory,
+        reason,
+        settings.keep,
+        Local::now().naive_local(),
+    )
+    .await
 }
 
 fn get_string_descriptor(device: &rusb::Device<rusb::Context>, index: Option<u8>) -> Option<String> {
@@ -509,9 +738,7 @@ fn get_string_descriptor(device: &rusb::Device<rusb::Context>, index: Option<u8>
     }
 }
 
-#[tauri::command]
-async fn list_usb_devices(
-) -> CommandResult<Vec<UsbDevice>> {
+fn usb_devices() -> CommandResult<Vec<UsbDevice>> {
     let context = Context::new()?;
     let devices = DeviceList::new_with_context(context)?;
 
@@ -532,119 +759,192 @@ async fn list_usb_devices(
 }
 
 
-#[tauri::command]
-async fn test_print_raw_file(device: UsbDevice, text_to_print: String) -> CommandResult<()> {
-    info!("Attempting to print {:?} on {:?}", text_to_print, device);
+async fn setup_db(host: &impl Host) -> CommandResult<Db> {
+    let settings = load_backup_settings(host).unwrap_or_default();
 
-    let driver = UsbDriver::open(device.vendor_id, device.product_id, None)?;
-    let mut printer = Printer::new(driver, Protocol::default(), None);
+    open_database(
+        &database_path(host)?,
+        &settings,
+        &backup_directory(host, &settings)?,
+    )
+    .await
+}
 
-    println!();
-    printer
-        .debug_mode(Some(DebugMode::Dec))
-        .init()?
-        .writeln(&text_to_print)?
-        .feed()?
-        .print_cut()?;
-    println!();
+/// Opens the database, creating it if needed, and brings its schema up to
+/// date. Pending migrations run on a copy that replaces the database only
+/// once all of them succeeded, so a failure leaves the old database untouched.
+async fn open_database(
+    path: &Path,
+    backup_settings: &BackupSettings,
+    backup_directory: &Path,
+) -> CommandResult<Db> {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
 
-    Ok(())
-}
+    let restored = backup::apply_pending_restore(path)?;
+    if restored {
+        info!("Database restored from a backup");
+    }
+
+    Sqlite::create_database(&format!("sqlite:{}", path.to_string_lossy())).await?;
+
+    let db = connect_db(path).await?;
+
+    let db = match migrate_db(path, &db, backup_settings, backup_directory).await {
+        Ok(None) => db,
+        Ok(Some(migrated_path)) => {
+            db.close().await;
+            backup::replace_database(&migrated_path, path)?;
+
+            connect_db(path).await?
+        }
+        Err(err) => {
+            db.close().await;
+
+            return Err(err);
+        }
+    };
 
-async fn setup_db(app: &App) -> Db {
-    let mut path = app.path().app_data_dir().expect("Failed to get data_dir");
-    if let Err(err) = std::fs::create_dir_all(path.clone()) {
-        panic!("Error creating directory {}", err);
+    if restored {
+        sync::reset_origin(&db).await?;
     }
+    journal::open(&db).await?;
 
-    path.push("app.db");
+    Ok(db)
+}
 
-    Sqlite::create_database(
-        format!(
-            "sqlite:{}",
-            path.to_str().expect("Path should be something")
-        )
-        .as_str(),
+async fn connect_db(path: &Path) -> CommandResult<Db> {
+    let options = SqliteConnectOptions::new().filename(path);
+
+    Ok(SqlitePoolOptions::new().connect_with(options).await?)
+}
+
+/// Starts a transaction that reads before writing, e.g. the next order number
+/// or the last journal hash, taking the write lock right away. Two deferred
+/// transactions would both read, then one would fail with "database is
+/// locked" instead of waiting for the other, as cash desks posting sales at
+/// the same moment do.
+async fn begin_write(db: &Db) -> CommandResult<Transaction<'static, Sqlite>> {
+    Ok(db.begin_with("BEGIN IMMEDIATE").await?)
+}
+
+/// Applies the pending migrations, returning the migrated copy that must
+/// replace the database, if any.
+async fn migrate_db(
+    path: &Path,
+    db: &Db,
+    backup_settings: &BackupSettings,
+    backup_directory: &Path,
+) -> CommandResult<Option<PathBuf>> {
+    if !backup::has_pending_migrations(db, &MIGRATOR).await? {
+        // A new database has nothing to lose, and an up to date one only has
+        // its applied migrations checked
+        MIGRATOR.run(db).await?;
+
+        return Ok(None);
+    }
+
+    backup::create_backup(
+        db,
+        backup_directory,
+        BackupReason::Migration,
+        backup_settings.keep,
+        Local::now().naive_local(),
     )
-    .await
-    .expect("Failed to create database");
+    .await?;
+
+    let migrated_path = path.with_extension("db.migrating");
+    if migrated_path.exists() {
+        std::fs::remove_file(&migrated_path)?;
+    }
+    backup::copy_database(db, &migrated_path).await?;
+
+    let migrated_db = connect_db(&migrated_path).await?;
+    let result = MIGRATOR.run(&migrated_db).await;
+    migrated_db.close().await;
 
-    let db = SqlitePoolOptions::new()
-        .connect(path.to_str().unwrap())
-        .await
-        .expect("Failed to connect to database");
+    if let Err(err) = result {
+        if let Err(remove_err) = std::fs::remove_file(&migrated_path) {
+            log::error!("Failed to remove the migrated copy {:?}", remove_err);
+        }
 
-    sqlx::migrate!("./migrations")
-        .run(&db)
-        .await
-        .expect("Failed to run migrations");
+        return Err(err.into());
+    }
 
-    db
+    Ok(Some(migrated_path))
 }
 
 #[cfg(debug_assertions)]
-fn setup_printer(_app: &App) -> Option<Printer<ConsoleDriver>> {
-    let driver = ConsoleDriver::open(true);
-    Some(Printer::new(driver, Protocol::default(), None))
+fn setup_printer(_store: &impl SettingsStore) -> Option<PrinterDriver> {
+    Some(ConsoleDriver::open(true))
 }
 
 #[cfg(not(debug_assertions))]
-fn setup_printer(app: &App) -> Option<Printer<UsbDriver>> {
-    app.get_store("store.json")
-        .and_then(|store| store.get("printer-device"))
+fn setup_printer(store: &impl SettingsStore) -> Option<PrinterDriver> {
+    store
+        .get_setting("printer-device")
+        .ok()
+        .flatten()
         .and_then(|device| {
             info!("Already configured printer device path found {}", device);
             let device = serde_json::from_value::<UsbDevice>(device).ok()?;
 
             UsbDriver::open(device.vendor_id, device.product_id, None).ok()
         })
-        .map(|driver| {
-            debug!("Existing printer restored");
-            Printer::new(driver, Protocol::default(), None)
-        })
+        .inspect(|_| debug!("Existing printer restored"))
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_log::Builder::new().build())
-        .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_store::Builder::new().build())
-        .invoke_handler(tauri::generate_handler![
-            list_products,
-            create_product,
-            update_product,
-            delete_product,
-            clear_sales_data,
-            process_sale,
-            get_sales_recap,
-            get_today_sales,
-            export_sales,
-            print_last_sale,
-            print_sale,
-            get_print_layout,
-            save_print_layout,
-            save_printer_device,
-            list_usb_devices,
-            test_print_raw_file,
-        ])
-        .setup(|app| {
-            app.store("store.json")?;
-
-            let langid_it = langid!("it");
-            let intl = Intl::try_new(langid_it).expect("Failed to load localization");
-            app.manage(intl);
-
-            let printer = setup_printer(app);
-            app.manage(Arc::new(Mutex::new(printer)));
-
-            tauri::async_runtime::block_on(async move {
-                let db = setup_db(app).await;
-                app.manage(AppState { db });
-            });
-
-            Ok(())
-        })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+/// Opens the configured printer, shared by the commands and the status monitor.
+fn setup_printer_state(store: &impl SettingsStore) -> (PrinterState, PrinterMonitorState) {
+    let driver = setup_printer(store);
+    let printer = Arc::new(Mutex::new(
+        driver
+            .clone()
+            .map(|driver| Printer::new(driver, Protocol::default(), None)),
+    ));
+
+    (printer.clone(), Arc::new(PrinterMonitor::new(driver, printer)))
+}
+
+/// Polls the printer status and notifies the frontend whenever it changes.
+fn watch_printer_status(host: impl Host) {
+    loop {
+        match host.printer_monitor().poll() {
+            Ok(Some(status)) => host.emit_event("printer-status-changed", &status),
+            Ok(None) => {}
+            Err(err) => log::error!("Failed to poll printer status {:?}", err),
+        }
+
+        std::thread::sleep(PRINTER_STATUS_POLL_INTERVAL);
+    }
+}
+
+/// Backs up the database every `interval_minutes`, reading the settings at
+/// every check so that changes apply without a restart.
+async fn run_scheduled_backups(host: impl Host) {
+    let mut last_backup = std::time::Instant::now();
+
+    loop {
+        tokio::time::sleep(BACKUP_SCHEDULE_CHECK_INTERVAL).await;
+
+        let interval_minutes = match load_backup_settings(&host) {
+            Ok(settings) => settings.interval_minutes,
+            Err(err) => {
+                log::error!("Failed to load the backup settings {:?}", err);
+                continue;
+            }
+        };
+
+        if interval_minutes == 0
+            || last_backup.elapsed() < Duration::from_secs(u64::from(interval_minutes) * 60)
+        {
+            continue;
+        }
+
+        last_backup = std::time::Instant::now();
+        if let Err(err) = backup_app_database(&host, BackupReason::Scheduled).await {
+            log::error!("Scheduled backup failed {:?}", err);
+        }
+    }
 }
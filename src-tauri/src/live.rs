@@ -0,0 +1,118 @@
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeDelta};
+use log::error;
+use serde::Serialize;
+use sqlx::{QueryBuilder, SqlitePool};
+
+use crate::{
+    host::Host,
+    load_event_settings,
+    reports::{sales_summary, CategorySummary, EventSettings, FilterScope, ReportFilter},
+    server, CommandResult,
+};
+
+/// Event emitted with the new `LiveTotals` whenever the sales change.
+pub(crate) const SALES_TOTALS_CHANGED_EVENT: &str = "sales-totals-changed";
+
+/// Event emitted when products are added, changed or removed, so that the
+/// screens of every terminal reload the catalog.
+pub(crate) const CATALOG_CHANGED_EVENT: &str = "catalog-changed";
+
+/// Window of the "items sold recently" counter, to show the current rush.
+const RECENT_MINUTES: i64 = 15;
+
+/// What changed the totals, so that a dashboard can react to it.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LiveTotalsChange {
+    Sale,
+    Void,
+    /// Products added, changed or removed from the catalog
+    Catalog,
+    /// Sales history cleared
+    #[cfg(feature = "gui")]
+    Clear,
+    /// Sales or voids received from the other terminals
+    Sync,
+}
+
+/// Totals of the current business day for the live dashboard.
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct LiveTotals {
+    pub change: Option<LiveTotalsChange>,
+    pub business_day: NaiveDate,
+    pub revenue: f64,
+    pub sales_count: i64,
+    pub items_sold: i64,
+    pub by_category: Vec<CategorySummary>,
+    pub recent_minutes: i64,
+    pub recent_items_sold: i64,
+    pub updated_at: NaiveDateTime,
+}
+
+pub(crate) async fn live_totals(
+    db: &SqlitePool,
+    settings: &EventSettings,
+    change: Option<LiveTotalsChange>,
+    now: NaiveDateTime,
+) -> CommandResult<LiveTotals> {
+    let business_day = settings.business_day_of(now);
+    let summary = sales_summary(db, &ReportFilter::business_day(business_day), settings).await?;
+
+    let recent = ReportFilter {
+        from: Some(now - TimeDelta::minutes(RECENT_MINUTES)),
+        ..Default::default()
+    };
+    let mut query = QueryBuilder::new(
+        r#"
+        SELECT COALESCE(SUM(si.quantity), 0)
+        FROM sale_items si
+        JOIN sales s ON s.id = si.sale_id
+        LEFT JOIN products p ON p.id = si.product_id
+        WHERE 1 = 1"#,
+    );
+    recent.push_conditions(&mut query, settings, FilterScope::Items);
+
+    let recent_items_sold = query.build_query_scalar::<i64>().fetch_one(db).await?;
+
+    Ok(LiveTotals {
+        change,
+        business_day,
+        revenue: summary.net_total,
+        sales_count: summary.sales_count,
+        items_sold: summary.items_sold,
+        by_category: summary.by_category,
+        recent_minutes: RECENT_MINUTES,
+        recent_items_sold,
+        updated_at: now,
+    })
+}
+
+/// Emits the new totals after a committed change, to this application and to
+/// the client terminals. Failures are only logged, as the change itself
+/// already succeeded.
+pub(crate) async fn notify_sales_changed(
+    host: &impl Host,
+    db: &SqlitePool,
+    change: LiveTotalsChange,
+) {
+    let totals = match load_event_settings(host) {
+        Ok(settings) => live_totals(db, &settings, Some(change), Local::now().naive_local()).await,
+        Err(err) => Err(err),
+    };
+
+    match totals {
+        Ok(totals) => {
+            host.emit_event(SALES_TOTALS_CHANGED_EVENT, &totals);
+            server::broadcast(host, SALES_TOTALS_CHANGED_EVENT, &totals);
+        }
+        Err(err) => error!("Failed to compute the sales totals: {:?}", err),
+    }
+}
+
+/// Notifies the catalog change, then the totals, which count the products
+/// of each category.
+pub(crate) async fn notify_catalog_changed(host: &impl Host, db: &SqlitePool) {
+    host.emit_event(CATALOG_CHANGED_EVENT, &());
+    server::broadcast(host, CATALOG_CHANGED_EVENT, &());
+
+    notify_sales_changed(host, db, LiveTotalsChange::Catalog).await;
+}
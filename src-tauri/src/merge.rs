@@ -0,0 +1,400 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use chrono::NaiveDateTime;
+use log::{error, info};
+use serde::Serialize;
+use sqlx::{
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    FromRow, SqliteConnection,
+};
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, Actor, AuditAction},
+    audit_product_change, backup, begin_write, journal, sync, CommandResult, Db,
+};
+
+/// Product of the local or the merged database.
+#[derive(Serialize, Debug, Clone, FromRow)]
+pub(crate) struct MergedProduct {
+    pub id: Uuid,
+    pub name: String,
+    pub category: String,
+    pub price: f64,
+    pub is_deleted: bool,
+    pub ticket_copies: Option<i64>,
+}
+
+impl MergedProduct {
+    fn same_details(&self, other: &MergedProduct) -> bool {
+        self.category == other.category
+            && self.price == other.price
+            && self.is_deleted == other.is_deleted
+            && self.ticket_copies == other.ticket_copies
+    }
+}
+
+#[derive(Serialize, Debug, Clone, FromRow)]
+pub(crate) struct MergedSale {
+    pub id: Uuid,
+    pub order_number: Option<i64>,
+    pub sale_time: NaiveDateTime,
+    pub total_amount: f64,
+    pub discount_amount: f64,
+    pub payment_method: Option<String>,
+    pub event_name: Option<String>,
+    pub shift_name: Option<String>,
+    pub operator_id: Option<Uuid>,
+    pub operator_name: Option<String>,
+    pub pos_name: Option<String>,
+    pub voided_at: Option<NaiveDateTime>,
+    pub void_reason: Option<String>,
+}
+
+impl MergedSale {
+    fn same_details(&self, other: &MergedSale) -> bool {
+        self.sale_time == other.sale_time
+            && self.total_amount == other.total_amount
+            && self.discount_amount == other.discount_amount
+    }
+}
+
+#[derive(FromRow)]
+struct MergedSaleItem {
+    id: Uuid,
+    sale_id: Uuid,
+    product_id: Uuid,
+    product_name: String,
+    quantity: i64,
+    price_at_sale: f64,
+}
+
+/// Record of both databases that don't agree, the local one is kept.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub(crate) enum MergeConflict {
+    /// Same product, by id or by name, with a different category, price,
+    /// ticket copies or deletion
+    Product {
+        local: MergedProduct,
+        other: MergedProduct,
+    },
+    /// Same sale with a different time or amount
+    Sale {
+        local: Box<MergedSale>,
+        other: Box<MergedSale>,
+    },
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub(crate) struct MergeReport {
+    pub products_added: i64,
+    /// Products of the other database matched to a local one by name
+    pub products_matched_by_name: i64,
+    pub sales_added: i64,
+    /// Sales already in the database, e.g. from a previous merge
+    pub sales_already_present: i64,
+    pub items_added: i64,
+    /// Voids of sales already present, made on the other terminal
+    pub voids_applied: i64,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Contents of the merged database.
+struct MergeSource {
+    products: Vec<MergedProduct>,
+    sales: Vec<MergedSale>,
+    items: HashMap<Uuid, Vec<MergedSaleItem>>,
+}
+
+/// Merges the products, sales and sale items of another database of this
+/// application into `db`, in one transaction. Records are matched by id, and
+/// products also by name as the catalog doesn't allow two products with the
+/// same name. The source is copied to `work_path` and migrated there, so that
+/// databases of older versions can be merged too. The merge is audited as
+/// made by `actor`, in the same transaction.
+pub(crate) async fn merge_database(
+    db: &Db,
+    actor: &Actor,
+    source_path: &Path,
+    work_path: &Path,
+    migrator: &Migrator,
+) -> CommandResult<MergeReport> {
+    info!("Merging the database {:?}", source_path);
+
+    let merge_actor = Actor::merge(source_path);
+    let source = read_source(source_path, work_path, migrator).await?;
+    let mut report = MergeReport::default();
+
+    let mut tx = begin_write(db).await?;
+
+    let mut product_ids = HashMap::new();
+    for product in &source.products {
+        let local_id = merge_product(&mut tx, &merge_actor, product, &mut report).await?;
+        product_ids.insert(product.id, local_id);
+    }
+
+    for sale in &source.sales {
+        let items = source
+            .items
+            .get(&sale.id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        merge_sale(&mut tx, sale, items, &product_ids, &mut report).await?;
+    }
+
+    audit::record(
+        &mut tx,
+        actor,
+        AuditAction::DatabaseMerged,
+        Some(&source_path.to_string_lossy()),
+        None,
+        Some(serde_json::to_value(&report)?),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Merged {} sales and {} products, {} conflicts",
+        report.sales_added,
+        report.products_added,
+        report.conflicts.len()
+    );
+
+    Ok(report)
+}
+
+async fn read_source(
+    source: &Path,
+    work_path: &Path,
+    migrator: &Migrator,
+) -> CommandResult<MergeSource> {
+    if work_path.exists() {
+        fs::remove_file(work_path)?;
+    }
+
+    let options = SqliteConnectOptions::new().filename(source).read_only(true);
+    let source_db = SqlitePoolOptions::new().connect_with(options).await?;
+    let copied = backup::copy_database(&source_db, work_path).await;
+    source_db.close().await;
+    copied?;
+
+    let read = read_copy(work_path, migrator).await;
+
+    if let Err(err) = fs::remove_file(work_path) {
+        error!("Failed to remove the merged copy {:?}", err);
+    }
+
+    read
+}
+
+async fn read_copy(path: &Path, migrator: &Migrator) -> CommandResult<MergeSource> {
+    let options = SqliteConnectOptions::new().filename(path);
+    let copy = SqlitePoolOptions::new().connect_with(options).await?;
+
+    let read = async {
+        migrator.run(&copy).await?;
+
+        let products = sqlx::query_as::<_, MergedProduct>(
+            "SELECT id, name, category, price, is_deleted, ticket_copies FROM products",
+        )
+        .fetch_all(&copy)
+        .await?;
+
+        let sales = sqlx::query_as::<_, MergedSale>(
+            r#"
+            SELECT id, order_number, sale_time, total_amount, discount_amount, payment_method,
+                event_name, shift_name, operator_id, operator_name, pos_name, voided_at, void_reason
+            FROM sales
+            ORDER BY sale_time
+            "#,
+        )
+        .fetch_all(&copy)
+        .await?;
+
+        let mut items: HashMap<Uuid, Vec<MergedSaleItem>> = HashMap::new();
+        for item in sqlx::query_as::<_, MergedSaleItem>(
+            "SELECT id, sale_id, product_id, product_name, quantity, price_at_sale FROM sale_items",
+        )
+        .fetch_all(&copy)
+        .await?
+        {
+            items.entry(item.sale_id).or_default().push(item);
+        }
+
+        Ok(MergeSource {
+            products,
+            sales,
+            items,
+        })
+    }
+    .await;
+
+    copy.close().await;
+
+    read
+}
+
+/// Returns the local id of the product, adding it when missing.
+async fn merge_product(
+    conn: &mut SqliteConnection,
+    actor: &Actor,
+    product: &MergedProduct,
+    report: &mut MergeReport,
+) -> CommandResult<Uuid> {
+    let select = "SELECT id, name, category, price, is_deleted, ticket_copies FROM products";
+
+    let same_id = sqlx::query_as::<_, MergedProduct>(&format!("{} WHERE id = ?", select))
+        .bind(product.id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let local = match same_id {
+        Some(local) => Some(local),
+        None => {
+            let same_name =
+                sqlx::query_as::<_, MergedProduct>(&format!("{} WHERE name = ?", select))
+                    .bind(&product.name)
+                    .fetch_optional(&mut *conn)
+                    .await?;
+            if same_name.is_some() {
+                report.products_matched_by_name += 1;
+            }
+
+            same_name
+        }
+    };
+
+    let Some(local) = local else {
+        sqlx::query(
+            r#"
+            INSERT INTO products (id, name, category, price, is_deleted, ticket_copies)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(product.id)
+        .bind(&product.name)
+        .bind(&product.category)
+        .bind(product.price)
+        .bind(product.is_deleted)
+        .bind(product.ticket_copies)
+        .execute(&mut *conn)
+        .await?;
+
+        audit_product_change(conn, actor, product.id, None).await?;
+        sync::record_product_change(conn, product.id).await?;
+        report.products_added += 1;
+
+        return Ok(product.id);
+    };
+
+    if !local.same_details(product) {
+        report.conflicts.push(MergeConflict::Product {
+            local: local.clone(),
+            other: product.clone(),
+        });
+    }
+
+    Ok(local.id)
+}
+
+async fn merge_sale(
+    conn: &mut SqliteConnection,
+    sale: &MergedSale,
+    items: &[MergedSaleItem],
+    product_ids: &HashMap<Uuid, Uuid>,
+    report: &mut MergeReport,
+) -> CommandResult<()> {
+    let local = sqlx::query_as::<_, MergedSale>(
+        r#"
+        SELECT id, order_number, sale_time, total_amount, discount_amount, payment_method,
+            event_name, shift_name, operator_id, operator_name, pos_name, voided_at, void_reason
+        FROM sales
+        WHERE id = ?
+        "#,
+    )
+    .bind(sale.id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(local) = local {
+        report.sales_already_present += 1;
+
+        if !local.same_details(sale) {
+            report.conflicts.push(MergeConflict::Sale {
+                local: Box::new(local),
+                other: Box::new(sale.clone()),
+            });
+        } else if local.voided_at.is_none() && sale.voided_at.is_some() {
+            sqlx::query("UPDATE sales SET voided_at = ?, void_reason = ? WHERE id = ?")
+                .bind(sale.voided_at)
+                .bind(&sale.void_reason)
+                .bind(sale.id)
+                .execute(&mut *conn)
+                .await?;
+
+            journal::record_void(conn, sale.id).await?;
+            sync::record_void_change(conn, sale.id).await?;
+            report.voids_applied += 1;
+        }
+
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO sales (id, order_number, sale_time, total_amount, discount_amount, payment_method,
+            event_name, shift_name, operator_id, operator_name, pos_name, voided_at, void_reason)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(sale.id)
+    .bind(sale.order_number)
+    .bind(sale.sale_time)
+    .bind(sale.total_amount)
+    .bind(sale.discount_amount)
+    .bind(&sale.payment_method)
+    .bind(&sale.event_name)
+    .bind(&sale.shift_name)
+    .bind(sale.operator_id)
+    .bind(&sale.operator_name)
+    .bind(&sale.pos_name)
+    .bind(sale.voided_at)
+    .bind(&sale.void_reason)
+    .execute(&mut *conn)
+    .await?;
+
+    for item in items {
+        let product_id = product_ids
+            .get(&item.product_id)
+            .copied()
+            .unwrap_or(item.product_id);
+
+        sqlx::query(
+            r#"
+            INSERT INTO sale_items (id, sale_id, product_id, product_name, quantity, price_at_sale)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(item.id)
+        .bind(sale.id)
+        .bind(product_id)
+        .bind(&item.product_name)
+        .bind(item.quantity)
+        .bind(item.price_at_sale)
+        .execute(&mut *conn)
+        .await?;
+
+        report.items_added += 1;
+    }
+
+    journal::record_sale(conn, sale.id).await?;
+    sync::record_sale_change(conn, sale.id).await?;
+    if sale.voided_at.is_some() {
+        journal::record_void(conn, sale.id).await?;
+        sync::record_void_change(conn, sale.id).await?;
+    }
+    report.sales_added += 1;
+
+    Ok(())
+}
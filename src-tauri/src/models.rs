@@ -3,23 +3,30 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
-#[derive(Deserialize)]
+use crate::operators::Role;
+
+#[derive(Deserialize, Serialize)]
 pub(crate) struct UnsavedProduct {
     pub name: String,
     pub category: String,
     pub price: f64,
+    #[serde(default)]
+    pub ticket_copies: Option<i64>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, FromRow)]
 pub(crate) struct Product {
     pub id: Uuid,
     pub name: String,
     pub category: String,
     pub price: f64,
     pub is_deleted: bool,
+    /// Overrides the number of ticket copies of the product category
+    #[serde(default)]
+    pub ticket_copies: Option<i64>,
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub(crate) struct CartItem {
     pub product_id: Uuid,
     pub name: String,
@@ -27,22 +34,59 @@ pub(crate) struct CartItem {
     pub quantity: i64,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PaymentMethod {
+    Cash,
+    Card,
+}
+
+impl PaymentMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentMethod::Cash => "Cash",
+            PaymentMethod::Card => "Card",
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, FromRow, Serialize)]
 pub(crate) struct Sale {
     pub id: Uuid,
+    pub order_number: i64,
     pub sale_time: NaiveDateTime,
+    /// Amount paid, after the discount
     pub total_amount: f64,
     pub payment_method: Option<String>,
+    pub discount_amount: f64,
+    pub voided_at: Option<NaiveDateTime>,
 }
 
-#[derive(Debug, FromRow, Serialize)]
-pub(crate) struct SaleItem {
-    pub id: Uuid,
-    pub sale_id: Uuid,
-    pub product_id: Uuid,
-    pub product_name: String,
-    pub quantity: i64,
-    pub price_at_sale: f64,
+/// Sale to record, sent by the cash desk where it was made.
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct SaleRequest {
+    pub items: Vec<CartItem>,
+    pub payment_method: PaymentMethod,
+    pub discount: Option<f64>,
+    pub pos_name: Option<String>,
+    /// Whether the cash desk opens its cash drawer for the sale
+    pub open_cash_drawer: bool,
+}
+
+impl SaleRequest {
+    /// Any cashier sells at full price, discounts are up to a supervisor.
+    pub(crate) fn required_role(&self) -> Role {
+        match self.discount {
+            Some(discount) if discount > 0.0 => Role::Supervisor,
+            _ => Role::Cashier,
+        }
+    }
+}
+
+/// Recorded sale with what is needed to print its tickets.
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct RecordedSale {
+    pub sale: Sale,
+    pub items: Vec<(CartItem, Product)>,
 }
 
 #[derive(Debug, FromRow, Serialize)]
@@ -63,7 +107,8 @@ pub(crate) struct CartItemWithProduct {
     pub name: String,
     pub category: String,
     pub is_product_deleted: bool,
-    pub price: f64
+    pub price: f64,
+    pub ticket_copies: Option<i64>,
 }
 
 impl From<CartItemWithProduct> for (CartItem, Product) {
@@ -80,7 +125,8 @@ impl From<CartItemWithProduct> for (CartItem, Product) {
             name: value.name,
             category: value.category,
             is_deleted: value.is_product_deleted,
-            price: value.price
+            price: value.price,
+            ticket_copies: value.ticket_copies,
         };
 
         (item, product)
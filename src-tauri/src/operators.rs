@@ -0,0 +1,611 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, SqliteConnection};
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, Actor, AuditAction},
+    begin_write, CommandError, CommandResult, Db, Intl,
+};
+
+/// What an operator may do, each role allowing what the ones before it do.
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    sqlx::Type,
+    clap::ValueEnum,
+)]
+pub(crate) enum Role {
+    /// Sells and reprints tickets
+    Cashier,
+    /// Voids sales, opens the cash drawer and sees the reports
+    Supervisor,
+    /// Edits the catalog and the settings, clears the sales
+    Admin,
+}
+
+/// Account logging in at the cash desk with a PIN.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, FromRow)]
+pub(crate) struct Operator {
+    pub id: Uuid,
+    pub name: String,
+    pub role: Role,
+    pub is_active: bool,
+}
+
+impl Operator {
+    pub(crate) fn ensure_role(&self, role: Role) -> CommandResult<()> {
+        if self.role < role {
+            warn!("Operator {} is not allowed a {:?} command", self.name, role);
+
+            return Err(CommandError::Forbidden);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct UnsavedOperator {
+    pub name: String,
+    pub role: Role,
+    pub pin: String,
+}
+
+/// New values of an operator, the PIN is only changed when given.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct OperatorUpdate {
+    pub name: String,
+    pub role: Role,
+    pub is_active: bool,
+    pub pin: Option<String>,
+}
+
+/// Operator logged in on this terminal, with the token of their session on
+/// the server when this is a client terminal.
+#[cfg(feature = "gui")]
+#[derive(Default)]
+pub(crate) struct Session(Mutex<Option<(Operator, Option<String>)>>);
+
+#[cfg(feature = "gui")]
+impl Session {
+    pub(crate) fn operator(&self) -> CommandResult<Option<Operator>> {
+        Ok(self
+            .0
+            .lock()?
+            .as_ref()
+            .map(|(operator, _)| operator.clone()))
+    }
+
+    /// Token naming the operator in the requests to the server.
+    pub(crate) fn server_token(&self) -> CommandResult<Option<String>> {
+        Ok(self.0.lock()?.as_ref().and_then(|(_, token)| token.clone()))
+    }
+
+    pub(crate) fn log_in(
+        &self,
+        operator: Operator,
+        server_token: Option<String>,
+    ) -> CommandResult<()> {
+        *self.0.lock()? = Some((operator, server_token));
+
+        Ok(())
+    }
+
+    /// Replaces the account of the logged in operator, e.g. after it was
+    /// edited, keeping their session on the server.
+    pub(crate) fn set_operator(&self, operator: Option<Operator>) -> CommandResult<()> {
+        let mut session = self.0.lock()?;
+        let token = session.take().and_then(|(_, token)| token);
+        *session = operator.map(|operator| (operator, token));
+
+        Ok(())
+    }
+}
+
+/// Sessions expire after this long without requests.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(12 * 60 * 60);
+
+struct OperatorSession {
+    operator_id: Uuid,
+    last_used: Instant,
+}
+
+/// Operators logged in on the client terminals, by the token the server
+/// gave them at login.
+#[derive(Default)]
+pub(crate) struct OperatorSessions(Mutex<HashMap<String, OperatorSession>>);
+
+impl OperatorSessions {
+    /// Starts a session of the operator, returning its token.
+    pub(crate) fn start(&self, operator_id: Uuid) -> CommandResult<String> {
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let mut sessions = self.0.lock()?;
+        sessions.retain(|_, session| session.last_used.elapsed() < SESSION_IDLE_TIMEOUT);
+        sessions.insert(
+            token.clone(),
+            OperatorSession {
+                operator_id,
+                last_used: Instant::now(),
+            },
+        );
+
+        Ok(token)
+    }
+
+    /// Operator of the session, which lasts as long as it's used.
+    pub(crate) fn operator_id(&self, token: &str) -> CommandResult<Uuid> {
+        let mut sessions = self.0.lock()?;
+        match sessions.get_mut(token) {
+            Some(session) if session.last_used.elapsed() < SESSION_IDLE_TIMEOUT => {
+                session.last_used = Instant::now();
+
+                Ok(session.operator_id)
+            }
+            _ => {
+                sessions.remove(token);
+
+                Err(CommandError::NotLoggedIn)
+            }
+        }
+    }
+
+    pub(crate) fn end(&self, token: &str) -> CommandResult<()> {
+        self.0.lock()?.remove(token);
+
+        Ok(())
+    }
+}
+
+/// Wrong PINs in a row after which a terminal must wait before each attempt.
+const MAX_WRONG_PINS: u32 = 5;
+
+const WRONG_PIN_DELAY: Duration = Duration::from_secs(60);
+
+struct WrongPins {
+    count: u32,
+    last: Instant,
+}
+
+/// Wrong PINs entered from each address, so that the short PINs can't be
+/// guessed by trying them all.
+#[derive(Default)]
+pub(crate) struct LoginAttempts(Mutex<HashMap<IpAddr, WrongPins>>);
+
+impl LoginAttempts {
+    /// Refuses the login while the address must wait after too many wrong PINs.
+    pub(crate) fn check(&self, intl: &Intl, address: IpAddr) -> CommandResult<()> {
+        let attempts = self.0.lock()?;
+        match attempts.get(&address) {
+            Some(wrong)
+                if wrong.count >= MAX_WRONG_PINS && wrong.last.elapsed() < WRONG_PIN_DELAY =>
+            {
+                warn!(
+                    "Login from {} refused after {} wrong PINs",
+                    address, wrong.count
+                );
+
+                Err(CommandError::InvalidInput(
+                    intl.t("operators-messages-too-many-attempts")?.to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts a wrong PIN of the address, a right one starting over.
+    pub(crate) fn record(&self, address: IpAddr, success: bool) -> CommandResult<()> {
+        let mut attempts = self.0.lock()?;
+        if success {
+            attempts.remove(&address);
+        } else {
+            let wrong = attempts.entry(address).or_insert(WrongPins {
+                count: 0,
+                last: Instant::now(),
+            });
+            wrong.count += 1;
+            wrong.last = Instant::now();
+        }
+
+        Ok(())
+    }
+}
+
+/// Who is logged in, and whether someone must be before using the POS.
+#[cfg(feature = "gui")]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct SessionStatus {
+    pub operator: Option<Operator>,
+    /// Operators are only required once an account exists
+    pub required: bool,
+}
+
+const OPERATOR_COLUMNS: &str = "SELECT id, name, role, is_active FROM operators";
+
+/// Hashes the PIN with Argon2id. PINs are short enough to be guessed from a
+/// copy of the database, the hash only makes each guess slow. The result
+/// keeps the salt and the parameters, so that the PIN is checked with the
+/// ones it was hashed with.
+fn pin_hash(pin: &str) -> CommandResult<String> {
+    let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes())?;
+
+    Ok(Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)?
+        .to_string())
+}
+
+#[derive(FromRow)]
+struct OperatorPin {
+    id: Uuid,
+    /// Only set for the PINs hashed with SHA-256, before Argon2 was used
+    pin_salt: String,
+    pin_hash: String,
+}
+
+impl OperatorPin {
+    fn is_legacy(&self) -> bool {
+        !self.pin_salt.is_empty()
+    }
+
+    fn matches(&self, pin: &str) -> bool {
+        if self.is_legacy() {
+            let legacy_hash = format!("{:x}", Sha256::digest(format!("{}:{}", self.pin_salt, pin)));
+            return legacy_hash == self.pin_hash;
+        }
+
+        PasswordHash::new(&self.pin_hash)
+            .and_then(|hash| Argon2::default().verify_password(pin.as_bytes(), &hash))
+            .is_ok()
+    }
+}
+
+/// Operator with the PIN, if any, the inactive ones included so that they
+/// keep their PIN should they come back.
+async fn find_by_pin(conn: &mut SqliteConnection, pin: &str) -> CommandResult<Option<OperatorPin>> {
+    let pins = sqlx::query_as::<_, OperatorPin>("SELECT id, pin_salt, pin_hash FROM operators")
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(pins.into_iter().find(|operator| operator.matches(pin)))
+}
+
+/// Saves the hash of the new PIN, clearing the salt of the SHA-256 ones.
+async fn save_pin(conn: &mut SqliteConnection, id: Uuid, pin: &str) -> CommandResult<()> {
+    sqlx::query("UPDATE operators SET pin_salt = '', pin_hash = ? WHERE id = ?")
+        .bind(pin_hash(pin)?)
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+async fn load(conn: &mut SqliteConnection, id: Uuid) -> CommandResult<Option<Operator>> {
+    Ok(
+        sqlx::query_as::<_, Operator>(&format!("{} WHERE id = ?", OPERATOR_COLUMNS))
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?,
+    )
+}
+
+pub(crate) async fn list(db: &Db) -> CommandResult<Vec<Operator>> {
+    Ok(
+        sqlx::query_as::<_, Operator>(&format!("{} ORDER BY name", OPERATOR_COLUMNS))
+            .fetch_all(db)
+            .await?,
+    )
+}
+
+/// Whether the commands need a logged in operator, i.e. any account is active.
+pub(crate) async fn required(db: &Db) -> CommandResult<bool> {
+    let active: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM operators WHERE is_active = 1")
+        .fetch_one(db)
+        .await?;
+
+    Ok(active > 0)
+}
+
+/// Checks that the operator may run a command of the role, returning their
+/// current account. Without operator, commands are only allowed as long as
+/// no account exists, so that a new POS works as before.
+pub(crate) async fn authorize(
+    db: &Db,
+    operator_id: Option<Uuid>,
+    role: Role,
+) -> CommandResult<Option<Operator>> {
+    let Some(operator_id) = operator_id else {
+        if required(db).await? {
+            return Err(CommandError::NotLoggedIn);
+        }
+
+        return Ok(None);
+    };
+
+    let mut conn = db.acquire().await?;
+    let operator = load(&mut conn, operator_id)
+        .await?
+        .filter(|operator| operator.is_active)
+        .ok_or(CommandError::NotLoggedIn)?;
+    operator.ensure_role(role)?;
+
+    Ok(Some(operator))
+}
+
+pub(crate) async fn login(db: &Db, intl: &Intl, pin: &str) -> CommandResult<Operator> {
+    let mut conn = db.acquire().await?;
+    let found = find_by_pin(&mut conn, pin).await?;
+    let operator = match &found {
+        Some(found) => load(&mut conn, found.id).await?,
+        None => None,
+    }
+    .filter(|operator| operator.is_active);
+    drop(conn);
+
+    // Hashed again with Argon2 now that the PIN is known
+    if let Some(found) = found.filter(OperatorPin::is_legacy) {
+        let mut tx = begin_write(db).await?;
+        save_pin(&mut tx, found.id, pin).await?;
+        tx.commit().await?;
+    }
+
+    match operator {
+        Some(operator) => {
+            info!("Operator {} logged in", operator.name);
+
+            Ok(operator)
+        }
+        None => {
+            warn!("Login with a wrong PIN");
+
+            Err(CommandError::InvalidInput(
+                intl.t("operators-messages-wrong-pin")?.to_string(),
+            ))
+        }
+    }
+}
+
+/// Checks the name and the PIN, which identifies the operator at login so
+/// it can't be shared with another one.
+async fn validate(
+    conn: &mut SqliteConnection,
+    intl: &Intl,
+    id: Option<Uuid>,
+    name: &str,
+    pin: Option<&str>,
+) -> CommandResult<()> {
+    let invalid = |key: &str| -> CommandResult<()> {
+        Err(CommandError::InvalidInput(intl.t(key)?.to_string()))
+    };
+
+    if name.trim().is_empty() {
+        return invalid("settings-operators-messages-name-required");
+    }
+
+    let same_name: Option<Uuid> = sqlx::query_scalar("SELECT id FROM operators WHERE name = ?")
+        .bind(name.trim())
+        .fetch_optional(&mut *conn)
+        .await?;
+    if same_name.is_some_and(|same_name| Some(same_name) != id) {
+        return invalid("settings-operators-messages-name-in-use");
+    }
+
+    let Some(pin) = pin else {
+        return Ok(());
+    };
+    if !(4..=8).contains(&pin.len()) || !pin.chars().all(|c| c.is_ascii_digit()) {
+        return invalid("settings-operators-messages-invalid-pin");
+    }
+    let same_pin = find_by_pin(conn, pin).await?;
+    if same_pin.is_some_and(|same_pin| Some(same_pin.id) != id) {
+        return invalid("settings-operators-messages-pin-in-use");
+    }
+
+    Ok(())
+}
+
+async fn active_admins(conn: &mut SqliteConnection) -> CommandResult<i64> {
+    Ok(
+        sqlx::query_scalar("SELECT COUNT(*) FROM operators WHERE is_active = 1 AND role = 'Admin'")
+            .fetch_one(&mut *conn)
+            .await?,
+    )
+}
+
+/// Audited values of the operator, never their PIN.
+fn audited(operator: &Operator, pin_changed: bool) -> CommandResult<Value> {
+    let mut value = serde_json::to_value(operator)?;
+    if pin_changed {
+        value["pin_changed"] = Value::Bool(true);
+    }
+
+    Ok(value)
+}
+
+pub(crate) async fn create(
+    db: &Db,
+    intl: &Intl,
+    actor: &Actor,
+    operator: &UnsavedOperator,
+) -> CommandResult<Operator> {
+    let mut tx = begin_write(db).await?;
+    validate(&mut tx, intl, None, &operator.name, Some(&operator.pin)).await?;
+
+    // Otherwise nobody could manage the operators once logins are required
+    if active_admins(&mut tx).await? == 0 && operator.role != Role::Admin {
+        return Err(CommandError::InvalidInput(
+            intl.t("settings-operators-messages-first-must-be-admin")?
+                .to_string(),
+        ));
+    }
+
+    let id = Uuid::new_v4();
+    sqlx::query(
+        r#"
+        INSERT INTO operators (id, name, role, pin_salt, pin_hash, is_active)
+        VALUES (?, ?, ?, '', ?, 1)
+        "#,
+    )
+    .bind(id)
+    .bind(operator.name.trim())
+    .bind(operator.role)
+    .bind(pin_hash(&operator.pin)?)
+    .execute(&mut *tx)
+    .await?;
+
+    let created = Operator {
+        id,
+        name: operator.name.trim().to_string(),
+        role: operator.role,
+        is_active: true,
+    };
+    audit::record(
+        &mut tx,
+        actor,
+        AuditAction::OperatorCreated,
+        Some(&id.to_string()),
+        None,
+        Some(audited(&created, false)?),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!("Operator {} created as {:?}", created.name, created.role);
+
+    Ok(created)
+}
+
+pub(crate) async fn update(
+    db: &Db,
+    intl: &Intl,
+    actor: &Actor,
+    id: Uuid,
+    update: &OperatorUpdate,
+) -> CommandResult<Operator> {
+    let mut tx = begin_write(db).await?;
+    let before = load(&mut tx, id)
+        .await?
+        .ok_or_else(|| CommandError::InvalidInput(format!("Operator {} not found", id)))?;
+    validate(&mut tx, intl, Some(id), &update.name, update.pin.as_deref()).await?;
+
+    sqlx::query("UPDATE operators SET name = ?, role = ?, is_active = ? WHERE id = ?")
+        .bind(update.name.trim())
+        .bind(update.role)
+        .bind(update.is_active)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    if let Some(pin) = &update.pin {
+        save_pin(&mut tx, id, pin).await?;
+    }
+
+    if before.role == Role::Admin && before.is_active && active_admins(&mut tx).await? == 0 {
+        return Err(CommandError::InvalidInput(
+            intl.t("settings-operators-messages-last-admin")?
+                .to_string(),
+        ));
+    }
+
+    let after = Operator {
+        id,
+        name: update.name.trim().to_string(),
+        role: update.role,
+        is_active: update.is_active,
+    };
+    if after != before || update.pin.is_some() {
+        audit::record(
+            &mut tx,
+            actor,
+            AuditAction::OperatorUpdated,
+            Some(&id.to_string()),
+            Some(audited(&before, false)?),
+            Some(audited(&after, update.pin.is_some())?),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(after)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+    use unic_langid::langid;
+
+    use super::*;
+    use crate::MIGRATOR;
+
+    async fn test_db() -> Db {
+        // One connection, every connection to memory has its own database
+        let db = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        MIGRATOR.run(&db).await.unwrap();
+
+        db
+    }
+
+    #[test]
+    fn pin_hash_keeps_the_salt_and_the_parameters() {
+        let hash = pin_hash("1234").unwrap();
+
+        assert!(hash.starts_with("$argon2id$v=19$m="));
+        assert_ne!(hash, pin_hash("1234").unwrap());
+    }
+
+    #[tokio::test]
+    async fn sha256_pin_is_hashed_again_at_login() {
+        let db = test_db().await;
+        let intl = Intl::try_new(langid!("it")).unwrap();
+        let id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO operators (id, name, role, pin_salt, pin_hash, is_active)
+            VALUES (?, 'Anna', ?, 'salt', ?, 1)
+            "#,
+        )
+        .bind(id)
+        .bind(Role::Admin)
+        .bind(format!("{:x}", Sha256::digest("salt:1234")))
+        .execute(&db)
+        .await
+        .unwrap();
+
+        assert_eq!(login(&db, &intl, "1234").await.unwrap().id, id);
+
+        let (pin_salt, pin_hash): (String, String) =
+            sqlx::query_as("SELECT pin_salt, pin_hash FROM operators WHERE id = ?")
+                .bind(id)
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        assert!(pin_salt.is_empty());
+        assert!(pin_hash.starts_with("$argon2id$"));
+
+        assert_eq!(login(&db, &intl, "1234").await.unwrap().id, id);
+        assert!(login(&db, &intl, "4321").await.is_err());
+    }
+}
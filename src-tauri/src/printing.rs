@@ -1,10 +1,17 @@
 use std::{collections::HashMap, fmt::Debug};
 
-use escpos::{driver::Driver, printer::Printer, utils::JustifyMode};
+use escpos::{
+    driver::Driver,
+    printer::Printer,
+    utils::{JustifyMode, QRCodeCorrectionLevel, QRCodeModel, QRCodeOption, ESC, GS},
+};
+use chrono::NaiveDateTime;
+use fluent_bundle::FluentArgs;
 use log::info;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::{CartItem, CommandResult, Product, Sale};
+use crate::{codepages::*, reports::SalesSummary, CartItem, CommandResult, Intl, Product, Sale};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 enum FontSize {
@@ -34,17 +41,173 @@ pub(crate) struct HeaderLayout {
     content: String,
 }
 
+#[derive(Deserialize, Debug, Serialize, Clone)]
+pub(crate) struct CodeLayout {
+    enabled: bool,
+    size: FontSize,
+    justify: Justify,
+}
+
+#[derive(Deserialize, Debug, Serialize, Clone, Default)]
+pub(crate) struct CopiesLayout {
+    /// Copies of each ticket by product category, one when not listed
+    by_category: HashMap<String, u8>,
+    print_copy_number: bool,
+}
+
+impl CopiesLayout {
+    fn of(&self, product: &Product) -> u8 {
+        if let Some(copies) = product.ticket_copies {
+            return copies.clamp(0, u8::MAX as i64) as u8;
+        }
+
+        self.by_category
+            .get(&product.category)
+            .copied()
+            .unwrap_or(1)
+    }
+}
+
 #[derive(Deserialize, Debug, Serialize, Clone)]
 pub(crate) struct PrintingLayout {
     group_tickets_by_category: bool,
     header: HeaderLayout,
     body: SectionLayout,
     footer: SectionLayout,
+    #[serde(default)]
+    qr_code: CodeLayout,
+    #[serde(default)]
+    barcode: CodeLayout,
+    #[serde(default)]
+    copies: CopiesLayout,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub(crate) enum PaperWidth {
+    Mm58,
+    Mm80,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub(crate) enum DrawerPin {
+    Pin2,
+    Pin5,
+}
+
+#[derive(Deserialize, Debug, Serialize, Clone)]
+pub(crate) struct CashDrawerSettings {
+    pub enabled: bool,
+    pin: DrawerPin,
+    /// Length of the pulse in milliseconds
+    pulse_on_ms: u16,
+    /// Pause after the pulse in milliseconds
+    pulse_off_ms: u16,
+}
+
+#[derive(Deserialize, Debug, Serialize, Clone)]
+pub(crate) struct BuzzerSettings {
+    enabled: bool,
+    times: u8,
+    /// Length of each beep in milliseconds
+    duration_ms: u16,
+}
+
+#[derive(Deserialize, Debug, Serialize, Clone)]
+pub(crate) struct PrinterSettings {
+    paper_width: PaperWidth,
+    /// Overrides the characters per line of the paper width, for printers
+    /// with a different font
+    #[serde(default)]
+    characters_per_line: Option<u8>,
+    code_page: CodePage,
+    #[serde(default)]
+    pub cash_drawer: CashDrawerSettings,
+    #[serde(default)]
+    buzzer: BuzzerSettings,
+}
+
+impl PrinterSettings {
+    fn columns(&self, font_size: &FontSize) -> usize {
+        let characters_per_line = self.characters_per_line.unwrap_or(match self.paper_width {
+            PaperWidth::Mm58 => 32,
+            PaperWidth::Mm80 => 48,
+        });
+
+        let columns = characters_per_line as usize / font_size.multiplier() as usize;
+
+        columns.max(1)
+    }
+}
+
+impl FontSize {
+    fn multiplier(&self) -> u8 {
+        match self {
+            FontSize::Small => 1,
+            FontSize::Normal => 2,
+            FontSize::Large => 3,
+        }
+    }
+}
+
+/// Sale reference decoded from a scanned ticket code.
+#[derive(Debug, PartialEq)]
+pub(crate) struct SaleCode {
+    pub order_number: Option<i64>,
+    /// Leading hex digits of the sale id, uppercase and without dashes
+    pub id_prefix: Option<String>,
+}
+
+impl SaleCode {
+    /// Accepts the QR code and barcode payloads, a bare sale id or a bare order number.
+    pub(crate) fn parse(code: &str) -> Option<Self> {
+        let code = code.trim();
+
+        if let Ok(id) = Uuid::parse_str(code) {
+            return Some(Self {
+                order_number: None,
+                id_prefix: Some(id.simple().to_string().to_uppercase()),
+            });
+        }
+
+        if let Ok(order_number) = code.parse::<i64>() {
+            return Some(Self {
+                order_number: Some(order_number),
+                id_prefix: None,
+            });
+        }
+
+        let (order_number, id) = code.split_once('-')?;
+        let order_number = order_number.parse::<i64>().ok()?;
+        let id_prefix = id.replace('-', "").to_uppercase();
+        if id_prefix.is_empty() || !id_prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        Some(Self {
+            order_number: Some(order_number),
+            id_prefix: Some(id_prefix),
+        })
+    }
+}
+
+/// QR codes carry the order number and the full sale id.
+pub(crate) fn qr_code_payload(sale: &Sale) -> String {
+    format!("{}-{}", sale.order_number, sale.id.simple())
+}
+
+/// Barcodes carry the order number and the first block of the sale id, so that
+/// they still fit on 58mm paper.
+pub(crate) fn barcode_payload(sale: &Sale) -> String {
+    let id = sale.id.simple().to_string();
+
+    format!("{}-{}", sale.order_number, &id[..8])
 }
 
 pub(crate) fn print_tickets<D>(
     printer: &mut Printer<D>,
     layout: &PrintingLayout,
+    settings: &PrinterSettings,
+    intl: &Intl,
     sale: &Sale,
     items: &[(CartItem, Product)],
 ) -> CommandResult<()>
@@ -53,10 +216,16 @@ where
 {
     info!("Printing tickets for sale {}", sale.id);
 
+    printer.page_code(settings.code_page.into())?;
+
     if layout.group_tickets_by_category {
-        print_grouped_tickets(printer, layout, sale, items)?;
+        print_grouped_tickets(printer, layout, settings, intl, sale, items)?;
     } else {
-        print_split_tickets(printer, layout, sale, items)?;
+        print_split_tickets(printer, layout, settings, intl, sale, items)?;
+    }
+
+    if settings.buzzer.enabled {
+        buzz(printer, &settings.buzzer)?;
     }
 
     info!("Completed print for sale {}", sale.id);
@@ -67,6 +236,8 @@ where
 fn print_split_tickets<D>(
     printer: &mut Printer<D>,
     layout: &PrintingLayout,
+    settings: &PrinterSettings,
+    intl: &Intl,
     sale: &Sale,
     items: &[(CartItem, Product)],
 ) -> CommandResult<()>
@@ -74,26 +245,40 @@ where
     D: Driver,
 {
     for item in items {
+        let copies = layout.copies.of(&item.1);
+        if copies == 0 {
+            info!("Skipping tickets for product {:?}, no copies required", item.0.name);
+            continue;
+        }
+
         for i in 0..item.0.quantity {
-            info!(
-                "Printing ticket for product {:?} ({} of {})",
-                item.0.name,
-                i + 1,
-                item.0.quantity
-            );
+            for copy in 1..=copies {
+                info!(
+                    "Printing ticket for product {:?} ({} of {}, copy {} of {})",
+                    item.0.name,
+                    i + 1,
+                    item.0.quantity,
+                    copy,
+                    copies
+                );
 
-            if layout.header.enabled {
-                info!("Printing header");
-                print_header(printer, &layout.header, sale)?;
-            }
+                if layout.header.enabled {
+                    info!("Printing header");
+                    print_header(printer, &layout.header, settings, sale)?;
+                }
 
-            info!("Printing body");
-            print_body(printer, &layout.body, &item.0)?;
+                info!("Printing body");
+                print_body(printer, &layout.body, settings, &item.0)?;
 
-            info!("Printing footer");
-            print_footer(printer, &layout.footer, sale)?;
+                info!("Printing footer");
+                print_footer(printer, &layout.footer, settings, sale)?;
 
-            printer.print_cut()?;
+                print_copy_number(printer, layout, settings, intl, copy, copies)?;
+
+                print_codes(printer, layout, sale)?;
+
+                printer.print_cut()?;
+            }
         }
     }
 
@@ -103,41 +288,93 @@ where
 fn print_grouped_tickets<D>(
     printer: &mut Printer<D>,
     layout: &PrintingLayout,
+    settings: &PrinterSettings,
+    intl: &Intl,
     sale: &Sale,
     items: &[(CartItem, Product)],
 ) -> CommandResult<()>
 where
     D: Driver,
 {
-    let mut groups: HashMap<String, Vec<CartItem>> = HashMap::new();
+    let mut groups: HashMap<String, Vec<(CartItem, u8)>> = HashMap::new();
     for item in items.iter() {
-        if let Some(sale_items) = groups.get_mut(&item.1.category) {
-            sale_items.push(item.0.clone());
-        } else {
-            groups.insert(item.1.category.clone(), vec![item.0.clone()]);
+        let copies = layout.copies.of(&item.1);
+        if copies == 0 {
+            info!("Skipping product {:?}, no copies required", item.0.name);
+            continue;
         }
+
+        groups
+            .entry(item.1.category.clone())
+            .or_default()
+            .push((item.0.clone(), copies));
     }
 
     for (category, items) in groups {
-        info!("Printing ticket for group {:?}", category);
-        print_header(printer, &layout.header, sale)?;
+        // Products asking for fewer copies than others in the same category
+        // are left out of the extra ones
+        let copies = items.iter().map(|(_, copies)| *copies).max().unwrap_or(0);
 
-        for item in items {
-            for _ in 0..item.quantity {
-                print_body(printer, &layout.body, &item)?;
+        for copy in 1..=copies {
+            info!(
+                "Printing ticket for group {:?} (copy {} of {})",
+                category, copy, copies
+            );
+            print_header(printer, &layout.header, settings, sale)?;
+
+            for (item, _) in items.iter().filter(|(_, item_copies)| *item_copies >= copy) {
+                for _ in 0..item.quantity {
+                    print_body(printer, &layout.body, settings, item)?;
+                }
             }
-        }
 
-        print_footer(printer, &layout.footer, sale)?;
+            print_footer(printer, &layout.footer, settings, sale)?;
+
+            print_copy_number(printer, layout, settings, intl, copy, copies)?;
+
+            print_codes(printer, layout, sale)?;
+
+            printer.print_cut()?;
+        }
     }
 
     Ok(())
 }
 
+fn print_copy_number<D>(
+    printer: &mut Printer<D>,
+    layout: &PrintingLayout,
+    settings: &PrinterSettings,
+    intl: &Intl,
+    copy: u8,
+    copies: u8,
+) -> CommandResult<()>
+where
+    D: Driver,
+{
+    if !layout.copies.print_copy_number || copies < 2 {
+        return Ok(())
+    }
+
+    let mut args = FluentArgs::new();
+    args.set("copy", copy);
+    args.set("copies", copies);
+    let text = intl.t_args("tickets-copy-number", Some(&args))?;
+
+    with_layout(printer, &layout.footer, |p| {
+        write_wrapped(p, settings, &layout.footer.font_size, &text)?;
+
+        Ok(())
+    })?
+    .feed()?;
+
+    Ok(())
+}
 
 fn print_header<D>(
     printer: &mut Printer<D>,
     layout: &HeaderLayout,
+    settings: &PrinterSettings,
     _sale: &Sale,
 ) -> CommandResult<()>
 where
@@ -150,7 +387,7 @@ where
     let section_layout: SectionLayout = layout.clone().into();
 
     with_layout(printer, &section_layout, |p| {
-        p.writeln(&layout.content)?;
+        write_wrapped(p, settings, &section_layout.font_size, &layout.content)?;
 
         Ok(())
     })?
@@ -162,6 +399,7 @@ where
 fn print_body<D>(
     printer: &mut Printer<D>,
     layout: &SectionLayout,
+    settings: &PrinterSettings,
     item: &CartItem,
 ) -> CommandResult<()>
 where
@@ -172,7 +410,7 @@ where
     }
 
     with_layout(printer, layout, |p| {
-        p.writeln(&item.name.to_string())?;
+        write_wrapped(p, settings, &layout.font_size, &item.name)?;
 
         Ok(())
     })?
@@ -184,6 +422,7 @@ where
 fn print_footer<D>(
     printer: &mut Printer<D>,
     layout: &SectionLayout,
+    settings: &PrinterSettings,
     sale: &Sale,
 ) -> CommandResult<()>
 where
@@ -196,7 +435,12 @@ where
     let sale_time_str = sale.sale_time.format("%d-%m-%Y %H:%M:%S").to_string();
 
     with_layout(printer, layout, |p| {
-        p.writeln(&format!("#{} - {}", sale.id, sale_time_str))?;
+        write_wrapped(
+            p,
+            settings,
+            &layout.font_size,
+            &format!("#{} - {}", sale.id, sale_time_str),
+        )?;
 
         Ok(())
     })?
@@ -205,27 +449,453 @@ where
     Ok(())
 }
 
-fn with_layout<'a, D, F>(
+/// Sends the drawer kick-out pulse (ESC p) on the connector of the printer.
+/// escpos only sends the pin, leaving the timings to the printer defaults.
+#[cfg(feature = "gui")]
+pub(crate) fn kick_cash_drawer<D>(
+    printer: &mut Printer<D>,
+    settings: &CashDrawerSettings,
+) -> CommandResult<()>
+where
+    D: Driver,
+{
+    info!("Opening cash drawer with settings {:?}", settings);
+
+    let pin = match settings.pin {
+        DrawerPin::Pin2 => 0,
+        DrawerPin::Pin5 => 1,
+    };
+    // Timings are expressed in units of 2ms
+    let on_time = (settings.pulse_on_ms / 2).clamp(1, 255) as u8;
+    let off_time = (settings.pulse_off_ms / 2).clamp(1, 255) as u8;
+
+    printer.custom(&[ESC, b'p', pin, on_time, off_time])?.print()?;
+
+    Ok(())
+}
+
+/// Beeps with ESC B, supported by most kitchen printers with a buzzer.
+fn buzz<D>(printer: &mut Printer<D>, settings: &BuzzerSettings) -> CommandResult<()>
+where
+    D: Driver,
+{
+    info!("Sounding buzzer with settings {:?}", settings);
+
+    let times = settings.times.clamp(1, 9);
+    // Duration is expressed in units of 50ms
+    let duration = (settings.duration_ms / 50).clamp(1, 9) as u8;
+
+    printer.custom(&[ESC, b'B', times, duration])?.print()?;
+
+    Ok(())
+}
+
+/// Prints the printer settings, a ruler as wide as a line and every character
+/// of the code page, to check them against what the printer actually does.
+pub(crate) fn print_test_page<D>(
+    printer: &mut Printer<D>,
+    settings: &PrinterSettings,
+) -> CommandResult<()>
+where
+    D: Driver,
+{
+    info!("Printing test page with settings {:?}", settings);
+
+    let columns = settings.columns(&FontSize::Small);
+
+    printer
+        .init()?
+        .page_code(settings.code_page.into())?
+        .justify(JustifyMode::LEFT)?;
+
+    write_wrapped(
+        printer,
+        settings,
+        &FontSize::Small,
+        &format!(
+            "{:?} - {} col. - {:?}",
+            settings.paper_width, columns, settings.code_page
+        ),
+    )?;
+
+    let ruler: String = (1..=columns)
+        .map(|i| char::from_digit((i % 10) as u32, 10).unwrap_or('-'))
+        .collect();
+    write_wrapped(printer, settings, &FontSize::Small, &ruler)?;
+    printer.feed()?;
+
+    write_wrapped(
+        printer,
+        settings,
+        &FontSize::Small,
+        "Q.tà caffè € àèéìòù ÀÈÉÌÒÙ",
+    )?;
+    printer.feed()?;
+
+    let charset: Vec<(u8, char)> = settings.code_page.charset().collect();
+    for row in charset.chunks(16) {
+        let mut line = format!("{:02X} ", row[0].0).into_bytes();
+        line.extend(row.iter().map(|(byte, _)| *byte));
+        line.push(b'\n');
+
+        printer.custom(&line)?;
+    }
+
+    printer.feed()?.print_cut()?;
+
+    Ok(())
+}
+
+/// Prints the end of day recap, in the small font to fit the product names.
+pub(crate) fn print_sales_summary<D>(
+    printer: &mut Printer<D>,
+    settings: &PrinterSettings,
+    intl: &Intl,
+    summary: &SalesSummary,
+    journal_head: Option<&str>,
+    printed_at: NaiveDateTime,
+) -> CommandResult<()>
+where
+    D: Driver,
+{
+    info!("Printing sales summary from {:?} to {:?}", summary.from, summary.to);
+
+    let font_size = FontSize::Small;
+    let columns = settings.columns(&font_size);
+    let rule = "-".repeat(columns);
+    let format_time = |time: Option<NaiveDateTime>| {
+        time.map(|time| time.format("%d/%m/%Y %H:%M").to_string())
+            .unwrap_or_else(|| "-".to_string())
+    };
+
+    printer
+        .init()?
+        .page_code(settings.code_page.into())?
+        .justify(JustifyMode::CENTER)?
+        .bold(true)?;
+    write_wrapped(printer, settings, &font_size, &intl.t("reports-summary-print-title")?)?;
+    printer.bold(false)?;
+
+    let mut args = FluentArgs::new();
+    args.set("from", format_time(summary.from));
+    args.set("to", format_time(summary.to));
+    write_wrapped(
+        printer,
+        settings,
+        &font_size,
+        &intl.t_args("reports-summary-print-period", Some(&args))?,
+    )?;
+    printer.justify(JustifyMode::LEFT)?;
+    write_wrapped(printer, settings, &font_size, &rule)?;
+
+    let totals = [
+        ("reports-summary-print-sales-count", summary.sales_count.to_string()),
+        ("reports-summary-print-items-sold", summary.items_sold.to_string()),
+        ("reports-summary-print-gross-total", format_amount(summary.gross_total)),
+        ("reports-summary-print-discounts-total", format_amount(summary.discounts_total)),
+        ("reports-summary-print-net-total", format_amount(summary.net_total)),
+        ("reports-summary-print-average-ticket", format_amount(summary.average_ticket)),
+        ("reports-summary-print-items-per-sale", format!("{:.1}", summary.items_per_sale)),
+        (
+            "reports-summary-print-voids",
+            format!("{} ({})", summary.voids_count, format_amount(summary.voids_total)),
+        ),
+    ];
+    for (label, value) in totals {
+        write_columns(printer, settings, &font_size, &intl.t(label)?, &value)?;
+    }
+
+    write_section_title(
+        printer,
+        settings,
+        &font_size,
+        &intl.t("reports-summary-print-by-category-title")?,
+    )?;
+    for category in &summary.by_category {
+        write_columns(
+            printer,
+            settings,
+            &font_size,
+            &format!("{} x{}", category.category, category.quantity),
+            &format_amount(category.total),
+        )?;
+    }
+
+    write_section_title(
+        printer,
+        settings,
+        &font_size,
+        &intl.t("reports-summary-print-by-payment-method-title")?,
+    )?;
+    for payment in &summary.by_payment_method {
+        let label = match payment.payment_method.as_str() {
+            "Cash" => intl.t("reports-payment-method-cash")?,
+            "Card" => intl.t("reports-payment-method-card")?,
+            other => other.into(),
+        };
+
+        write_columns(
+            printer,
+            settings,
+            &font_size,
+            &format!("{} x{}", label, payment.sales_count),
+            &format_amount(payment.total),
+        )?;
+    }
+
+    write_section_title(
+        printer,
+        settings,
+        &font_size,
+        &intl.t("reports-summary-print-products-title")?,
+    )?;
+    for product in &summary.products {
+        write_columns(
+            printer,
+            settings,
+            &font_size,
+            &format!("{} x{}", product.product_name, product.total_quantity_sold),
+            &format_amount(product.total_value_sold),
+        )?;
+    }
+
+    write_wrapped(printer, settings, &font_size, &rule)?;
+    if let Some(journal_head) = journal_head {
+        write_wrapped(
+            printer,
+            settings,
+            &font_size,
+            &intl.t("reports-summary-print-journal-head")?,
+        )?;
+        write_wrapped(printer, settings, &font_size, journal_head)?;
+    }
+    let mut args = FluentArgs::new();
+    args.set("date", format_time(Some(printed_at)));
+    write_wrapped(
+        printer,
+        settings,
+        &font_size,
+        &intl.t_args("reports-summary-print-printed-at", Some(&args))?,
+    )?;
+
+    printer.feed()?.print_cut()?;
+
+    Ok(())
+}
+
+fn write_section_title<D>(
+    printer: &mut Printer<D>,
+    settings: &PrinterSettings,
+    font_size: &FontSize,
+    title: &str,
+) -> CommandResult<()>
+where
+    D: Driver,
+{
+    printer.feed()?.bold(true)?;
+    write_wrapped(printer, settings, font_size, title)?;
+    printer.bold(false)?;
+
+    Ok(())
+}
+
+/// Writes the label on the left and the value aligned to the right margin,
+/// wrapping the label when both don't fit on one line.
+fn write_columns<D>(
+    printer: &mut Printer<D>,
+    settings: &PrinterSettings,
+    font_size: &FontSize,
+    label: &str,
+    value: &str,
+) -> CommandResult<()>
+where
+    D: Driver,
+{
+    let columns = settings.columns(font_size);
+    let label = transcode(label, settings.code_page);
+    let value = transcode(value, settings.code_page);
+    let value_length = value.chars().count();
+
+    let mut lines = wrap(&label, columns.saturating_sub(value_length + 1).max(1));
+    let last = lines.pop().unwrap_or_default();
+    let padding = columns.saturating_sub(last.chars().count() + value_length).max(1);
+    lines.push(format!("{}{}{}", last, " ".repeat(padding), value));
+
+    for line in lines {
+        let mut bytes = encode(&line, settings.code_page);
+        bytes.push(b'\n');
+
+        printer.custom(&bytes)?;
+    }
+
+    Ok(())
+}
+
+fn format_amount(amount: f64) -> String {
+    format!("{:.2} €", amount).replace('.', ",")
+}
+
+/// Transcodes the text to the printer code page and splits it on word
+/// boundaries, so that no line is longer than the paper allows.
+fn write_wrapped<D>(
+    printer: &mut Printer<D>,
+    settings: &PrinterSettings,
+    font_size: &FontSize,
+    text: &str,
+) -> CommandResult<()>
+where
+    D: Driver,
+{
+    for line in wrapped_lines(text, settings.code_page, settings.columns(font_size)) {
+        let mut bytes = encode(&line, settings.code_page);
+        bytes.push(b'\n');
+
+        printer.custom(&bytes)?;
+    }
+
+    Ok(())
+}
+
+/// Lines of the text as printed, each line of the text transcoded on its
+/// own since transcoding drops the line breaks.
+fn wrapped_lines(text: &str, code_page: CodePage, columns: usize) -> Vec<String> {
+    text.lines()
+        .flat_map(|line| wrap(&transcode(line, code_page), columns))
+        .collect()
+}
+
+fn wrap(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = vec![];
+
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_length = 0;
+
+        for word in paragraph.split_whitespace() {
+            let word_length = word.chars().count();
+
+            if line_length > 0 && line_length + 1 + word_length > columns {
+                lines.push(std::mem::take(&mut line));
+                line_length = 0;
+            }
+
+            if line_length > 0 {
+                line.push(' ');
+                line_length += 1;
+            }
+
+            let mut chars = word.chars().peekable();
+            while chars.peek().is_some() {
+                if line_length == columns {
+                    lines.push(std::mem::take(&mut line));
+                    line_length = 0;
+                }
+
+                line.push(chars.next().expect("Peeked char must exist"));
+                line_length += 1;
+            }
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+fn print_codes<D>(
+    printer: &mut Printer<D>,
+    layout: &PrintingLayout,
+    sale: &Sale,
+) -> CommandResult<()>
+where
+    D: Driver,
+{
+    if layout.qr_code.enabled {
+        info!("Printing QR code");
+
+        let size = match layout.qr_code.size {
+            FontSize::Small => 4,
+            FontSize::Normal => 6,
+            FontSize::Large => 8,
+        };
+        let option = QRCodeOption::new(QRCodeModel::Model2, size, QRCodeCorrectionLevel::M);
+
+        justify(printer, &layout.qr_code.justify)?
+            .qrcode_option(&qr_code_payload(sale), option)?
+            .feed()?;
+    }
+
+    if layout.barcode.enabled {
+        info!("Printing barcode");
+
+        let module_width = match layout.barcode.size {
+            FontSize::Small => 2,
+            FontSize::Normal => 3,
+            FontSize::Large => 4,
+        };
+
+        justify(printer, &layout.barcode.justify)?;
+        print_code128(printer, &barcode_payload(sale), module_width)?.feed()?;
+    }
+
+    Ok(())
+}
+
+/// The escpos crate doesn't support Code128, so the GS k command is sent raw,
+/// using code set B and printing the human readable text below the bars.
+fn print_code128<'a, D>(
     printer: &'a mut Printer<D>,
-    layout: &'a SectionLayout,
-    func: F,
+    data: &str,
+    module_width: u8,
 ) -> CommandResult<&'a mut Printer<D>>
 where
     D: Driver,
-    F: FnOnce(&mut Printer<D>) -> CommandResult<()>,
 {
-    match layout.font_size {
-        FontSize::Small => printer.size(1, 1)?,
-        FontSize::Normal => printer.size(2, 2)?,
-        FontSize::Large => printer.size(3, 3)?,
-    };
+    let payload = format!("{{B{}", data);
+
+    let mut cmd = vec![
+        GS, b'h', 80, // height in dots
+        GS, b'w', module_width,
+        GS, b'H', 2, // HRI characters below the barcode
+        GS, b'k', 73, payload.len() as u8,
+    ];
+    cmd.extend_from_slice(payload.as_bytes());
+
+    printer.custom(&cmd)?;
+
+    Ok(printer)
+}
 
-    match layout.justify {
+fn justify<'a, D>(
+    printer: &'a mut Printer<D>,
+    justify: &Justify,
+) -> CommandResult<&'a mut Printer<D>>
+where
+    D: Driver,
+{
+    match justify {
         Justify::Left => printer.justify(JustifyMode::LEFT)?,
         Justify::Center => printer.justify(JustifyMode::CENTER)?,
         Justify::Right => printer.justify(JustifyMode::RIGHT)?,
     };
 
+    Ok(printer)
+}
+
+fn with_layout<'a, D, F>(
+    printer: &'a mut Printer<D>,
+    layout: &'a SectionLayout,
+    func: F,
+) -> CommandResult<&'a mut Printer<D>>
+where
+    D: Driver,
+    F: FnOnce(&mut Printer<D>) -> CommandResult<()>,
+{
+    let multiplier = layout.font_size.multiplier();
+    printer.size(multiplier, multiplier)?;
+
+    justify(printer, &layout.justify)?;
+
     func(printer)?;
 
     printer.reset_size()?;
@@ -253,6 +923,19 @@ impl Default for PrintingLayout {
                 font_size: FontSize::Normal,
                 justify: Justify::Center,
             },
+            qr_code: CodeLayout::default(),
+            barcode: CodeLayout::default(),
+            copies: CopiesLayout::default(),
+        }
+    }
+}
+
+impl Default for CodeLayout {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            size: FontSize::Normal,
+            justify: Justify::Center,
         }
     }
 }
@@ -266,3 +949,68 @@ impl From<HeaderLayout> for SectionLayout {
         }
     }
 }
+
+impl Default for PrinterSettings {
+    fn default() -> Self {
+        Self {
+            paper_width: PaperWidth::Mm80,
+            characters_per_line: None,
+            code_page: CodePage::PC858,
+            cash_drawer: CashDrawerSettings::default(),
+            buzzer: BuzzerSettings::default(),
+        }
+    }
+}
+
+impl Default for CashDrawerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pin: DrawerPin::Pin2,
+            pulse_on_ms: 100,
+            pulse_off_ms: 200,
+        }
+    }
+}
+
+impl Default for BuzzerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            times: 2,
+            duration_ms: 200,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapped_lines_keep_the_line_breaks_of_the_text() {
+        let lines = wrapped_lines(
+            "Sagra di paese\nVia Roma 1\r\n\nGrazie!",
+            CodePage::PC858,
+            32,
+        );
+
+        assert_eq!(lines, vec!["Sagra di paese", "Via Roma 1", "", "Grazie!"]);
+    }
+
+    #[test]
+    fn wrapped_lines_wrap_the_transcoded_text() {
+        // The euro sign is missing from PC437, and takes three columns as EUR
+        let lines = wrapped_lines("Menu completo 12 €\tbevanda inclusa", CodePage::PC437, 16);
+
+        assert_eq!(lines, vec!["Menu completo 12", "EUR bevanda", "inclusa"]);
+    }
+
+    #[test]
+    fn wrap_splits_the_words_longer_than_a_line() {
+        assert_eq!(
+            wrap("Supercalifragilistico", 8),
+            vec!["Supercal", "ifragili", "stico"]
+        );
+    }
+}
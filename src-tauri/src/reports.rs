@@ -0,0 +1,1037 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use chrono::{Days, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, QueryBuilder, Sqlite, SqlitePool};
+use uuid::Uuid;
+
+use crate::{AggregatedSaleItem, CommandResult, PaymentMethod, Sale};
+
+/// Event the sales are recorded for, stored in the "event-settings" key.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct EventSettings {
+    pub name: Option<String>,
+    pub shift: Option<String>,
+    /// Hour at which a business day ends, so a night closing at 2 AM is still
+    /// counted on the previous day
+    #[serde(default = "default_business_day_cutoff_hour")]
+    pub business_day_cutoff_hour: u32,
+    /// Image printed in the header of the PDF report
+    #[serde(default)]
+    pub logo_path: Option<PathBuf>,
+}
+
+fn default_business_day_cutoff_hour() -> u32 {
+    2
+}
+
+impl Default for EventSettings {
+    fn default() -> Self {
+        Self {
+            name: None,
+            shift: None,
+            business_day_cutoff_hour: default_business_day_cutoff_hour(),
+            logo_path: None,
+        }
+    }
+}
+
+impl EventSettings {
+    pub(crate) fn business_day_start(&self, day: NaiveDate) -> NaiveDateTime {
+        let cutoff = NaiveTime::from_hms_opt(self.business_day_cutoff_hour.min(23), 0, 0)
+            .unwrap_or_default();
+
+        day.and_time(cutoff)
+    }
+
+    /// SQLite date modifier shifting a sale time to its business day.
+    fn business_day_modifier(&self) -> String {
+        format!("-{} hours", self.business_day_cutoff_hour.min(23))
+    }
+
+    pub(crate) fn business_day_of(&self, time: NaiveDateTime) -> NaiveDate {
+        let cutoff = TimeDelta::hours(self.business_day_cutoff_hour.min(23).into());
+
+        (time - cutoff).date()
+    }
+}
+
+/// Sales selection shared by the reports. Times are local, as `sale_time`.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub(crate) struct ReportFilter {
+    /// Inclusive
+    pub from: Option<NaiveDateTime>,
+    /// Exclusive
+    pub to: Option<NaiveDateTime>,
+    /// First business day, starting at the cutoff hour
+    pub from_day: Option<NaiveDate>,
+    /// Last business day, inclusive
+    pub to_day: Option<NaiveDate>,
+    pub event: Option<String>,
+    pub shift: Option<String>,
+    pub operator: Option<String>,
+    pub category: Option<String>,
+    pub payment_method: Option<PaymentMethod>,
+    pub pos_name: Option<String>,
+    /// Voided sales are left out of the totals unless asked for
+    pub include_voided: bool,
+}
+
+/// Whether the query reads `sale_items` rows, on which the category applies
+/// directly, or whole sales.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum FilterScope {
+    Sales,
+    Items,
+}
+
+impl ReportFilter {
+    pub(crate) fn business_day(day: NaiveDate) -> Self {
+        Self {
+            from_day: Some(day),
+            to_day: Some(day),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn time_range(
+        &self,
+        settings: &EventSettings,
+    ) -> (Option<NaiveDateTime>, Option<NaiveDateTime>) {
+        let from_day = self.from_day.map(|day| settings.business_day_start(day));
+        let to_day = self
+            .to_day
+            .and_then(|day| day.checked_add_days(Days::new(1)))
+            .map(|day| settings.business_day_start(day));
+
+        let from = match (self.from, from_day) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        let to = match (self.to, to_day) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        (from, to)
+    }
+
+    /// Appends the filter conditions to a query reading `sales` as `s` and,
+    /// for the items scope, `sale_items` as `si` joined with `products` as `p`.
+    /// The query must already contain a `WHERE` clause.
+    pub(crate) fn push_conditions<'a>(
+        &'a self,
+        query: &mut QueryBuilder<'a, Sqlite>,
+        settings: &EventSettings,
+        scope: FilterScope,
+    ) {
+        let (from, to) = self.time_range(settings);
+
+        if !self.include_voided {
+            query.push(" AND s.voided_at IS NULL");
+        }
+
+        if let Some(from) = from {
+            query.push(" AND s.sale_time >= ").push_bind(from);
+        }
+        if let Some(to) = to {
+            query.push(" AND s.sale_time < ").push_bind(to);
+        }
+        if let Some(event) = &self.event {
+            query.push(" AND s.event_name = ").push_bind(event);
+        }
+        if let Some(shift) = &self.shift {
+            query.push(" AND s.shift_name = ").push_bind(shift);
+        }
+        if let Some(operator) = &self.operator {
+            query.push(" AND s.operator_name = ").push_bind(operator);
+        }
+        if let Some(payment_method) = &self.payment_method {
+            query
+                .push(" AND s.payment_method = ")
+                .push_bind(payment_method.as_str());
+        }
+        if let Some(pos_name) = &self.pos_name {
+            query.push(" AND s.pos_name = ").push_bind(pos_name);
+        }
+        if let Some(category) = &self.category {
+            match scope {
+                FilterScope::Items => {
+                    query.push(" AND p.category = ").push_bind(category);
+                }
+                FilterScope::Sales => {
+                    query
+                        .push(
+                            " AND EXISTS (SELECT 1 FROM sale_items fsi JOIN products fp ON fp.id = fsi.product_id WHERE fsi.sale_id = s.id AND fp.category = ",
+                        )
+                        .push_bind(category)
+                        .push(")");
+                }
+            }
+        }
+    }
+}
+
+/// Distinct values found in the sales, to fill the report filter choices.
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct ReportFilterOptions {
+    pub events: Vec<String>,
+    pub shifts: Vec<String>,
+    pub operators: Vec<String>,
+    pub categories: Vec<String>,
+    pub pos_names: Vec<String>,
+}
+
+pub(crate) async fn list_sales(
+    db: &SqlitePool,
+    filter: &ReportFilter,
+    settings: &EventSettings,
+) -> CommandResult<Vec<Sale>> {
+    let mut query = QueryBuilder::new(
+        r#"
+        SELECT s.id, s.order_number, s.sale_time, s.total_amount, s.payment_method,
+            s.discount_amount, s.voided_at
+        FROM sales s
+        WHERE 1 = 1"#,
+    );
+    filter.push_conditions(&mut query, settings, FilterScope::Sales);
+    query.push(" ORDER BY s.sale_time DESC");
+
+    let sales = query.build_query_as::<Sale>().fetch_all(db).await?;
+
+    Ok(sales)
+}
+
+pub(crate) async fn sales_recap(
+    db: &SqlitePool,
+    filter: &ReportFilter,
+    settings: &EventSettings,
+) -> CommandResult<Vec<AggregatedSaleItem>> {
+    let mut query = QueryBuilder::new(
+        r#"
+        SELECT si.product_id,
+            MAX(si.product_name) AS product_name,
+            SUM(si.quantity) AS total_quantity_sold,
+            SUM(si.quantity * si.price_at_sale) AS total_value_sold
+        FROM sale_items si
+        JOIN sales s ON s.id = si.sale_id
+        LEFT JOIN products p ON p.id = si.product_id
+        WHERE 1 = 1"#,
+    );
+    filter.push_conditions(&mut query, settings, FilterScope::Items);
+    query.push(" GROUP BY si.product_id");
+
+    let item_sales = query
+        .build_query_as::<AggregatedSaleItem>()
+        .fetch_all(db)
+        .await?;
+
+    Ok(item_sales)
+}
+
+pub(crate) async fn report_filter_options(db: &SqlitePool) -> CommandResult<ReportFilterOptions> {
+    let distinct = |sql: &'static str| sqlx::query_scalar::<_, String>(sql).fetch_all(db);
+
+    Ok(ReportFilterOptions {
+        events: distinct(
+            "SELECT DISTINCT event_name FROM sales WHERE event_name IS NOT NULL ORDER BY 1",
+        )
+        .await?,
+        shifts: distinct(
+            "SELECT DISTINCT shift_name FROM sales WHERE shift_name IS NOT NULL ORDER BY 1",
+        )
+        .await?,
+        operators: distinct(
+            "SELECT DISTINCT operator_name FROM sales WHERE operator_name IS NOT NULL ORDER BY 1",
+        )
+        .await?,
+        categories: distinct("SELECT DISTINCT category FROM products ORDER BY 1").await?,
+        pos_names: distinct(
+            "SELECT DISTINCT pos_name FROM sales WHERE pos_name IS NOT NULL ORDER BY 1",
+        )
+        .await?,
+    })
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BreakdownInterval {
+    /// Every hour from the first to the last sale
+    Hour,
+    /// Hours of the day summed over all the days, to spot the rush
+    HourOfDay,
+    /// Business days
+    Day,
+}
+
+/// Series aligned to `SalesBreakdown::buckets`.
+#[derive(Serialize, Debug)]
+pub(crate) struct BreakdownSeries {
+    pub name: String,
+    pub category: String,
+    pub quantity: Vec<i64>,
+    pub value: Vec<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct SalesBreakdown {
+    pub interval: BreakdownInterval,
+    /// "YYYY-MM-DD HH:00", "HH" or "YYYY-MM-DD" labels, by interval
+    pub buckets: Vec<String>,
+    pub sales_count: Vec<i64>,
+    pub items_sold: Vec<i64>,
+    pub total_value: Vec<f64>,
+    pub by_category: Vec<BreakdownSeries>,
+    pub by_product: Vec<BreakdownSeries>,
+}
+
+#[derive(FromRow)]
+struct BreakdownSalesRow {
+    bucket: String,
+    sales_count: i64,
+}
+
+#[derive(FromRow)]
+struct BreakdownItemsRow {
+    bucket: String,
+    category: String,
+    product_name: String,
+    quantity: i64,
+    value: f64,
+}
+
+impl BreakdownInterval {
+    fn push_bucket<'a>(&self, query: &mut QueryBuilder<'a, Sqlite>, settings: &EventSettings) {
+        match self {
+            BreakdownInterval::Hour => query.push("strftime('%Y-%m-%d %H:00', s.sale_time)"),
+            BreakdownInterval::HourOfDay => query.push("strftime('%H', s.sale_time)"),
+            BreakdownInterval::Day => query
+                .push("date(s.sale_time, ")
+                .push_bind(settings.business_day_modifier())
+                .push(")"),
+        };
+    }
+
+    /// Every bucket between the first and the last one found, so that charts
+    /// show the quiet hours too.
+    fn fill_buckets(&self, found: &[String], settings: &EventSettings) -> Vec<String> {
+        match self {
+            BreakdownInterval::HourOfDay => (0..24)
+                .map(|hour| format!("{:02}", (hour + settings.business_day_cutoff_hour) % 24))
+                .collect(),
+            BreakdownInterval::Hour => {
+                let times: Vec<NaiveDateTime> = found
+                    .iter()
+                    .filter_map(|bucket| {
+                        NaiveDateTime::parse_from_str(bucket, "%Y-%m-%d %H:%M").ok()
+                    })
+                    .collect();
+                let (Some(first), Some(last)) = (times.iter().min(), times.iter().max()) else {
+                    return found.to_vec();
+                };
+
+                std::iter::successors(Some(*first), |time| Some(*time + TimeDelta::hours(1)))
+                    .take_while(|time| time <= last)
+                    .map(|time| time.format("%Y-%m-%d %H:00").to_string())
+                    .collect()
+            }
+            BreakdownInterval::Day => {
+                let days: Vec<NaiveDate> = found
+                    .iter()
+                    .filter_map(|bucket| NaiveDate::parse_from_str(bucket, "%Y-%m-%d").ok())
+                    .collect();
+                let (Some(first), Some(last)) = (days.iter().min(), days.iter().max()) else {
+                    return found.to_vec();
+                };
+
+                first
+                    .iter_days()
+                    .take_while(|day| day <= last)
+                    .map(|day| day.format("%Y-%m-%d").to_string())
+                    .collect()
+            }
+        }
+    }
+}
+
+pub(crate) async fn sales_breakdown(
+    db: &SqlitePool,
+    filter: &ReportFilter,
+    settings: &EventSettings,
+    interval: BreakdownInterval,
+) -> CommandResult<SalesBreakdown> {
+    let mut query = QueryBuilder::new("SELECT ");
+    interval.push_bucket(&mut query, settings);
+    query.push(
+        r#" AS bucket,
+            COUNT(*) AS sales_count
+        FROM sales s
+        WHERE 1 = 1"#,
+    );
+    filter.push_conditions(&mut query, settings, FilterScope::Sales);
+    query.push(" GROUP BY bucket");
+
+    let sales_rows = query
+        .build_query_as::<BreakdownSalesRow>()
+        .fetch_all(db)
+        .await?;
+
+    let mut query = QueryBuilder::new("SELECT ");
+    interval.push_bucket(&mut query, settings);
+    query.push(
+        r#" AS bucket,
+            COALESCE(p.category, '') AS category,
+            MAX(si.product_name) AS product_name,
+            SUM(si.quantity) AS quantity,
+            SUM(si.quantity * si.price_at_sale) AS value
+        FROM sale_items si
+        JOIN sales s ON s.id = si.sale_id
+        LEFT JOIN products p ON p.id = si.product_id
+        WHERE 1 = 1"#,
+    );
+    filter.push_conditions(&mut query, settings, FilterScope::Items);
+    query.push(" GROUP BY bucket, p.category, si.product_id");
+
+    let item_rows = query
+        .build_query_as::<BreakdownItemsRow>()
+        .fetch_all(db)
+        .await?;
+
+    let found: Vec<String> = sales_rows
+        .iter()
+        .map(|row| row.bucket.clone())
+        .chain(item_rows.iter().map(|row| row.bucket.clone()))
+        .collect();
+    let buckets = interval.fill_buckets(&found, settings);
+    let positions: HashMap<&str, usize> = buckets
+        .iter()
+        .enumerate()
+        .map(|(i, bucket)| (bucket.as_str(), i))
+        .collect();
+
+    let mut sales_count = vec![0; buckets.len()];
+    for row in &sales_rows {
+        if let Some(&i) = positions.get(row.bucket.as_str()) {
+            sales_count[i] += row.sales_count;
+        }
+    }
+
+    let mut items_sold = vec![0; buckets.len()];
+    let mut total_value = vec![0.0; buckets.len()];
+    let mut by_category: BTreeMap<String, BreakdownSeries> = BTreeMap::new();
+    let mut by_product: BTreeMap<(String, String), BreakdownSeries> = BTreeMap::new();
+    for row in item_rows {
+        let Some(&i) = positions.get(row.bucket.as_str()) else {
+            continue;
+        };
+
+        items_sold[i] += row.quantity;
+        total_value[i] += row.value;
+
+        let empty_series = |name: &str, category: &str| BreakdownSeries {
+            name: name.to_string(),
+            category: category.to_string(),
+            quantity: vec![0; buckets.len()],
+            value: vec![0.0; buckets.len()],
+        };
+
+        let series = by_category
+            .entry(row.category.clone())
+            .or_insert_with(|| empty_series(&row.category, &row.category));
+        series.quantity[i] += row.quantity;
+        series.value[i] += row.value;
+
+        let series = by_product
+            .entry((row.category.clone(), row.product_name.clone()))
+            .or_insert_with(|| empty_series(&row.product_name, &row.category));
+        series.quantity[i] += row.quantity;
+        series.value[i] += row.value;
+    }
+
+    Ok(SalesBreakdown {
+        interval,
+        buckets,
+        sales_count,
+        items_sold,
+        total_value,
+        by_category: by_category.into_values().collect(),
+        by_product: by_product.into_values().collect(),
+    })
+}
+
+#[derive(Serialize, Debug, Clone, FromRow)]
+pub(crate) struct CategorySummary {
+    pub category: String,
+    pub quantity: i64,
+    pub total: f64,
+}
+
+#[derive(Serialize, Debug, FromRow)]
+pub(crate) struct PaymentMethodSummary {
+    pub payment_method: String,
+    pub sales_count: i64,
+    pub total: f64,
+}
+
+/// End of day recap of the sales matching a filter.
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct SalesSummary {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub sales_count: i64,
+    pub items_sold: i64,
+    /// Before discounts
+    pub gross_total: f64,
+    pub discounts_total: f64,
+    pub net_total: f64,
+    pub average_ticket: f64,
+    pub items_per_sale: f64,
+    pub voids_count: i64,
+    pub voids_total: f64,
+    pub by_category: Vec<CategorySummary>,
+    pub by_payment_method: Vec<PaymentMethodSummary>,
+    pub products: Vec<AggregatedSaleItem>,
+}
+
+#[derive(FromRow)]
+struct SalesTotalsRow {
+    sales_count: i64,
+    net_total: f64,
+    discounts_total: f64,
+}
+
+async fn sales_totals(
+    db: &SqlitePool,
+    filter: &ReportFilter,
+    settings: &EventSettings,
+    voided: bool,
+) -> CommandResult<SalesTotalsRow> {
+    let mut query = QueryBuilder::new(
+        r#"
+        SELECT COUNT(*) AS sales_count,
+            COALESCE(SUM(s.total_amount), 0.0) AS net_total,
+            COALESCE(SUM(s.discount_amount), 0.0) AS discounts_total
+        FROM sales s
+        WHERE 1 = 1"#,
+    );
+    filter.push_conditions(&mut query, settings, FilterScope::Sales);
+    if voided {
+        query.push(" AND s.voided_at IS NOT NULL");
+    }
+
+    let totals = query
+        .build_query_as::<SalesTotalsRow>()
+        .fetch_one(db)
+        .await?;
+
+    Ok(totals)
+}
+
+pub(crate) async fn sales_summary(
+    db: &SqlitePool,
+    filter: &ReportFilter,
+    settings: &EventSettings,
+) -> CommandResult<SalesSummary> {
+    let totals = sales_totals(db, filter, settings, false).await?;
+
+    let voids_filter = ReportFilter {
+        include_voided: true,
+        ..filter.clone()
+    };
+    let voids = sales_totals(db, &voids_filter, settings, true).await?;
+
+    let mut query = QueryBuilder::new(
+        r#"
+        SELECT COALESCE(p.category, '') AS category,
+            SUM(si.quantity) AS quantity,
+            SUM(si.quantity * si.price_at_sale) AS total
+        FROM sale_items si
+        JOIN sales s ON s.id = si.sale_id
+        LEFT JOIN products p ON p.id = si.product_id
+        WHERE 1 = 1"#,
+    );
+    filter.push_conditions(&mut query, settings, FilterScope::Items);
+    query.push(" GROUP BY p.category ORDER BY category");
+
+    let by_category = query
+        .build_query_as::<CategorySummary>()
+        .fetch_all(db)
+        .await?;
+
+    let mut query = QueryBuilder::new(
+        r#"
+        SELECT COALESCE(s.payment_method, '') AS payment_method,
+            COUNT(*) AS sales_count,
+            SUM(s.total_amount) AS total
+        FROM sales s
+        WHERE 1 = 1"#,
+    );
+    filter.push_conditions(&mut query, settings, FilterScope::Sales);
+    query.push(" GROUP BY s.payment_method ORDER BY payment_method");
+
+    let by_payment_method = query
+        .build_query_as::<PaymentMethodSummary>()
+        .fetch_all(db)
+        .await?;
+
+    let mut products = sales_recap(db, filter, settings).await?;
+    products.sort_by(|a, b| a.product_name.cmp(&b.product_name));
+
+    let items_sold: i64 = by_category.iter().map(|category| category.quantity).sum();
+    let (average_ticket, items_per_sale) = if totals.sales_count > 0 {
+        (
+            totals.net_total / totals.sales_count as f64,
+            items_sold as f64 / totals.sales_count as f64,
+        )
+    } else {
+        (0.0, 0.0)
+    };
+    let (from, to) = filter.time_range(settings);
+
+    Ok(SalesSummary {
+        from,
+        to,
+        sales_count: totals.sales_count,
+        items_sold,
+        gross_total: totals.net_total + totals.discounts_total,
+        discounts_total: totals.discounts_total,
+        net_total: totals.net_total,
+        average_ticket,
+        items_per_sale,
+        voids_count: voids.sales_count,
+        voids_total: voids.net_total,
+        by_category,
+        by_payment_method,
+        products,
+    })
+}
+
+/// One of the editions, shifts or date ranges being compared.
+#[cfg(feature = "gui")]
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct ComparisonPeriod {
+    pub label: String,
+    pub filter: ReportFilter,
+}
+
+#[cfg(feature = "gui")]
+#[derive(Serialize, Debug)]
+pub(crate) struct ComparisonPeriodTotals {
+    pub label: String,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub sales_count: i64,
+    pub items_sold: i64,
+    pub net_total: f64,
+    pub average_ticket: f64,
+}
+
+#[cfg(feature = "gui")]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ProductTrend {
+    /// Sold in the first and in the last period
+    Kept,
+    /// Not sold in the first period
+    New,
+    /// Not sold in the last period
+    Discontinued,
+}
+
+/// Sales of a product in each period. Products are matched by name, as the
+/// catalog is often recreated between editions.
+#[cfg(feature = "gui")]
+#[derive(Serialize, Debug)]
+pub(crate) struct ProductComparison {
+    pub product_name: String,
+    pub category: String,
+    pub quantity: Vec<i64>,
+    pub value: Vec<f64>,
+    /// Change from the previous period, zero for the first one
+    pub quantity_delta: Vec<i64>,
+    pub value_delta: Vec<f64>,
+    pub trend: ProductTrend,
+}
+
+/// Totals of the n-th business day of each period, so that the first
+/// evening of this year lines up with the first of last year.
+#[cfg(feature = "gui")]
+#[derive(Serialize, Debug)]
+pub(crate) struct DayComparison {
+    pub day_number: usize,
+    pub days: Vec<Option<NaiveDate>>,
+    pub sales_count: Vec<i64>,
+    pub total_value: Vec<f64>,
+}
+
+#[cfg(feature = "gui")]
+#[derive(Serialize, Debug)]
+pub(crate) struct SalesComparison {
+    pub periods: Vec<ComparisonPeriodTotals>,
+    pub products: Vec<ProductComparison>,
+    pub days: Vec<DayComparison>,
+}
+
+#[cfg(feature = "gui")]
+#[derive(FromRow)]
+struct ComparedProductRow {
+    product_name: String,
+    category: String,
+    quantity: i64,
+    value: f64,
+}
+
+#[cfg(feature = "gui")]
+pub(crate) async fn sales_comparison(
+    db: &SqlitePool,
+    periods: &[ComparisonPeriod],
+    settings: &EventSettings,
+) -> CommandResult<SalesComparison> {
+    let count = periods.len();
+    let mut totals = Vec::with_capacity(count);
+    let mut products: BTreeMap<String, ProductComparison> = BTreeMap::new();
+    let mut days: Vec<DayComparison> = Vec::new();
+
+    for (i, period) in periods.iter().enumerate() {
+        let summary = sales_summary(db, &period.filter, settings).await?;
+        totals.push(ComparisonPeriodTotals {
+            label: period.label.clone(),
+            from: summary.from,
+            to: summary.to,
+            sales_count: summary.sales_count,
+            items_sold: summary.items_sold,
+            net_total: summary.net_total,
+            average_ticket: summary.average_ticket,
+        });
+
+        let mut query = QueryBuilder::new(
+            r#"
+            SELECT MAX(TRIM(si.product_name)) AS product_name,
+                COALESCE(MAX(p.category), '') AS category,
+                SUM(si.quantity) AS quantity,
+                SUM(si.quantity * si.price_at_sale) AS value
+            FROM sale_items si
+            JOIN sales s ON s.id = si.sale_id
+            LEFT JOIN products p ON p.id = si.product_id
+            WHERE 1 = 1"#,
+        );
+        period
+            .filter
+            .push_conditions(&mut query, settings, FilterScope::Items);
+        query.push(" GROUP BY LOWER(TRIM(si.product_name))");
+
+        let rows = query
+            .build_query_as::<ComparedProductRow>()
+            .fetch_all(db)
+            .await?;
+
+        for row in rows {
+            let product = products
+                .entry(row.product_name.to_lowercase())
+                .or_insert_with(|| ProductComparison {
+                    product_name: row.product_name.clone(),
+                    category: row.category.clone(),
+                    quantity: vec![0; count],
+                    value: vec![0.0; count],
+                    quantity_delta: vec![0; count],
+                    value_delta: vec![0.0; count],
+                    trend: ProductTrend::Kept,
+                });
+            // The latest name and category win, as the current catalog does
+            product.product_name = row.product_name;
+            if !row.category.is_empty() {
+                product.category = row.category;
+            }
+            product.quantity[i] += row.quantity;
+            product.value[i] += row.value;
+        }
+
+        let breakdown =
+            sales_breakdown(db, &period.filter, settings, BreakdownInterval::Day).await?;
+        for (day_index, bucket) in breakdown.buckets.iter().enumerate() {
+            if days.len() <= day_index {
+                days.push(DayComparison {
+                    day_number: day_index + 1,
+                    days: vec![None; count],
+                    sales_count: vec![0; count],
+                    total_value: vec![0.0; count],
+                });
+            }
+            let day = &mut days[day_index];
+            day.days[i] = NaiveDate::parse_from_str(bucket, "%Y-%m-%d").ok();
+            day.sales_count[i] = breakdown.sales_count[day_index];
+            day.total_value[i] = breakdown.total_value[day_index];
+        }
+    }
+
+    let mut products: Vec<ProductComparison> = products.into_values().collect();
+    for product in products.iter_mut() {
+        for i in 1..count {
+            product.quantity_delta[i] = product.quantity[i] - product.quantity[i - 1];
+            product.value_delta[i] = product.value[i] - product.value[i - 1];
+        }
+
+        let sold_first = product
+            .quantity
+            .first()
+            .is_some_and(|quantity| *quantity > 0);
+        let sold_last = product
+            .quantity
+            .last()
+            .is_some_and(|quantity| *quantity > 0);
+        product.trend = match (sold_first, sold_last) {
+            (false, _) => ProductTrend::New,
+            (true, false) => ProductTrend::Discontinued,
+            (true, true) => ProductTrend::Kept,
+        };
+    }
+    products.sort_by(|a, b| {
+        a.category
+            .cmp(&b.category)
+            .then_with(|| a.product_name.cmp(&b.product_name))
+    });
+
+    Ok(SalesComparison {
+        periods: totals,
+        products,
+        days,
+    })
+}
+
+/// Thresholds of the slow movers analysis.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct ProductPerformanceOptions {
+    /// Products without sales in the last hours are flagged as idle
+    pub idle_hours: u32,
+    /// Products that sold fewer units over the selection are flagged as slow
+    pub min_quantity: i64,
+}
+
+impl Default for ProductPerformanceOptions {
+    fn default() -> Self {
+        Self {
+            idle_hours: 2,
+            min_quantity: 10,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ProductPerformance {
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub category: String,
+    pub quantity: i64,
+    pub revenue: f64,
+    /// Orders including the product
+    pub sales_count: i64,
+    /// 1 for the best selling product
+    pub quantity_rank: usize,
+    pub revenue_rank: usize,
+    /// Shares of the category totals, between 0 and 1
+    pub category_quantity_share: f64,
+    pub category_revenue_share: f64,
+    pub last_sold_at: Option<NaiveDateTime>,
+    /// Not sold in the last `idle_hours`
+    pub idle: bool,
+    /// Sold less than `min_quantity`
+    pub slow: bool,
+}
+
+/// How often the orders with an item of the base category also include an
+/// item of the attached one, such as a drink with a panino.
+#[derive(Serialize, Debug, FromRow)]
+pub(crate) struct AttachRate {
+    pub base_category: String,
+    pub attached_category: String,
+    pub base_orders: i64,
+    pub attached_orders: i64,
+    pub rate: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ProductPerformanceReport {
+    /// Time the idle products are measured from
+    pub reference_time: NaiveDateTime,
+    pub products: Vec<ProductPerformance>,
+    pub attach_rates: Vec<AttachRate>,
+}
+
+#[derive(FromRow)]
+struct ProductPerformanceRow {
+    product_id: Uuid,
+    product_name: String,
+    category: String,
+    quantity: i64,
+    revenue: f64,
+    sales_count: i64,
+    last_sold_at: Option<NaiveDateTime>,
+}
+
+#[derive(FromRow)]
+struct CatalogProductRow {
+    id: Uuid,
+    name: String,
+    category: String,
+}
+
+pub(crate) async fn product_performance(
+    db: &SqlitePool,
+    filter: &ReportFilter,
+    settings: &EventSettings,
+    options: &ProductPerformanceOptions,
+    now: NaiveDateTime,
+) -> CommandResult<ProductPerformanceReport> {
+    let mut query = QueryBuilder::new(
+        r#"
+        SELECT si.product_id,
+            MAX(si.product_name) AS product_name,
+            COALESCE(MAX(p.category), '') AS category,
+            SUM(si.quantity) AS quantity,
+            SUM(si.quantity * si.price_at_sale) AS revenue,
+            COUNT(DISTINCT si.sale_id) AS sales_count,
+            MAX(s.sale_time) AS last_sold_at
+        FROM sale_items si
+        JOIN sales s ON s.id = si.sale_id
+        LEFT JOIN products p ON p.id = si.product_id
+        WHERE 1 = 1"#,
+    );
+    filter.push_conditions(&mut query, settings, FilterScope::Items);
+    query.push(" GROUP BY si.product_id");
+
+    let mut rows = query
+        .build_query_as::<ProductPerformanceRow>()
+        .fetch_all(db)
+        .await?;
+
+    // Products of the catalog that never sold are the slowest movers
+    let mut query =
+        QueryBuilder::new("SELECT id, name, category FROM products WHERE is_deleted = 0");
+    if let Some(category) = &filter.category {
+        query.push(" AND category = ").push_bind(category);
+    }
+    let catalog = query
+        .build_query_as::<CatalogProductRow>()
+        .fetch_all(db)
+        .await?;
+
+    for product in catalog {
+        if !rows.iter().any(|row| row.product_id == product.id) {
+            rows.push(ProductPerformanceRow {
+                product_id: product.id,
+                product_name: product.name,
+                category: product.category,
+                quantity: 0,
+                revenue: 0.0,
+                sales_count: 0,
+                last_sold_at: None,
+            });
+        }
+    }
+
+    let mut category_totals: HashMap<String, (i64, f64)> = HashMap::new();
+    for row in &rows {
+        let totals = category_totals.entry(row.category.clone()).or_default();
+        totals.0 += row.quantity;
+        totals.1 += row.revenue;
+    }
+
+    // Idle time is measured up to the end of the selection when it is past
+    let reference_time = match filter.time_range(settings) {
+        (_, Some(to)) if to < now => to,
+        _ => now,
+    };
+    let idle_since = reference_time - TimeDelta::hours(options.idle_hours.into());
+
+    let share = |value: f64, total: f64| if total > 0.0 { value / total } else { 0.0 };
+
+    let mut products: Vec<ProductPerformance> = rows
+        .into_iter()
+        .map(|row| {
+            let (category_quantity, category_revenue) = category_totals
+                .get(&row.category)
+                .copied()
+                .unwrap_or_default();
+
+            ProductPerformance {
+                category_quantity_share: share(row.quantity as f64, category_quantity as f64),
+                category_revenue_share: share(row.revenue, category_revenue),
+                idle: row.last_sold_at.is_none_or(|time| time < idle_since),
+                slow: row.quantity < options.min_quantity,
+                product_id: row.product_id,
+                product_name: row.product_name,
+                category: row.category,
+                quantity: row.quantity,
+                revenue: row.revenue,
+                sales_count: row.sales_count,
+                quantity_rank: 0,
+                revenue_rank: 0,
+                last_sold_at: row.last_sold_at,
+            }
+        })
+        .collect();
+
+    products.sort_by(|a, b| b.revenue.total_cmp(&a.revenue));
+    for (i, product) in products.iter_mut().enumerate() {
+        product.revenue_rank = i + 1;
+    }
+    products.sort_by(|a, b| {
+        b.quantity
+            .cmp(&a.quantity)
+            .then(b.revenue.total_cmp(&a.revenue))
+    });
+    for (i, product) in products.iter_mut().enumerate() {
+        product.quantity_rank = i + 1;
+    }
+
+    let attach_rates = attach_rates(db, filter, settings).await?;
+
+    Ok(ProductPerformanceReport {
+        reference_time,
+        products,
+        attach_rates,
+    })
+}
+
+async fn attach_rates(
+    db: &SqlitePool,
+    filter: &ReportFilter,
+    settings: &EventSettings,
+) -> CommandResult<Vec<AttachRate>> {
+    // The category filter selects the orders including it, with all their items
+    let mut query = QueryBuilder::new(
+        r#"
+        WITH sale_categories AS (
+            SELECT DISTINCT si.sale_id, COALESCE(p.category, '') AS category
+            FROM sale_items si
+            JOIN sales s ON s.id = si.sale_id
+            LEFT JOIN products p ON p.id = si.product_id
+            WHERE 1 = 1"#,
+    );
+    filter.push_conditions(&mut query, settings, FilterScope::Sales);
+    query.push(
+        r#"
+        ),
+        base_orders AS (
+            SELECT category, COUNT(*) AS orders FROM sale_categories GROUP BY category
+        )
+        SELECT base.category AS base_category,
+            attached.category AS attached_category,
+            bo.orders AS base_orders,
+            COUNT(*) AS attached_orders,
+            CAST(COUNT(*) AS REAL) / bo.orders AS rate
+        FROM sale_categories base
+        JOIN sale_categories attached
+            ON attached.sale_id = base.sale_id AND attached.category <> base.category
+        JOIN base_orders bo ON bo.category = base.category
+        GROUP BY base.category, attached.category
+        ORDER BY base.category, rate DESC"#,
+    );
+
+    let attach_rates = query.build_query_as::<AttachRate>().fetch_all(db).await?;
+
+    Ok(attach_rates)
+}
@@ -0,0 +1,746 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, FromRequestParts, Path, State,
+    },
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use chrono::Local;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+#[cfg(feature = "gui")]
+use tauri::{AppHandle, Manager};
+use tokio::{net::TcpListener, sync::broadcast};
+use uuid::Uuid;
+
+use crate::{
+    audit::Actor,
+    fetch_products, find_sale,
+    host::Host,
+    insert_product, list_today_sales,
+    live::{self, LiveTotals, LiveTotalsChange},
+    load_event_settings, load_last_sale, load_recorded_sale, load_terminal_settings,
+    mark_product_deleted,
+    operators::{
+        self, LoginAttempts, Operator, OperatorSessions, OperatorUpdate, Role, UnsavedOperator,
+    },
+    print_recorded_sale, print_summary, record_sale, record_void,
+    reports::{
+        self, BreakdownInterval, ProductPerformanceOptions, ProductPerformanceReport, ReportFilter,
+        ReportFilterOptions, SalesBreakdown, SalesSummary,
+    },
+    save_product,
+    status::PrinterStatus,
+    summary_filter,
+    sync::{self, SyncBatch, SyncRequest},
+    terminal::{
+        OperatorLogin, ServerEvent, ServerStatus, TerminalSettings, OPERATOR_SESSION_HEADER,
+        POS_NAME_HEADER,
+    },
+    AggregatedSaleItem, CommandError, CommandResult, Db, Product, RecordedSale, Sale, SaleRequest,
+    UnsavedProduct,
+};
+
+/// Events kept for clients that fall behind before they are dropped.
+const EVENTS_CAPACITY: usize = 64;
+
+/// Sends the events to the connected client terminals, available while the
+/// API is served.
+pub(crate) struct ServerEvents(broadcast::Sender<ServerEvent>);
+
+impl Default for ServerEvents {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENTS_CAPACITY);
+
+        ServerEvents(sender)
+    }
+}
+
+#[derive(Clone)]
+struct ServerState<H> {
+    host: H,
+    token: Arc<String>,
+    sessions: Arc<OperatorSessions>,
+    login_attempts: Arc<LoginAttempts>,
+}
+
+/// Token the requests must carry, the API is never served without one.
+pub(crate) fn api_token(settings: &TerminalSettings) -> Option<String> {
+    settings.api_token.clone().filter(|token| !token.is_empty())
+}
+
+/// Serves the API used by the client terminals on the LAN, until the
+/// application is closed. Requests must carry the shared token as a bearer
+/// token, so the server refuses to start without one.
+#[cfg(feature = "gui")]
+pub(crate) async fn serve(app: AppHandle, settings: TerminalSettings) {
+    let Some(token) = api_token(&settings) else {
+        error!("Terminal server not started, the API token is not configured");
+        return;
+    };
+
+    app.manage(ServerEvents::default());
+
+    if let Err(err) = listen(app, token, settings.server_port).await {
+        error!("Terminal server stopped {:?}", err);
+    }
+}
+
+/// Serves the API of the host on every interface until the process ends.
+pub(crate) async fn listen<H: Host>(host: H, token: String, port: u16) -> CommandResult<()> {
+    let state = ServerState {
+        host,
+        token: Arc::new(token),
+        sessions: Arc::default(),
+        login_attempts: Arc::default(),
+    };
+
+    let router = Router::new()
+        .route("/api/status", get(status::<H>))
+        .route(
+            "/api/products",
+            get(list_products::<H>).post(create_product::<H>),
+        )
+        .route(
+            "/api/products/{id}",
+            put(update_product::<H>).delete(delete_product::<H>),
+        )
+        .route("/api/sales", post(process_sale::<H>))
+        .route("/api/sales/today", get(today_sales::<H>))
+        .route("/api/sales/last", get(last_sale::<H>))
+        .route("/api/sales/lookup", post(lookup_sale::<H>))
+        .route("/api/sales/{id}", get(get_sale::<H>))
+        .route("/api/sales/{id}/print", post(print_sale::<H>))
+        .route("/api/sales/{id}/void", post(void_sale::<H>))
+        .route("/api/reports/sales", post(list_sales::<H>))
+        .route("/api/reports/recap", post(sales_recap::<H>))
+        .route("/api/reports/summary", post(sales_summary::<H>))
+        .route("/api/reports/summary/print", post(print_sales_summary::<H>))
+        .route("/api/reports/breakdown", post(sales_breakdown::<H>))
+        .route(
+            "/api/reports/product-performance",
+            post(product_performance::<H>),
+        )
+        .route("/api/reports/filter-options", get(filter_options::<H>))
+        .route("/api/live-totals", get(live_totals::<H>))
+        .route("/api/printer/status", get(printer_status::<H>))
+        .route(
+            "/api/operators",
+            get(list_operators::<H>).post(create_operator::<H>),
+        )
+        .route("/api/operators/login", post(login_operator::<H>))
+        .route("/api/operators/logout", post(logout_operator::<H>))
+        .route("/api/operators/{id}", put(update_operator::<H>))
+        .route("/api/sync/changes", post(sync_changes::<H>))
+        .route("/api/events", get(events::<H>))
+        .with_state(state);
+
+    let address = SocketAddr::from(([0, 0, 0, 0], port));
+    let listener = TcpListener::bind(address).await.inspect_err(|err| {
+        error!("Failed to listen on {}: {:?}", address, err);
+    })?;
+
+    info!("Terminal server listening on {}", address);
+
+    // The address of the terminals limits their wrong PINs
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Sends the event to the client terminals, when this POS is the server.
+pub(crate) fn broadcast<T: Serialize>(host: &impl Host, event: &str, payload: &T) {
+    let Some(events) = host.server_events() else {
+        return;
+    };
+
+    match serde_json::to_value(payload) {
+        Ok(payload) => {
+            // Fails only when no client is connected
+            let _ = events.0.send(ServerEvent {
+                event: event.to_string(),
+                payload,
+            });
+        }
+        Err(err) => error!("Failed to serialize the server event {:?}", err),
+    }
+}
+
+impl IntoResponse for CommandError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            CommandError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            CommandError::Unauthorized => StatusCode::UNAUTHORIZED,
+            CommandError::NotLoggedIn | CommandError::Forbidden => StatusCode::FORBIDDEN,
+            CommandError::SaleNotFound => StatusCode::NOT_FOUND,
+            CommandError::DatabaseUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status, Json(self)).into_response()
+    }
+}
+
+/// Terminal making the request and operator logged in on it, identified by
+/// the headers its client adds.
+struct Caller {
+    pos_name: Option<String>,
+    /// Token of the session started at login, which names the operator
+    session_token: Option<String>,
+    operator_id: Option<Uuid>,
+}
+
+impl Caller {
+    /// Checks that the operator may run a command of the role, against the
+    /// accounts of this database, and returns them as the actor of the changes.
+    async fn authorize(&self, db: &Db, role: Role) -> CommandResult<Actor> {
+        let operator = operators::authorize(db, self.operator_id, role).await?;
+
+        Ok(Actor::operator(operator.as_ref(), self.pos_name.clone()))
+    }
+}
+
+impl<H: Host> FromRequestParts<ServerState<H>> for Caller {
+    type Rejection = CommandError;
+
+    /// Rejects the sessions that expired or never existed, the operator must
+    /// log in again.
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &ServerState<H>,
+    ) -> Result<Self, Self::Rejection> {
+        let pos_name = parts
+            .headers
+            .get(POS_NAME_HEADER)
+            .and_then(|value| String::from_utf8(value.as_bytes().to_vec()).ok());
+        let session_token = parts
+            .headers
+            .get(OPERATOR_SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let operator_id = match &session_token {
+            Some(token) => Some(state.sessions.operator_id(token).inspect_err(|_| {
+                warn!("Terminal request to {} with an expired session", parts.uri);
+            })?),
+            None => None,
+        };
+
+        Ok(Caller {
+            pos_name,
+            session_token,
+            operator_id,
+        })
+    }
+}
+
+/// Guard of the handlers, checking the bearer token of the request.
+struct Authorized;
+
+impl<H: Host> FromRequestParts<ServerState<H>> for Authorized {
+    type Rejection = CommandError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &ServerState<H>,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        match token {
+            // Compared in constant time, so that the time taken doesn't tell
+            // how much of the token was right
+            Some(token) if bool::from(token.as_bytes().ct_eq(state.token.as_bytes())) => {
+                Ok(Authorized)
+            }
+            _ => {
+                warn!("Unauthorized terminal request to {}", parts.uri);
+
+                Err(CommandError::Unauthorized)
+            }
+        }
+    }
+}
+
+async fn status<H: Host>(
+    _: Authorized,
+    State(state): State<ServerState<H>>,
+) -> CommandResult<Json<ServerStatus>> {
+    let settings = load_terminal_settings(&state.host)?;
+
+    Ok(Json(ServerStatus {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        pos_name: settings.pos_name,
+        operators_required: operators::required(&state.host.db()?).await?,
+    }))
+}
+
+async fn list_products<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+) -> CommandResult<Json<Vec<Product>>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Cashier).await?;
+
+    Ok(Json(fetch_products(&db).await?))
+}
+
+async fn create_product<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Json(product): Json<UnsavedProduct>,
+) -> CommandResult<Json<()>> {
+    let db = state.host.db()?;
+    let actor = caller.authorize(&db, Role::Admin).await?;
+    insert_product(&db, &actor, &product).await?;
+
+    live::notify_catalog_changed(&state.host, &db).await;
+
+    Ok(Json(()))
+}
+
+async fn update_product<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Path(id): Path<Uuid>,
+    Json(product): Json<Product>,
+) -> CommandResult<Json<()>> {
+    let db = state.host.db()?;
+    let actor = caller.authorize(&db, Role::Admin).await?;
+    save_product(&db, &actor, &Product { id, ..product }).await?;
+
+    live::notify_catalog_changed(&state.host, &db).await;
+
+    Ok(Json(()))
+}
+
+async fn delete_product<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Path(id): Path<Uuid>,
+) -> CommandResult<Json<()>> {
+    let db = state.host.db()?;
+    let actor = caller.authorize(&db, Role::Admin).await?;
+    mark_product_deleted(&db, &actor, id).await?;
+
+    live::notify_catalog_changed(&state.host, &db).await;
+
+    Ok(Json(()))
+}
+
+/// Records a sale made on a client terminal, which prints its own tickets.
+async fn process_sale<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Json(request): Json<SaleRequest>,
+) -> CommandResult<Json<RecordedSale>> {
+    let db = state.host.db()?;
+    let actor = caller.authorize(&db, request.required_role()).await?;
+    let event_settings = load_event_settings(&state.host)?;
+    let recorded = record_sale(&db, state.host.intl(), &event_settings, &actor, &request).await?;
+
+    live::notify_sales_changed(&state.host, &db, LiveTotalsChange::Sale).await;
+
+    Ok(Json(recorded))
+}
+
+async fn get_sale<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Path(id): Path<Uuid>,
+) -> CommandResult<Json<RecordedSale>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Cashier).await?;
+
+    Ok(Json(load_recorded_sale(&db, id).await?))
+}
+
+/// Last sale, for the client to reprint its tickets.
+async fn last_sale<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+) -> CommandResult<Json<RecordedSale>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Cashier).await?;
+
+    Ok(Json(load_last_sale(&db, caller.pos_name.as_deref()).await?))
+}
+
+/// Body of the sale lookup requests.
+#[derive(Deserialize)]
+struct LookupRequest {
+    code: String,
+}
+
+/// Sale of a code scanned on the client.
+async fn lookup_sale<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Json(request): Json<LookupRequest>,
+) -> CommandResult<Json<Sale>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Cashier).await?;
+
+    Ok(Json(
+        find_sale(
+            &db,
+            state.host.intl(),
+            &request.code,
+            caller.pos_name.as_deref(),
+        )
+        .await?,
+    ))
+}
+
+/// Prints the tickets of the sale on the printer of the server.
+async fn print_sale<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Path(id): Path<Uuid>,
+) -> CommandResult<Json<()>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Cashier).await?;
+    let recorded = load_recorded_sale(&db, id).await?;
+    print_recorded_sale(
+        &state.host,
+        state.host.printer(),
+        state.host.intl(),
+        &recorded,
+    )?;
+
+    Ok(Json(()))
+}
+
+/// Body of the void requests.
+#[derive(Deserialize)]
+struct VoidRequest {
+    reason: Option<String>,
+}
+
+async fn void_sale<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<VoidRequest>,
+) -> CommandResult<Json<()>> {
+    let db = state.host.db()?;
+    let actor = caller.authorize(&db, Role::Supervisor).await?;
+    record_void(&db, state.host.intl(), &actor, id, request.reason).await?;
+
+    live::notify_sales_changed(&state.host, &db, LiveTotalsChange::Void).await;
+
+    Ok(Json(()))
+}
+
+async fn today_sales<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+) -> CommandResult<Json<Vec<Sale>>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Cashier).await?;
+    let settings = load_event_settings(&state.host)?;
+
+    Ok(Json(list_today_sales(&db, &settings).await?))
+}
+
+/// Body of the report requests, with the same fields as the arguments of the
+/// matching commands.
+#[derive(Deserialize)]
+struct ReportRequest {
+    filter: Option<ReportFilter>,
+    interval: Option<BreakdownInterval>,
+    options: Option<ProductPerformanceOptions>,
+}
+
+async fn list_sales<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Json(request): Json<ReportRequest>,
+) -> CommandResult<Json<Vec<Sale>>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Supervisor).await?;
+    let settings = load_event_settings(&state.host)?;
+    let filter = request.filter.unwrap_or_default();
+
+    Ok(Json(reports::list_sales(&db, &filter, &settings).await?))
+}
+
+async fn sales_recap<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Json(request): Json<ReportRequest>,
+) -> CommandResult<Json<Vec<AggregatedSaleItem>>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Supervisor).await?;
+    let settings = load_event_settings(&state.host)?;
+    let filter = request.filter.unwrap_or_default();
+
+    Ok(Json(reports::sales_recap(&db, &filter, &settings).await?))
+}
+
+/// Summary of the given sales, of the current business day by default.
+async fn sales_summary<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Json(request): Json<ReportRequest>,
+) -> CommandResult<Json<SalesSummary>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Supervisor).await?;
+    let settings = load_event_settings(&state.host)?;
+    let filter = summary_filter(request.filter, &settings);
+
+    Ok(Json(reports::sales_summary(&db, &filter, &settings).await?))
+}
+
+/// Prints the summary on the printer of the server.
+async fn print_sales_summary<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Json(request): Json<ReportRequest>,
+) -> CommandResult<Json<()>> {
+    let host = &state.host;
+    let db = host.db()?;
+    caller.authorize(&db, Role::Supervisor).await?;
+    print_summary(host, &db, host.printer(), host.intl(), request.filter).await?;
+
+    Ok(Json(()))
+}
+
+async fn sales_breakdown<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Json(request): Json<ReportRequest>,
+) -> CommandResult<Json<SalesBreakdown>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Supervisor).await?;
+    let settings = load_event_settings(&state.host)?;
+    let filter = request.filter.unwrap_or_default();
+    let interval = request.interval.ok_or_else(|| {
+        CommandError::InvalidInput("The breakdown interval is missing".to_string())
+    })?;
+
+    Ok(Json(
+        reports::sales_breakdown(&db, &filter, &settings, interval).await?,
+    ))
+}
+
+async fn product_performance<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Json(request): Json<ReportRequest>,
+) -> CommandResult<Json<ProductPerformanceReport>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Supervisor).await?;
+    let settings = load_event_settings(&state.host)?;
+
+    Ok(Json(
+        reports::product_performance(
+            &db,
+            &request.filter.unwrap_or_default(),
+            &settings,
+            &request.options.unwrap_or_default(),
+            Local::now().naive_local(),
+        )
+        .await?,
+    ))
+}
+
+async fn filter_options<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+) -> CommandResult<Json<ReportFilterOptions>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Supervisor).await?;
+
+    Ok(Json(reports::report_filter_options(&db).await?))
+}
+
+async fn live_totals<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+) -> CommandResult<Json<LiveTotals>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Cashier).await?;
+    let settings = load_event_settings(&state.host)?;
+
+    Ok(Json(
+        live::live_totals(&db, &settings, None, Local::now().naive_local()).await?,
+    ))
+}
+
+/// Last status read from the printer of the server, none when it has no
+/// printer or doesn't answer status requests.
+async fn printer_status<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+) -> CommandResult<Json<Option<PrinterStatus>>> {
+    caller.authorize(&state.host.db()?, Role::Cashier).await?;
+
+    Ok(Json(state.host.printer_monitor().current_status()?))
+}
+
+/// Body of the login requests.
+#[derive(Deserialize)]
+struct LoginRequest {
+    pin: String,
+}
+
+/// Logs in an operator of the server on a client terminal, starting the
+/// session whose token the terminal then sends in its requests. Terminals
+/// entering too many wrong PINs must wait before each new attempt.
+async fn login_operator<H: Host>(
+    _: Authorized,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    State(state): State<ServerState<H>>,
+    Json(request): Json<LoginRequest>,
+) -> CommandResult<Json<OperatorLogin>> {
+    let intl = state.host.intl();
+    state.login_attempts.check(intl, address.ip())?;
+
+    let login = operators::login(&state.host.db()?, intl, &request.pin).await;
+    state.login_attempts.record(address.ip(), login.is_ok())?;
+    let operator = login?;
+
+    Ok(Json(OperatorLogin {
+        token: state.sessions.start(operator.id)?,
+        operator,
+    }))
+}
+
+/// Ends the session of the operator logged in on the client terminal.
+async fn logout_operator<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+) -> CommandResult<Json<()>> {
+    if let Some(token) = &caller.session_token {
+        state.sessions.end(token)?;
+    }
+
+    Ok(Json(()))
+}
+
+async fn list_operators<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+) -> CommandResult<Json<Vec<Operator>>> {
+    let db = state.host.db()?;
+    caller.authorize(&db, Role::Admin).await?;
+
+    Ok(Json(operators::list(&db).await?))
+}
+
+async fn create_operator<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Json(operator): Json<UnsavedOperator>,
+) -> CommandResult<Json<Operator>> {
+    let db = state.host.db()?;
+    let actor = caller.authorize(&db, Role::Admin).await?;
+
+    Ok(Json(
+        operators::create(&db, state.host.intl(), &actor, &operator).await?,
+    ))
+}
+
+async fn update_operator<H: Host>(
+    _: Authorized,
+    caller: Caller,
+    State(state): State<ServerState<H>>,
+    Path(id): Path<Uuid>,
+    Json(update): Json<OperatorUpdate>,
+) -> CommandResult<Json<Operator>> {
+    let db = state.host.db()?;
+    let actor = caller.authorize(&db, Role::Admin).await?;
+
+    Ok(Json(
+        operators::update(&db, state.host.intl(), &actor, id, &update).await?,
+    ))
+}
+
+/// Changes of the log the calling peer doesn't have yet.
+async fn sync_changes<H: Host>(
+    _: Authorized,
+    State(state): State<ServerState<H>>,
+    Json(request): Json<SyncRequest>,
+) -> CommandResult<Json<SyncBatch>> {
+    let settings = load_terminal_settings(&state.host)?;
+
+    Ok(Json(
+        sync::changes_since(&state.host.db()?, &request.known, settings.pos_name).await?,
+    ))
+}
+
+async fn events<H: Host>(
+    _: Authorized,
+    State(state): State<ServerState<H>>,
+    upgrade: WebSocketUpgrade,
+) -> CommandResult<Response> {
+    let receiver = state
+        .host
+        .server_events()
+        .map(|events| events.0.subscribe())
+        .ok_or(CommandError::DatabaseUnavailable)?;
+
+    Ok(upgrade.on_upgrade(move |socket| forward_events(socket, receiver)))
+}
+
+async fn forward_events(mut socket: WebSocket, mut receiver: broadcast::Receiver<ServerEvent>) {
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Terminal fell behind, {} events skipped", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+
+        let text = match serde_json::to_string(&event) {
+            Ok(text) => text,
+            Err(err) => {
+                error!("Failed to serialize the server event {:?}", err);
+                continue;
+            }
+        };
+
+        if socket.send(Message::Text(text.into())).await.is_err() {
+            // The client disconnected
+            return;
+        }
+    }
+}
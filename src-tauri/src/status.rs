@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use escpos::{
+    driver::Driver,
+    printer::Printer,
+    utils::{RealTimeStatusRequest, RealTimeStatusResponse, DLE, EOT},
+};
+use RealTimeStatusResponse::*;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandError, CommandResult};
+
+/// Printer state decoded from the DLE EOT real-time status responses.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct PrinterStatus {
+    pub connected: bool,
+    pub online: bool,
+    pub cover_open: bool,
+    pub paper_near_end: bool,
+    pub paper_out: bool,
+    pub error: bool,
+}
+
+impl PrinterStatus {
+    fn disconnected() -> Self {
+        Self::default()
+    }
+
+    #[cfg(feature = "gui")]
+    pub(crate) fn is_ready(&self) -> bool {
+        self.connected && self.online && !self.cover_open && !self.paper_out && !self.error
+    }
+}
+
+/// Keeps a handle on the printer driver, next to the one owned by the
+/// `Printer`, to read back the real-time status.
+pub(crate) struct PrinterMonitor<D: Driver> {
+    driver: Mutex<Option<D>>,
+    /// Printer of the tickets, locked while reading the status so that the
+    /// requests and their replies never mix with a ticket being printed
+    printer: Arc<Mutex<Option<Printer<D>>>>,
+    last_status: Mutex<Option<PrinterStatus>>,
+}
+
+impl<D> PrinterMonitor<D>
+where
+    D: Driver,
+{
+    pub(crate) fn new(driver: Option<D>, printer: Arc<Mutex<Option<Printer<D>>>>) -> Self {
+        Self {
+            driver: Mutex::new(driver),
+            printer,
+            last_status: Mutex::new(None),
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub(crate) fn set_driver(&self, driver: Option<D>) -> CommandResult<()> {
+        *self.driver.lock()? = driver;
+        *self.last_status.lock()? = None;
+
+        Ok(())
+    }
+
+    /// Returns `None` when the printer doesn't answer status requests, as the
+    /// console driver used in development, and an error when the requests
+    /// can't be sent, e.g. to an unplugged printer.
+    pub(crate) fn read_status(&self) -> CommandResult<Option<PrinterStatus>> {
+        let _printing = self.printer.lock()?;
+        let guard = self.driver.lock()?;
+        let driver = guard.as_ref().ok_or(CommandError::PrinterNotConfigured)?;
+
+        let Some(printer) = request_status(driver, RealTimeStatusRequest::Printer)? else {
+            return Ok(None);
+        };
+        let printer = parse_status(RealTimeStatusRequest::Printer, Some(printer));
+        let offline_cause = parse_status(
+            RealTimeStatusRequest::OfflineCause,
+            request_status(driver, RealTimeStatusRequest::OfflineCause)?,
+        );
+        let error_cause = parse_status(
+            RealTimeStatusRequest::ErrorCause,
+            request_status(driver, RealTimeStatusRequest::ErrorCause)?,
+        );
+        let paper = parse_status(
+            RealTimeStatusRequest::RollPaperSensor,
+            request_status(driver, RealTimeStatusRequest::RollPaperSensor)?,
+        );
+
+        let status = PrinterStatus {
+            connected: true,
+            online: printer.get(&Online) == Some(&true),
+            cover_open: offline_cause.get(&CoverClosed) == Some(&false),
+            paper_near_end: paper.get(&RollPaperNearEndSensorPaperAdequate) == Some(&false),
+            paper_out: paper.get(&RollPaperEndSensorPaperPresent) == Some(&false)
+                || offline_cause.get(&PrintingStopsDueToPaperEnd) == Some(&true),
+            error: error_cause.get(&UnrecoverableErrorOccurred) == Some(&true)
+                || error_cause.get(&AutocutterErrorOccurred) == Some(&true),
+        };
+
+        Ok(Some(status))
+    }
+
+    /// Reads the status, reporting a printer that can't be reached as
+    /// disconnected.
+    pub(crate) fn current_status(&self) -> CommandResult<Option<PrinterStatus>> {
+        match self.read_status() {
+            Err(CommandError::PrinterNotConfigured) => Err(CommandError::PrinterNotConfigured),
+            Err(err) => {
+                debug!("Printer status not available {:?}", err);
+                Ok(Some(PrinterStatus::disconnected()))
+            }
+            status => status,
+        }
+    }
+
+    /// Reads the status and returns it only when it differs from the last poll.
+    pub(crate) fn poll(&self) -> CommandResult<Option<PrinterStatus>> {
+        let status = match self.current_status() {
+            Ok(Some(status)) => status,
+            Ok(None) | Err(CommandError::PrinterNotConfigured) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let mut last_status = self.last_status.lock()?;
+        if last_status.as_ref() == Some(&status) {
+            return Ok(None);
+        }
+
+        info!("Printer status changed to {:?}", status);
+        *last_status = Some(status.clone());
+
+        Ok(Some(status))
+    }
+}
+
+fn parse_status(
+    request: RealTimeStatusRequest,
+    response: Option<u8>,
+) -> HashMap<RealTimeStatusResponse, bool> {
+    response
+        .and_then(|byte| RealTimeStatusResponse::parse(request, byte).ok())
+        .unwrap_or_default()
+}
+
+/// Sends the status request, returning `None` when the printer doesn't answer.
+fn request_status<D>(driver: &D, request: RealTimeStatusRequest) -> CommandResult<Option<u8>>
+where
+    D: Driver,
+{
+    let (n, a) = request.into();
+    let cmd = if a == 0 { vec![DLE, EOT, n] } else { vec![DLE, EOT, n, a] };
+
+    driver.write(&cmd)?;
+    driver.flush()?;
+
+    let mut buf = [0; 1];
+    match driver.read(&mut buf) {
+        Ok(1) => Ok(Some(buf[0])),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,1250 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{NaiveDateTime, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, QueryBuilder, Sqlite, SqliteConnection};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::{
+    audit::Actor,
+    audit_product_change, begin_write,
+    host::Host,
+    journal,
+    live::{self, LiveTotalsChange},
+    load_product, load_terminal_settings,
+    terminal::{RemoteClient, TerminalMode},
+    CommandResult, Db,
+};
+
+/// Wait between two synchronizations with the peers.
+const SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Changes sent in one answer, the peer asks again for the rest.
+const CHANGES_BATCH_SIZE: i64 = 500;
+
+/// Conflicts listed in the sync status.
+#[cfg(feature = "gui")]
+const CONFLICTS_LIMIT: i64 = 50;
+
+/// Payload left on the changes of cleared sales, which keep their place in
+/// the log so that the sequences stay the same.
+const CLEARED_PAYLOAD: &str = "null";
+
+/// Scheduled and manual synchronizations would apply the same changes twice.
+static SYNC_LOCK: Mutex<()> = Mutex::const_new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+pub(crate) enum SyncEntity {
+    Product,
+    Sale,
+    Void,
+}
+
+/// Change made on a terminal, identified by the terminal (`origin`) and its
+/// position in the changes of that terminal (`sequence`).
+#[derive(Serialize, Deserialize, Debug, Clone, FromRow)]
+pub(crate) struct SyncChange {
+    pub id: Uuid,
+    pub origin: String,
+    pub sequence: i64,
+    pub entity: SyncEntity,
+    pub entity_id: Uuid,
+    /// UTC time of the change, deciding which catalog edit wins
+    pub changed_at: NaiveDateTime,
+    /// JSON snapshot of the entity after the change
+    pub payload: String,
+}
+
+/// Product as saved by a change.
+#[derive(Serialize, Deserialize, Debug, Clone, FromRow)]
+pub(crate) struct ProductSnapshot {
+    pub id: Uuid,
+    pub name: String,
+    pub category: String,
+    pub price: f64,
+    pub is_deleted: bool,
+    pub ticket_copies: Option<i64>,
+    /// Change the edit was based on, an edit made without knowing the
+    /// current version of the product is a conflict
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub base_change_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+struct SaleSnapshot {
+    id: Uuid,
+    order_number: Option<i64>,
+    sale_time: NaiveDateTime,
+    total_amount: f64,
+    discount_amount: f64,
+    payment_method: Option<String>,
+    event_name: Option<String>,
+    shift_name: Option<String>,
+    /// Missing from the changes of older versions
+    #[serde(default)]
+    operator_id: Option<Uuid>,
+    operator_name: Option<String>,
+    pos_name: Option<String>,
+    #[sqlx(skip)]
+    items: Vec<SaleItemSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+struct SaleItemSnapshot {
+    id: Uuid,
+    product_id: Uuid,
+    product_name: String,
+    /// Category of the product, for the placeholder created when the peer
+    /// doesn't know the product
+    category: Option<String>,
+    quantity: i64,
+    price_at_sale: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+struct VoidSnapshot {
+    sale_id: Uuid,
+    voided_at: NaiveDateTime,
+    void_reason: Option<String>,
+}
+
+/// Asks a peer for the changes it has and the caller hasn't, given the last
+/// sequence the caller knows for each terminal.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct SyncRequest {
+    pub known: HashMap<String, i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct SyncBatch {
+    pub origin: String,
+    pub pos_name: Option<String>,
+    pub changes: Vec<SyncChange>,
+    pub has_more: bool,
+}
+
+/// Last synchronization with a peer.
+#[cfg(feature = "gui")]
+#[derive(Serialize, Debug, Clone, FromRow)]
+pub(crate) struct SyncPeer {
+    pub url: String,
+    pub origin: Option<String>,
+    pub pos_name: Option<String>,
+    pub last_attempt_at: Option<NaiveDateTime>,
+    pub last_success_at: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    pub changes_received: i64,
+}
+
+/// Concurrent edits of a product made on two terminals, solved by keeping the
+/// latest one.
+#[cfg(feature = "gui")]
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct SyncConflict {
+    pub id: Uuid,
+    pub detected_at: NaiveDateTime,
+    pub kept: SyncConflictChange,
+    pub discarded: SyncConflictChange,
+}
+
+#[cfg(feature = "gui")]
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct SyncConflictChange {
+    pub origin: String,
+    pub changed_at: NaiveDateTime,
+    pub product: ProductSnapshot,
+}
+
+#[cfg(feature = "gui")]
+#[derive(FromRow)]
+struct SyncConflictRow {
+    id: Uuid,
+    detected_at: NaiveDateTime,
+    kept_origin: String,
+    kept_changed_at: NaiveDateTime,
+    kept_payload: String,
+    discarded_origin: String,
+    discarded_changed_at: NaiveDateTime,
+    discarded_payload: String,
+}
+
+#[cfg(feature = "gui")]
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct SyncStatus {
+    pub origin: String,
+    pub local_changes: i64,
+    pub received_changes: i64,
+    pub peers: Vec<SyncPeer>,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+/// What the applied changes touched, to refresh the screens.
+#[derive(Debug, Default)]
+struct AppliedChanges {
+    count: i64,
+    catalog: bool,
+    sales: bool,
+}
+
+impl AppliedChanges {
+    fn add(&mut self, other: AppliedChanges) {
+        self.count += other.count;
+        self.catalog |= other.catalog;
+        self.sales |= other.sales;
+    }
+}
+
+#[derive(FromRow)]
+struct ProductVersion {
+    change_id: Uuid,
+    origin: String,
+    changed_at: NaiveDateTime,
+}
+
+/// Identifier of this terminal in the change logs, created with the first
+/// change.
+pub(crate) async fn local_origin(conn: &mut SqliteConnection) -> CommandResult<String> {
+    let origin: Option<String> =
+        sqlx::query_scalar("SELECT value FROM sync_state WHERE key = 'origin'")
+            .fetch_optional(&mut *conn)
+            .await?;
+
+    if let Some(origin) = origin {
+        return Ok(origin);
+    }
+
+    let origin = Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO sync_state (key, value) VALUES ('origin', ?)")
+        .bind(&origin)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(origin)
+}
+
+/// Gives a restored database a new identifier: its changes log went back in
+/// time, and the peers would ignore the new changes reusing old sequences.
+pub(crate) async fn reset_origin(db: &Db) -> CommandResult<()> {
+    sqlx::query("DELETE FROM sync_state WHERE key = 'origin'")
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+async fn record_change<T: Serialize>(
+    conn: &mut SqliteConnection,
+    entity: SyncEntity,
+    entity_id: Uuid,
+    payload: &T,
+) -> CommandResult<SyncChange> {
+    let origin = local_origin(conn).await?;
+    let sequence: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(sequence), 0) + 1 FROM sync_changes WHERE origin = ?",
+    )
+    .bind(&origin)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let change = SyncChange {
+        id: Uuid::new_v4(),
+        origin,
+        sequence,
+        entity,
+        entity_id,
+        changed_at: Utc::now().naive_utc(),
+        payload: serde_json::to_string(payload)?,
+    };
+    insert_change(conn, &change).await?;
+
+    Ok(change)
+}
+
+async fn insert_change(conn: &mut SqliteConnection, change: &SyncChange) -> CommandResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO sync_changes (id, origin, sequence, entity, entity_id, changed_at, payload, received_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(change.id)
+    .bind(&change.origin)
+    .bind(change.sequence)
+    .bind(change.entity)
+    .bind(change.entity_id)
+    .bind(change.changed_at)
+    .bind(&change.payload)
+    .bind(Utc::now().naive_utc())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Logs the current state of the product, after a local create, update or
+/// delete in the same transaction.
+pub(crate) async fn record_product_change(
+    conn: &mut SqliteConnection,
+    product_id: Uuid,
+) -> CommandResult<()> {
+    let mut product = sqlx::query_as::<_, ProductSnapshot>(
+        "SELECT id, name, category, price, is_deleted, ticket_copies FROM products WHERE id = ?",
+    )
+    .bind(product_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    product.base_change_id = product_version(conn, product_id)
+        .await?
+        .map(|version| version.change_id);
+
+    let change = record_change(conn, SyncEntity::Product, product_id, &product).await?;
+    save_product_version(conn, product_id, &change).await
+}
+
+/// Logs a sale made on this terminal, in the transaction recording it.
+pub(crate) async fn record_sale_change(
+    conn: &mut SqliteConnection,
+    sale_id: Uuid,
+) -> CommandResult<()> {
+    let mut sale = sqlx::query_as::<_, SaleSnapshot>(
+        r#"
+        SELECT id, order_number, sale_time, total_amount, discount_amount, payment_method,
+            event_name, shift_name, operator_id, operator_name, pos_name
+        FROM sales
+        WHERE id = ?
+        "#,
+    )
+    .bind(sale_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    sale.items = sqlx::query_as::<_, SaleItemSnapshot>(
+        r#"
+        SELECT si.id, si.product_id, si.product_name, p.category, si.quantity, si.price_at_sale
+        FROM sale_items si
+        LEFT JOIN products p ON p.id = si.product_id
+        WHERE si.sale_id = ?
+        "#,
+    )
+    .bind(sale_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    record_change(conn, SyncEntity::Sale, sale_id, &sale).await?;
+
+    Ok(())
+}
+
+/// Logs the void of a sale, in the transaction voiding it.
+pub(crate) async fn record_void_change(
+    conn: &mut SqliteConnection,
+    sale_id: Uuid,
+) -> CommandResult<()> {
+    let void = sqlx::query_as::<_, VoidSnapshot>(
+        "SELECT id AS sale_id, voided_at, void_reason FROM sales WHERE id = ? AND voided_at IS NOT NULL",
+    )
+    .bind(sale_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    record_change(conn, SyncEntity::Void, sale_id, &void).await?;
+
+    Ok(())
+}
+
+/// Drops the sales and voids from the log when the sales are cleared, in the
+/// same transaction. Peers skip the changes received this way.
+#[cfg(feature = "gui")]
+pub(crate) async fn clear_sale_changes(conn: &mut SqliteConnection) -> CommandResult<()> {
+    sqlx::query("UPDATE sync_changes SET payload = ? WHERE entity IN (?, ?)")
+        .bind(CLEARED_PAYLOAD)
+        .bind(SyncEntity::Sale)
+        .bind(SyncEntity::Void)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+async fn product_version(
+    conn: &mut SqliteConnection,
+    product_id: Uuid,
+) -> CommandResult<Option<ProductVersion>> {
+    Ok(sqlx::query_as::<_, ProductVersion>(
+        "SELECT change_id, origin, changed_at FROM sync_product_versions WHERE product_id = ?",
+    )
+    .bind(product_id)
+    .fetch_optional(&mut *conn)
+    .await?)
+}
+
+async fn save_product_version(
+    conn: &mut SqliteConnection,
+    product_id: Uuid,
+    change: &SyncChange,
+) -> CommandResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO sync_product_versions (product_id, change_id, origin, changed_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT(product_id) DO UPDATE SET change_id = excluded.change_id,
+            origin = excluded.origin, changed_at = excluded.changed_at
+        "#,
+    )
+    .bind(product_id)
+    .bind(change.id)
+    .bind(&change.origin)
+    .bind(change.changed_at)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Changes of the log after the sequences known by the peer, in the order
+/// they were made on each terminal.
+pub(crate) async fn changes_since(
+    db: &Db,
+    known: &HashMap<String, i64>,
+    pos_name: Option<String>,
+) -> CommandResult<SyncBatch> {
+    let mut conn = db.acquire().await?;
+    let origin = local_origin(&mut conn).await?;
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT id, origin, sequence, entity, entity_id, changed_at, payload
+        FROM sync_changes
+        WHERE 1 = 1"#,
+    );
+    for (known_origin, sequence) in known {
+        query
+            .push(" AND NOT (origin = ")
+            .push_bind(known_origin)
+            .push(" AND sequence <= ")
+            .push_bind(sequence)
+            .push(")");
+    }
+    query
+        .push(" ORDER BY origin, sequence LIMIT ")
+        .push_bind(CHANGES_BATCH_SIZE + 1);
+
+    let mut changes = query
+        .build_query_as::<SyncChange>()
+        .fetch_all(&mut *conn)
+        .await?;
+
+    let has_more = changes.len() as i64 > CHANGES_BATCH_SIZE;
+    changes.truncate(CHANGES_BATCH_SIZE as usize);
+
+    Ok(SyncBatch {
+        origin,
+        pos_name,
+        changes,
+        has_more,
+    })
+}
+
+/// Last sequence received from each terminal, this one included.
+async fn known_sequences(db: &Db) -> CommandResult<HashMap<String, i64>> {
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        "SELECT origin, MAX(sequence) FROM sync_changes GROUP BY origin",
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.into_iter().collect())
+}
+
+/// Applies the changes received from a peer in one transaction: products
+/// first, so that the sales find them, then sales and voids.
+async fn apply_changes(db: &Db, mut changes: Vec<SyncChange>) -> CommandResult<AppliedChanges> {
+    changes.sort_by_key(|change| {
+        let rank = match change.entity {
+            SyncEntity::Product => 0,
+            SyncEntity::Sale => 1,
+            SyncEntity::Void => 2,
+        };
+        (rank, change.changed_at)
+    });
+
+    let mut tx = begin_write(db).await?;
+    let origin = local_origin(&mut tx).await?;
+    let mut applied = AppliedChanges::default();
+
+    for change in changes {
+        let received: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sync_changes WHERE origin = ? AND sequence = ?",
+        )
+        .bind(&change.origin)
+        .bind(change.sequence)
+        .fetch_one(&mut *tx)
+        .await?;
+        if received > 0 || change.origin == origin {
+            continue;
+        }
+
+        insert_change(&mut tx, &change).await?;
+        if change.payload == CLEARED_PAYLOAD {
+            continue;
+        }
+
+        match change.entity {
+            SyncEntity::Product => {
+                apply_product_change(&mut tx, &change).await?;
+                applied.catalog = true;
+            }
+            SyncEntity::Sale => {
+                apply_sale_change(&mut tx, &change).await?;
+                applied.sales = true;
+            }
+            SyncEntity::Void => {
+                let void: VoidSnapshot = serde_json::from_str(&change.payload)?;
+                apply_void(&mut tx, &void).await?;
+                applied.sales = true;
+            }
+        }
+        applied.count += 1;
+    }
+
+    tx.commit().await?;
+
+    Ok(applied)
+}
+
+/// Local product matching the product of a peer: the same one, one already
+/// matched, or one with the same name created separately.
+async fn resolve_product(
+    conn: &mut SqliteConnection,
+    product_id: Uuid,
+    name: &str,
+) -> CommandResult<Option<Uuid>> {
+    let exists: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM products WHERE id = ?")
+        .bind(product_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if exists > 0 {
+        return Ok(Some(product_id));
+    }
+
+    let alias: Option<Uuid> =
+        sqlx::query_scalar("SELECT local_id FROM sync_product_aliases WHERE remote_id = ?")
+            .bind(product_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+    if alias.is_some() {
+        return Ok(alias);
+    }
+
+    let same_name: Option<Uuid> = sqlx::query_scalar("SELECT id FROM products WHERE name = ?")
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await?;
+    if let Some(local_id) = same_name {
+        sqlx::query("INSERT INTO sync_product_aliases (remote_id, local_id) VALUES (?, ?)")
+            .bind(product_id)
+            .bind(local_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(same_name)
+}
+
+/// Last writer wins: the edit with the latest time is kept, and an edit made
+/// without knowing the current version is recorded as a conflict. Applied
+/// edits are audited as made by the terminal they come from.
+async fn apply_product_change(
+    conn: &mut SqliteConnection,
+    change: &SyncChange,
+) -> CommandResult<()> {
+    let product: ProductSnapshot = serde_json::from_str(&change.payload)?;
+    let actor = Actor::sync(&change.origin);
+
+    let Some(local_id) = resolve_product(conn, product.id, &product.name).await? else {
+        sqlx::query(
+            r#"
+            INSERT INTO products (id, name, category, price, is_deleted, ticket_copies)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(product.id)
+        .bind(&product.name)
+        .bind(&product.category)
+        .bind(product.price)
+        .bind(product.is_deleted)
+        .bind(product.ticket_copies)
+        .execute(&mut *conn)
+        .await?;
+
+        audit_product_change(conn, &actor, product.id, None).await?;
+        return save_product_version(conn, product.id, change).await;
+    };
+
+    let current = product_version(conn, local_id).await?;
+    let concurrent = match &current {
+        Some(current) => product.base_change_id != Some(current.change_id),
+        None => false,
+    };
+    let wins = match &current {
+        Some(current) if concurrent => {
+            (change.changed_at, &change.origin) > (current.changed_at, &current.origin)
+        }
+        _ => true,
+    };
+
+    if wins {
+        let before = load_product(conn, local_id).await?;
+        // A rename to the name of another product would break the unique
+        // names, the product keeps its name then
+        sqlx::query(
+            r#"
+            UPDATE products
+            SET name = CASE WHEN EXISTS(SELECT 1 FROM products WHERE name = ? AND id != ?) THEN name ELSE ? END,
+                category = ?, price = ?, is_deleted = ?, ticket_copies = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&product.name)
+        .bind(local_id)
+        .bind(&product.name)
+        .bind(&product.category)
+        .bind(product.price)
+        .bind(product.is_deleted)
+        .bind(product.ticket_copies)
+        .bind(local_id)
+        .execute(&mut *conn)
+        .await?;
+
+        audit_product_change(conn, &actor, local_id, Some(before)).await?;
+        save_product_version(conn, local_id, change).await?;
+    }
+
+    if let Some(current) = current.filter(|_| concurrent) {
+        let (kept, discarded) = if wins {
+            (change.id, current.change_id)
+        } else {
+            (current.change_id, change.id)
+        };
+
+        warn!(
+            "Concurrent edits of the product {}, change {} kept over {}",
+            product.name, kept, discarded
+        );
+
+        sqlx::query(
+            r#"
+            INSERT INTO sync_conflicts (id, entity, entity_id, kept_change_id, discarded_change_id, detected_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(SyncEntity::Product)
+        .bind(local_id)
+        .bind(kept)
+        .bind(discarded)
+        .bind(Utc::now().naive_utc())
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Sales never change once recorded, so they're only added. A void received
+/// before its sale is applied with it.
+async fn apply_sale_change(conn: &mut SqliteConnection, change: &SyncChange) -> CommandResult<()> {
+    let sale: SaleSnapshot = serde_json::from_str(&change.payload)?;
+
+    let exists: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sales WHERE id = ?")
+        .bind(sale.id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if exists > 0 {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO sales (id, order_number, sale_time, total_amount, discount_amount, payment_method,
+            event_name, shift_name, operator_id, operator_name, pos_name)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(sale.id)
+    .bind(sale.order_number)
+    .bind(sale.sale_time)
+    .bind(sale.total_amount)
+    .bind(sale.discount_amount)
+    .bind(&sale.payment_method)
+    .bind(&sale.event_name)
+    .bind(&sale.shift_name)
+    .bind(sale.operator_id)
+    .bind(&sale.operator_name)
+    .bind(&sale.pos_name)
+    .execute(&mut *conn)
+    .await?;
+
+    for item in &sale.items {
+        let product_id = match resolve_product(conn, item.product_id, &item.product_name).await? {
+            Some(product_id) => product_id,
+            None => {
+                // Product not received yet, the sale keeps its history until
+                // the catalog change arrives
+                sqlx::query(
+                    r#"
+                    INSERT INTO products (id, name, category, price, is_deleted)
+                    VALUES (?, ?, ?, ?, 1)
+                    "#,
+                )
+                .bind(item.product_id)
+                .bind(&item.product_name)
+                .bind(item.category.as_deref().unwrap_or_default())
+                .bind(item.price_at_sale)
+                .execute(&mut *conn)
+                .await?;
+
+                item.product_id
+            }
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO sale_items (id, sale_id, product_id, product_name, quantity, price_at_sale)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(item.id)
+        .bind(sale.id)
+        .bind(product_id)
+        .bind(&item.product_name)
+        .bind(item.quantity)
+        .bind(item.price_at_sale)
+        .execute(&mut *conn)
+        .await?;
+    }
+    journal::record_sale(conn, sale.id).await?;
+
+    let pending_void: Option<String> = sqlx::query_scalar(
+        "SELECT payload FROM sync_changes WHERE entity = ? AND entity_id = ? AND payload != ? LIMIT 1",
+    )
+    .bind(SyncEntity::Void)
+    .bind(sale.id)
+    .bind(CLEARED_PAYLOAD)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(payload) = pending_void {
+        apply_void(conn, &serde_json::from_str(&payload)?).await?;
+    }
+
+    Ok(())
+}
+
+async fn apply_void(conn: &mut SqliteConnection, void: &VoidSnapshot) -> CommandResult<()> {
+    let result = sqlx::query(
+        "UPDATE sales SET voided_at = ?, void_reason = ? WHERE id = ? AND voided_at IS NULL",
+    )
+    .bind(void.voided_at)
+    .bind(&void.void_reason)
+    .bind(void.sale_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        journal::record_void(conn, void.sale_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Pulls the changes of a peer until it has nothing new.
+async fn sync_with_peer(
+    db: &Db,
+    client: &RemoteClient,
+) -> CommandResult<(SyncBatch, AppliedChanges)> {
+    let mut applied = AppliedChanges::default();
+
+    loop {
+        let request = SyncRequest {
+            known: known_sequences(db).await?,
+        };
+        let mut batch = client.pull_changes(&request).await?;
+        let changes = std::mem::take(&mut batch.changes);
+
+        applied.add(apply_changes(db, changes).await?);
+
+        if !batch.has_more {
+            return Ok((batch, applied));
+        }
+    }
+}
+
+async fn save_peer_result(
+    db: &Db,
+    url: &str,
+    result: &CommandResult<(SyncBatch, AppliedChanges)>,
+) -> CommandResult<()> {
+    let now = Utc::now().naive_utc();
+
+    match result {
+        Ok((batch, applied)) => {
+            sqlx::query(
+                r#"
+                INSERT INTO sync_peers (url, origin, pos_name, last_attempt_at, last_success_at, last_error, changes_received)
+                VALUES (?, ?, ?, ?, ?, NULL, ?)
+                ON CONFLICT(url) DO UPDATE SET origin = excluded.origin, pos_name = excluded.pos_name,
+                    last_attempt_at = excluded.last_attempt_at, last_success_at = excluded.last_success_at,
+                    last_error = NULL, changes_received = changes_received + excluded.changes_received
+                "#,
+            )
+            .bind(url)
+            .bind(&batch.origin)
+            .bind(&batch.pos_name)
+            .bind(now)
+            .bind(now)
+            .bind(applied.count)
+            .execute(db)
+            .await?;
+        }
+        Err(err) => {
+            sqlx::query(
+                r#"
+                INSERT INTO sync_peers (url, last_attempt_at, last_error)
+                VALUES (?, ?, ?)
+                ON CONFLICT(url) DO UPDATE SET last_attempt_at = excluded.last_attempt_at,
+                    last_error = excluded.last_error
+                "#,
+            )
+            .bind(url)
+            .bind(now)
+            .bind(err.to_string())
+            .execute(db)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Synchronizes with every configured peer, a peer out of reach is tried
+/// again at the next round.
+pub(crate) async fn sync_with_peers(host: &impl Host) -> CommandResult<()> {
+    let _guard = SYNC_LOCK.lock().await;
+
+    let settings = load_terminal_settings(host)?;
+    let db = host.db()?;
+
+    let mut applied = AppliedChanges::default();
+    for url in &settings.sync_peers {
+        let token = settings.api_token.as_deref().unwrap_or_default();
+        let result = match RemoteClient::for_peer(url, token) {
+            Ok(client) => sync_with_peer(&db, &client).await,
+            Err(err) => Err(err),
+        };
+
+        match &result {
+            Ok((_, peer_applied)) => {
+                if peer_applied.count > 0 {
+                    info!("Received {} changes from {}", peer_applied.count, url);
+                }
+            }
+            Err(err) => warn!("Failed to sync with {} {:?}", url, err),
+        }
+
+        save_peer_result(&db, url, &result).await?;
+
+        if let Ok((_, peer_applied)) = result {
+            applied.add(peer_applied);
+        }
+    }
+
+    if applied.catalog {
+        live::notify_catalog_changed(host, &db).await;
+    } else if applied.sales {
+        live::notify_sales_changed(host, &db, LiveTotalsChange::Sync).await;
+    }
+
+    Ok(())
+}
+
+/// Synchronizes with the peers until the application is closed.
+pub(crate) async fn watch_peers(host: impl Host) {
+    loop {
+        match load_terminal_settings(&host) {
+            Ok(settings) if settings.mode != TerminalMode::Peer => return,
+            Ok(_) => {
+                if let Err(err) = sync_with_peers(&host).await {
+                    error!("Failed to sync with the peers {:?}", err);
+                }
+            }
+            Err(err) => error!("Failed to load the terminal settings {:?}", err),
+        }
+
+        tokio::time::sleep(SYNC_INTERVAL).await;
+    }
+}
+
+#[cfg(feature = "gui")]
+pub(crate) async fn sync_status(db: &Db) -> CommandResult<SyncStatus> {
+    let mut conn = db.acquire().await?;
+    let origin = local_origin(&mut conn).await?;
+
+    let local_changes: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM sync_changes WHERE origin = ?")
+            .bind(&origin)
+            .fetch_one(&mut *conn)
+            .await?;
+    let received_changes: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM sync_changes WHERE origin != ?")
+            .bind(&origin)
+            .fetch_one(&mut *conn)
+            .await?;
+
+    let peers = sqlx::query_as::<_, SyncPeer>(
+        r#"
+        SELECT url, origin, pos_name, last_attempt_at, last_success_at, last_error, changes_received
+        FROM sync_peers
+        ORDER BY url
+        "#,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let conflicts = sqlx::query_as::<_, SyncConflictRow>(
+        r#"
+        SELECT c.id, c.detected_at,
+            kept.origin AS kept_origin, kept.changed_at AS kept_changed_at, kept.payload AS kept_payload,
+            discarded.origin AS discarded_origin, discarded.changed_at AS discarded_changed_at,
+            discarded.payload AS discarded_payload
+        FROM sync_conflicts c
+        JOIN sync_changes kept ON kept.id = c.kept_change_id
+        JOIN sync_changes discarded ON discarded.id = c.discarded_change_id
+        ORDER BY c.detected_at DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(CONFLICTS_LIMIT)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Ok(SyncConflict {
+            id: row.id,
+            detected_at: row.detected_at,
+            kept: SyncConflictChange {
+                origin: row.kept_origin,
+                changed_at: row.kept_changed_at,
+                product: serde_json::from_str(&row.kept_payload)?,
+            },
+            discarded: SyncConflictChange {
+                origin: row.discarded_origin,
+                changed_at: row.discarded_changed_at,
+                product: serde_json::from_str(&row.discarded_payload)?,
+            },
+        })
+    })
+    .collect::<CommandResult<Vec<_>>>()?;
+
+    Ok(SyncStatus {
+        origin,
+        local_changes,
+        received_changes,
+        peers,
+        conflicts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+    use crate::{audit::AuditAction, MIGRATOR};
+
+    async fn test_db() -> Db {
+        // One connection, every connection to memory has its own database
+        let db = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        MIGRATOR.run(&db).await.unwrap();
+
+        db
+    }
+
+    async fn create_product(db: &Db, name: &str, price: f64) -> Uuid {
+        let id = Uuid::new_v4();
+        let mut tx = begin_write(db).await.unwrap();
+        sqlx::query("INSERT INTO products (id, name, category, price) VALUES (?, ?, 'Food', ?)")
+            .bind(id)
+            .bind(name)
+            .bind(price)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        record_product_change(&mut tx, id).await.unwrap();
+        tx.commit().await.unwrap();
+
+        id
+    }
+
+    fn remote_change<T: Serialize>(
+        sequence: i64,
+        entity: SyncEntity,
+        entity_id: Uuid,
+        changed_at: NaiveDateTime,
+        payload: &T,
+    ) -> SyncChange {
+        SyncChange {
+            id: Uuid::new_v4(),
+            origin: "peer".to_string(),
+            sequence,
+            entity,
+            entity_id,
+            changed_at,
+            payload: serde_json::to_string(payload).unwrap(),
+        }
+    }
+
+    fn product_snapshot(id: Uuid, name: &str, price: f64, base: Option<Uuid>) -> ProductSnapshot {
+        ProductSnapshot {
+            id,
+            name: name.to_string(),
+            category: "Food".to_string(),
+            price,
+            is_deleted: false,
+            ticket_copies: None,
+            base_change_id: base,
+        }
+    }
+
+    fn sale_snapshot(product_id: Uuid) -> SaleSnapshot {
+        SaleSnapshot {
+            id: Uuid::new_v4(),
+            order_number: Some(1),
+            sale_time: Utc::now().naive_utc(),
+            total_amount: 3.0,
+            discount_amount: 0.0,
+            payment_method: Some("Cash".to_string()),
+            event_name: None,
+            shift_name: None,
+            operator_id: None,
+            operator_name: None,
+            pos_name: Some("Cassa 2".to_string()),
+            items: vec![SaleItemSnapshot {
+                id: Uuid::new_v4(),
+                product_id,
+                product_name: "Panino".to_string(),
+                category: Some("Food".to_string()),
+                quantity: 1,
+                price_at_sale: 3.0,
+            }],
+        }
+    }
+
+    async fn price(db: &Db, id: Uuid) -> f64 {
+        sqlx::query_scalar("SELECT price FROM products WHERE id = ?")
+            .bind(id)
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    async fn conflicts(db: &Db) -> Vec<(Uuid, Uuid)> {
+        sqlx::query_as("SELECT kept_change_id, discarded_change_id FROM sync_conflicts")
+            .fetch_all(db)
+            .await
+            .unwrap()
+    }
+
+    async fn synced_audit_actions(db: &Db) -> Vec<AuditAction> {
+        sqlx::query_scalar("SELECT action FROM audit_log WHERE actor = 'sync peer' ORDER BY id")
+            .fetch_all(db)
+            .await
+            .unwrap()
+    }
+
+    async fn current_change(db: &Db, id: Uuid) -> Uuid {
+        let mut conn = db.acquire().await.unwrap();
+        product_version(&mut conn, id)
+            .await
+            .unwrap()
+            .unwrap()
+            .change_id
+    }
+
+    #[tokio::test]
+    async fn edit_based_on_the_current_version_is_applied() {
+        let db = test_db().await;
+        let id = create_product(&db, "Panino", 3.0).await;
+        let base = current_change(&db, id).await;
+
+        let change = remote_change(
+            1,
+            SyncEntity::Product,
+            id,
+            Utc::now().naive_utc(),
+            &product_snapshot(id, "Panino", 4.0, Some(base)),
+        );
+        apply_changes(&db, vec![change.clone()]).await.unwrap();
+
+        assert_eq!(price(&db, id).await, 4.0);
+        assert_eq!(current_change(&db, id).await, change.id);
+        assert!(conflicts(&db).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn later_concurrent_edit_wins_and_is_recorded_as_a_conflict() {
+        let db = test_db().await;
+        let id = create_product(&db, "Panino", 3.0).await;
+        let local = current_change(&db, id).await;
+
+        let change = remote_change(
+            1,
+            SyncEntity::Product,
+            id,
+            Utc::now().naive_utc() + TimeDelta::minutes(1),
+            &product_snapshot(id, "Panino", 4.0, None),
+        );
+        apply_changes(&db, vec![change.clone()]).await.unwrap();
+
+        assert_eq!(price(&db, id).await, 4.0);
+        assert_eq!(conflicts(&db).await, vec![(change.id, local)]);
+        assert_eq!(
+            synced_audit_actions(&db).await,
+            vec![AuditAction::PriceChanged]
+        );
+    }
+
+    #[tokio::test]
+    async fn earlier_concurrent_edit_loses_and_is_recorded_as_a_conflict() {
+        let db = test_db().await;
+        let id = create_product(&db, "Panino", 3.0).await;
+        let local = current_change(&db, id).await;
+
+        let change = remote_change(
+            1,
+            SyncEntity::Product,
+            id,
+            Utc::now().naive_utc() - TimeDelta::minutes(1),
+            &product_snapshot(id, "Panino", 4.0, None),
+        );
+        apply_changes(&db, vec![change.clone()]).await.unwrap();
+
+        assert_eq!(price(&db, id).await, 3.0);
+        assert_eq!(current_change(&db, id).await, local);
+        assert_eq!(conflicts(&db).await, vec![(local, change.id)]);
+        assert!(synced_audit_actions(&db).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn product_with_the_same_name_is_merged_into_the_local_one() {
+        let db = test_db().await;
+        let id = create_product(&db, "Panino", 3.0).await;
+        let remote_id = Uuid::new_v4();
+
+        let change = remote_change(
+            1,
+            SyncEntity::Product,
+            remote_id,
+            Utc::now().naive_utc() + TimeDelta::minutes(1),
+            &product_snapshot(remote_id, "Panino", 4.0, None),
+        );
+        apply_changes(&db, vec![change]).await.unwrap();
+
+        let products: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM products")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(products, 1);
+        assert_eq!(price(&db, id).await, 4.0);
+
+        let mut conn = db.acquire().await.unwrap();
+        let resolved = resolve_product(&mut conn, remote_id, "Panino")
+            .await
+            .unwrap();
+        assert_eq!(resolved, Some(id));
+    }
+
+    #[tokio::test]
+    async fn void_received_before_its_sale_is_applied_with_it() {
+        let db = test_db().await;
+        let product_id = create_product(&db, "Panino", 3.0).await;
+        let sale = sale_snapshot(product_id);
+        let now = Utc::now().naive_utc();
+        let void = VoidSnapshot {
+            sale_id: sale.id,
+            voided_at: now,
+            void_reason: Some("Errore".to_string()),
+        };
+
+        apply_changes(
+            &db,
+            vec![remote_change(2, SyncEntity::Void, sale.id, now, &void)],
+        )
+        .await
+        .unwrap();
+        apply_changes(
+            &db,
+            vec![remote_change(1, SyncEntity::Sale, sale.id, now, &sale)],
+        )
+        .await
+        .unwrap();
+
+        let void_reason: Option<String> =
+            sqlx::query_scalar("SELECT void_reason FROM sales WHERE id = ?")
+                .bind(sale.id)
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        assert_eq!(void_reason.as_deref(), Some("Errore"));
+    }
+
+    #[cfg(feature = "gui")]
+    #[tokio::test]
+    async fn cleared_sales_are_not_sent_again() {
+        let db = test_db().await;
+        let product_id = create_product(&db, "Panino", 3.0).await;
+        let sale = sale_snapshot(product_id);
+        let now = Utc::now().naive_utc();
+        apply_changes(
+            &db,
+            vec![remote_change(1, SyncEntity::Sale, sale.id, now, &sale)],
+        )
+        .await
+        .unwrap();
+
+        let mut tx = begin_write(&db).await.unwrap();
+        sqlx::query("DELETE FROM sale_items")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM sales")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        clear_sale_changes(&mut tx).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let batch = changes_since(&db, &HashMap::new(), None).await.unwrap();
+        let sale_change = batch
+            .changes
+            .iter()
+            .find(|change| change.entity == SyncEntity::Sale)
+            .unwrap();
+        assert_eq!(sale_change.payload, CLEARED_PAYLOAD);
+
+        let other = test_db().await;
+        apply_changes(&other, batch.changes).await.unwrap();
+        let sales: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sales")
+            .fetch_one(&other)
+            .await
+            .unwrap();
+        assert_eq!(sales, 0);
+        assert_eq!(known_sequences(&other).await.unwrap().get("peer"), Some(&1));
+    }
+}
@@ -0,0 +1,383 @@
+use std::time::Duration;
+
+#[cfg(feature = "gui")]
+use futures_util::StreamExt;
+#[cfg(feature = "gui")]
+use log::{error, info, warn};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+#[cfg(feature = "gui")]
+use tokio_tungstenite::tungstenite::{
+    client::IntoClientRequest, http::header::AUTHORIZATION, Message,
+};
+#[cfg(feature = "gui")]
+use uuid::Uuid;
+
+#[cfg(feature = "gui")]
+use crate::{
+    host::Host,
+    load_terminal_settings,
+    operators::{OperatorUpdate, UnsavedOperator},
+    Product, RecordedSale, Sale, SaleRequest, UnsavedProduct,
+};
+use crate::{
+    operators::Operator,
+    sync::{SyncBatch, SyncRequest},
+    CommandError, CommandResult,
+};
+
+/// Wait before connecting again to the server events after a failure.
+#[cfg(feature = "gui")]
+const EVENTS_RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Header naming the client terminal of a request.
+pub(crate) const POS_NAME_HEADER: &str = "x-pos-name";
+
+/// Header with the token of the session of the operator logged in on the
+/// client terminal.
+pub(crate) const OPERATOR_SESSION_HEADER: &str = "x-operator-session";
+
+/// Role of this POS when several cash desks share one database, stored in
+/// the "terminal-settings" key.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct TerminalSettings {
+    /// Name of the cash desk, saved with every sale
+    pub pos_name: Option<String>,
+    #[serde(default)]
+    pub mode: TerminalMode,
+    /// Port the server listens on
+    #[serde(default = "default_server_port")]
+    pub server_port: u16,
+    /// Address of the server for the clients, e.g. "http://192.168.1.10:7878"
+    pub server_url: Option<String>,
+    /// Secret shared by the server and its clients
+    pub api_token: Option<String>,
+    /// Addresses of the other terminals to sync with, in peer mode
+    #[serde(default)]
+    pub sync_peers: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum TerminalMode {
+    /// Own database, no network
+    #[default]
+    Standalone,
+    /// Own database, shared with the clients over the LAN
+    Server,
+    /// Sales and catalog on the server
+    Client,
+    /// Own database, synced with the other terminals whenever they're reachable
+    Peer,
+}
+
+fn default_server_port() -> u16 {
+    7878
+}
+
+impl Default for TerminalSettings {
+    fn default() -> Self {
+        Self {
+            pos_name: None,
+            mode: TerminalMode::default(),
+            server_port: default_server_port(),
+            server_url: None,
+            api_token: None,
+            sync_peers: Vec::new(),
+        }
+    }
+}
+
+/// Event forwarded by the server to its clients, re-emitted as is to their
+/// frontend.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ServerEvent {
+    pub event: String,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct ServerStatus {
+    pub version: String,
+    pub pos_name: Option<String>,
+    /// Whether the clients must log in an operator of the server
+    #[serde(default)]
+    pub operators_required: bool,
+}
+
+/// Operator logged in on a client terminal, and the token of their session
+/// on the server.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct OperatorLogin {
+    pub operator: Operator,
+    pub token: String,
+}
+
+/// HTTP client of the server API, used by the commands of client terminals.
+pub(crate) struct RemoteClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: String,
+    pos_name: Option<String>,
+    session_token: Option<String>,
+}
+
+impl RemoteClient {
+    /// Client of another terminal, which shares the same token.
+    pub(crate) fn for_peer(url: &str, token: &str) -> CommandResult<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+
+        Ok(Self {
+            http,
+            base_url: url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            pos_name: None,
+            session_token: None,
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api{}", self.base_url, path)
+    }
+
+    /// Sends the request, turning the errors returned by the server back into
+    /// the `CommandError` it raised.
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> CommandResult<T> {
+        let mut request = request.bearer_auth(&self.token);
+        if let Some(pos_name) = &self.pos_name {
+            // Names aren't always ASCII, the server reads them back as UTF-8
+            request = request.header(
+                POS_NAME_HEADER,
+                HeaderValue::from_bytes(pos_name.as_bytes())
+                    .map_err(|err| CommandError::Remote(err.to_string()))?,
+            );
+        }
+        if let Some(session_token) = &self.session_token {
+            request = request.header(OPERATOR_SESSION_HEADER, session_token);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+
+        if status.is_success() {
+            return Ok(response.json::<T>().await?);
+        }
+
+        if status == StatusCode::UNAUTHORIZED {
+            return Err(CommandError::Unauthorized);
+        }
+
+        let body = response.text().await?;
+        Err(serde_json::from_str::<CommandError>(&body)
+            .unwrap_or_else(|_| CommandError::Remote(format!("{} {}", status, body))))
+    }
+
+    pub(crate) async fn pull_changes(&self, request: &SyncRequest) -> CommandResult<SyncBatch> {
+        self.send(self.http.post(self.url("/sync/changes")).json(request))
+            .await
+    }
+}
+
+#[cfg(feature = "gui")]
+impl RemoteClient {
+    /// Client of the configured server, or `None` when this terminal works
+    /// on its own database.
+    pub(crate) fn from_settings(settings: &TerminalSettings) -> CommandResult<Option<Self>> {
+        if settings.mode != TerminalMode::Client {
+            return Ok(None);
+        }
+
+        let (Some(server_url), Some(token)) = (&settings.server_url, &settings.api_token) else {
+            return Err(CommandError::Remote(
+                "Server address or token not configured".to_string(),
+            ));
+        };
+
+        let client = Self::for_peer(server_url, token)?;
+
+        Ok(Some(Self {
+            pos_name: settings.pos_name.clone(),
+            ..client
+        }))
+    }
+
+    /// Sends the token of the session of the operator logged in on this
+    /// terminal, the server checking their role.
+    pub(crate) fn with_session(self, session_token: Option<String>) -> Self {
+        Self {
+            session_token,
+            ..self
+        }
+    }
+
+    pub(crate) async fn status(&self) -> CommandResult<ServerStatus> {
+        self.send(self.http.get(self.url("/status"))).await
+    }
+
+    pub(crate) async fn list_products(&self) -> CommandResult<Vec<Product>> {
+        self.send(self.http.get(self.url("/products"))).await
+    }
+
+    pub(crate) async fn create_product(&self, product: &UnsavedProduct) -> CommandResult<()> {
+        self.send(self.http.post(self.url("/products")).json(product))
+            .await
+    }
+
+    pub(crate) async fn update_product(&self, product: &Product) -> CommandResult<()> {
+        self.send(
+            self.http
+                .put(self.url(&format!("/products/{}", product.id)))
+                .json(product),
+        )
+        .await
+    }
+
+    pub(crate) async fn delete_product(&self, product_id: Uuid) -> CommandResult<()> {
+        self.send(
+            self.http
+                .delete(self.url(&format!("/products/{}", product_id))),
+        )
+        .await
+    }
+
+    pub(crate) async fn process_sale(&self, request: &SaleRequest) -> CommandResult<RecordedSale> {
+        self.send(self.http.post(self.url("/sales")).json(request))
+            .await
+    }
+
+    pub(crate) async fn get_sale(&self, sale_id: Uuid) -> CommandResult<RecordedSale> {
+        self.send(self.http.get(self.url(&format!("/sales/{}", sale_id))))
+            .await
+    }
+
+    pub(crate) async fn get_last_sale(&self) -> CommandResult<RecordedSale> {
+        self.send(self.http.get(self.url("/sales/last"))).await
+    }
+
+    pub(crate) async fn find_sale(&self, code: &str) -> CommandResult<Sale> {
+        self.send(
+            self.http
+                .post(self.url("/sales/lookup"))
+                .json(&serde_json::json!({ "code": code })),
+        )
+        .await
+    }
+
+    pub(crate) async fn today_sales(&self) -> CommandResult<Vec<Sale>> {
+        self.send(self.http.get(self.url("/sales/today"))).await
+    }
+
+    pub(crate) async fn void_sale(
+        &self,
+        sale_id: Uuid,
+        reason: Option<String>,
+    ) -> CommandResult<()> {
+        self.send(
+            self.http
+                .post(self.url(&format!("/sales/{}/void", sale_id)))
+                .json(&serde_json::json!({ "reason": reason })),
+        )
+        .await
+    }
+
+    pub(crate) async fn login(&self, pin: &str) -> CommandResult<OperatorLogin> {
+        self.send(
+            self.http
+                .post(self.url("/operators/login"))
+                .json(&serde_json::json!({ "pin": pin })),
+        )
+        .await
+    }
+
+    /// Ends the session of the operator on the server.
+    pub(crate) async fn logout(&self) -> CommandResult<()> {
+        self.send(self.http.post(self.url("/operators/logout")))
+            .await
+    }
+
+    pub(crate) async fn list_operators(&self) -> CommandResult<Vec<Operator>> {
+        self.send(self.http.get(self.url("/operators"))).await
+    }
+
+    pub(crate) async fn create_operator(
+        &self,
+        operator: &UnsavedOperator,
+    ) -> CommandResult<Operator> {
+        self.send(self.http.post(self.url("/operators")).json(operator))
+            .await
+    }
+
+    pub(crate) async fn update_operator(
+        &self,
+        operator_id: Uuid,
+        update: &OperatorUpdate,
+    ) -> CommandResult<Operator> {
+        self.send(
+            self.http
+                .put(self.url(&format!("/operators/{}", operator_id)))
+                .json(update),
+        )
+        .await
+    }
+
+    fn events_url(&self) -> String {
+        let url = self.url("/events");
+
+        match url.strip_prefix("http") {
+            Some(rest) => format!("ws{}", rest),
+            None => url,
+        }
+    }
+}
+
+/// Re-emits the events of the server, such as catalog and sales totals
+/// changes, so that the client screens stay up to date. Reconnects until the
+/// application is closed.
+#[cfg(feature = "gui")]
+pub(crate) async fn watch_server_events(host: impl Host) {
+    loop {
+        let client = load_terminal_settings(&host)
+            .and_then(|settings| RemoteClient::from_settings(&settings));
+
+        match client {
+            Ok(Some(client)) => {
+                if let Err(err) = forward_server_events(&host, &client).await {
+                    warn!("Server events connection lost {:?}", err);
+                }
+            }
+            // Not a client anymore
+            Ok(None) => return,
+            Err(err) => error!("Failed to connect to the server events {:?}", err),
+        }
+
+        tokio::time::sleep(EVENTS_RECONNECT_INTERVAL).await;
+    }
+}
+
+#[cfg(feature = "gui")]
+async fn forward_server_events(host: &impl Host, client: &RemoteClient) -> CommandResult<()> {
+    let mut request = client.events_url().into_client_request()?;
+    let authorization = HeaderValue::from_str(&format!("Bearer {}", client.token))
+        .map_err(|err| CommandError::Remote(err.to_string()))?;
+    request.headers_mut().insert(AUTHORIZATION, authorization);
+
+    let (mut stream, _) = tokio_tungstenite::connect_async(request).await?;
+    info!("Connected to the server events");
+
+    while let Some(message) = stream.next().await {
+        match message? {
+            Message::Text(text) => match serde_json::from_str::<ServerEvent>(&text) {
+                Ok(event) => host.emit_event(&event.event, &event.payload),
+                Err(err) => error!("Invalid server event {:?}", err),
+            },
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
@@ -2,7 +2,8 @@ export interface Product {
   id: number,
   name: string,
   price: number,
-  category: string
+  category: string,
+  ticket_copies: number | null
 }
 export type UnsavedProduct = Omit<Product, 'id'>
 
@@ -17,3 +18,336 @@ export interface AppMessage {
   type: string,
   message: string
 }
+
+// Sale recorded at the cash desk, whose tickets may have failed to print
+export interface ProcessedSale {
+  sale_id: string,
+  print_error: AppMessage | null
+}
+
+export interface PrinterStatus {
+  connected: boolean,
+  online: boolean,
+  cover_open: boolean,
+  paper_near_end: boolean,
+  paper_out: boolean,
+  error: boolean
+}
+
+export interface EventSettings {
+  name: string | null,
+  shift: string | null,
+  business_day_cutoff_hour: number,
+  logo_path: string | null
+}
+
+export interface ReportFilter {
+  from_day?: string | null,
+  to_day?: string | null,
+  event?: string | null,
+  shift?: string | null,
+  operator?: string | null,
+  category?: string | null,
+  payment_method?: 'Cash' | 'Card' | null,
+  pos_name?: string | null
+}
+
+export interface ReportFilterOptions {
+  events: string[],
+  shifts: string[],
+  operators: string[],
+  categories: string[],
+  pos_names: string[]
+}
+
+export interface BreakdownSeries {
+  name: string,
+  category: string,
+  quantity: number[],
+  value: number[]
+}
+
+export interface SalesBreakdown {
+  interval: 'Hour' | 'HourOfDay' | 'Day',
+  buckets: string[],
+  sales_count: number[],
+  items_sold: number[],
+  total_value: number[],
+  by_category: BreakdownSeries[],
+  by_product: BreakdownSeries[]
+}
+
+export interface SalesSummary {
+  from: string | null,
+  to: string | null,
+  sales_count: number,
+  items_sold: number,
+  gross_total: number,
+  discounts_total: number,
+  net_total: number,
+  average_ticket: number,
+  items_per_sale: number,
+  voids_count: number,
+  voids_total: number,
+  by_category: { category: string, quantity: number, total: number }[],
+  by_payment_method: { payment_method: string, sales_count: number, total: number }[],
+  products: { product_id: string, product_name: string, total_quantity_sold: number, total_value_sold: number }[]
+}
+
+export interface ExportSettings {
+  directory: string | null,
+  file_name_template: string,
+  csv_decimal_comma: boolean
+}
+
+export type ExportFormat = 'Xlsx' | 'Csv' | 'Json'
+
+export interface BackupSettings {
+  directory: string | null,
+  interval_minutes: number,
+  keep: number,
+  on_close: boolean
+}
+
+export type BackupReason = 'Scheduled' | 'Manual' | 'Close' | 'Migration' | 'Restore' | 'Merge'
+
+export interface BackupFile {
+  path: string,
+  file_name: string,
+  reason: BackupReason | null,
+  size: number,
+  created_at: string
+}
+
+export interface MergedProduct {
+  id: string,
+  name: string,
+  category: string,
+  price: number,
+  is_deleted: boolean,
+  ticket_copies: number | null
+}
+
+export interface MergedSale {
+  id: string,
+  order_number: number | null,
+  sale_time: string,
+  total_amount: number,
+  discount_amount: number,
+  payment_method: string | null,
+  event_name: string | null,
+  shift_name: string | null,
+  operator_id: string | null,
+  operator_name: string | null,
+  pos_name: string | null,
+  voided_at: string | null,
+  void_reason: string | null
+}
+
+export type MergeConflict =
+  | { type: 'Product', local: MergedProduct, other: MergedProduct }
+  | { type: 'Sale', local: MergedSale, other: MergedSale }
+
+export interface MergeReport {
+  products_added: number,
+  products_matched_by_name: number,
+  sales_added: number,
+  sales_already_present: number,
+  items_added: number,
+  voids_applied: number,
+  conflicts: MergeConflict[]
+}
+
+export type TerminalMode = 'Standalone' | 'Server' | 'Client' | 'Peer'
+
+export interface TerminalSettings {
+  pos_name: string | null,
+  mode: TerminalMode,
+  server_port: number,
+  server_url: string | null,
+  api_token: string | null,
+  sync_peers: string[]
+}
+
+export interface ServerStatus {
+  version: string,
+  pos_name: string | null,
+  operators_required: boolean
+}
+
+export interface SyncPeer {
+  url: string,
+  origin: string | null,
+  pos_name: string | null,
+  last_attempt_at: string | null,
+  last_success_at: string | null,
+  last_error: string | null,
+  changes_received: number
+}
+
+export interface SyncConflictChange {
+  origin: string,
+  changed_at: string,
+  product: Product & { is_deleted: boolean }
+}
+
+export interface SyncConflict {
+  id: string,
+  detected_at: string,
+  kept: SyncConflictChange,
+  discarded: SyncConflictChange
+}
+
+export interface SyncStatus {
+  origin: string,
+  local_changes: number,
+  received_changes: number,
+  peers: SyncPeer[],
+  conflicts: SyncConflict[]
+}
+
+export type AuditAction =
+  'ProductCreated' | 'ProductUpdated' | 'PriceChanged' | 'ProductDeleted' |
+  'SettingsChanged' | 'SaleVoided' | 'SalesCleared' | 'DatabaseMerged' |
+  'OperatorCreated' | 'OperatorUpdated' | 'CashDrawerOpened'
+
+export interface AuditEntry {
+  id: number,
+  recorded_at: string,
+  action: AuditAction,
+  entity_id: string | null,
+  actor: string | null,
+  operator_id: string | null,
+  pos_name: string | null,
+  before: unknown,
+  after: unknown
+}
+
+export interface AuditFilter {
+  from_day?: string | null,
+  to_day?: string | null,
+  action?: AuditAction | null
+}
+
+export type JournalIssue =
+  { type: 'BrokenChain', sequence: number } |
+  { type: 'SaleModified', sequence: number, sale_id: string, order_number: number | null } |
+  { type: 'SaleMissing', sequence: number, sale_id: string } |
+  { type: 'SaleNotRecorded', sale_id: string, order_number: number | null } |
+  { type: 'VoidModified', sale_id: string, order_number: number | null } |
+  { type: 'JournalEmpty', sales: number }
+
+export interface JournalVerification {
+  records: number,
+  head_hash: string | null,
+  known_hash_found: boolean | null,
+  issues: JournalIssue[]
+}
+
+export interface StartupError {
+  message: string,
+  data_dir: string | null,
+  latest_backup: BackupFile | null
+}
+
+export interface ComparisonPeriod {
+  label: string,
+  filter: ReportFilter
+}
+
+export interface SalesComparison {
+  periods: {
+    label: string,
+    from: string | null,
+    to: string | null,
+    sales_count: number,
+    items_sold: number,
+    net_total: number,
+    average_ticket: number
+  }[],
+  products: {
+    product_name: string,
+    category: string,
+    quantity: number[],
+    value: number[],
+    quantity_delta: number[],
+    value_delta: number[],
+    trend: 'Kept' | 'New' | 'Discontinued'
+  }[],
+  days: {
+    day_number: number,
+    days: (string | null)[],
+    sales_count: number[],
+    total_value: number[]
+  }[]
+}
+
+export interface LiveTotals {
+  change: 'Sale' | 'Void' | 'Catalog' | 'Clear' | 'Sync' | null,
+  business_day: string,
+  revenue: number,
+  sales_count: number,
+  items_sold: number,
+  by_category: { category: string, quantity: number, total: number }[],
+  recent_minutes: number,
+  recent_items_sold: number,
+  updated_at: string
+}
+
+export interface ProductPerformanceOptions {
+  idle_hours: number,
+  min_quantity: number
+}
+
+export interface ProductPerformanceReport {
+  reference_time: string,
+  products: {
+    product_id: string,
+    product_name: string,
+    category: string,
+    quantity: number,
+    revenue: number,
+    sales_count: number,
+    quantity_rank: number,
+    revenue_rank: number,
+    category_quantity_share: number,
+    category_revenue_share: number,
+    last_sold_at: string | null,
+    idle: boolean,
+    slow: boolean
+  }[],
+  attach_rates: {
+    base_category: string,
+    attached_category: string,
+    base_orders: number,
+    attached_orders: number,
+    rate: number
+  }[]
+}
+
+export type Role = 'Cashier' | 'Supervisor' | 'Admin'
+
+export interface Operator {
+  id: string,
+  name: string,
+  role: Role,
+  is_active: boolean
+}
+
+export interface UnsavedOperator {
+  name: string,
+  role: Role,
+  pin: string
+}
+
+export interface OperatorUpdate {
+  name: string,
+  role: Role,
+  is_active: boolean,
+  pin: string | null
+}
+
+export interface SessionStatus {
+  operator: Operator | null,
+  required: boolean
+}
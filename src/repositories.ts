@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { Product, UnsavedProduct } from "./lib";
 
 export async function listProducts(): Promise<Product[]> {
@@ -16,3 +17,8 @@ export async function updateProduct(product: Product): Promise<void> {
 export async function deleteProduct(product: Product): Promise<void> {
   return await invoke("delete_product", { product })
 }
+
+/** Called when the catalog changes on this or, for client terminals, on the server */
+export async function onCatalogChanged(callback: () => void): Promise<UnlistenFn> {
+  return await listen('catalog-changed', callback)
+}
@@ -2,10 +2,20 @@ import PosView from "./components/views/PosView.vue";
 import CheckoutView from "./components/views/pos/CheckoutView.vue";
 import PaymentView from "./components/views/pos/PaymentView.vue";
 import ReportView from "./components/views/ReportView.vue";
+import ComparisonView from "./components/views/ComparisonView.vue";
+import DashboardView from "./components/views/DashboardView.vue";
 import SettingsView from "./components/views/SettingsView.vue";
 import PrinterView from "./components/views/settings/PrinterView.vue";
 import ProductSettingsView from "./components/views/settings/ProductsView.vue";
 import LayoutView from "./components/views/settings/LayoutView.vue";
+import EventView from "./components/views/settings/EventView.vue";
+import ExportView from "./components/views/settings/ExportView.vue";
+import BackupView from "./components/views/settings/BackupView.vue";
+import MergeView from "./components/views/settings/MergeView.vue";
+import TerminalView from "./components/views/settings/TerminalView.vue";
+import SyncView from "./components/views/settings/SyncView.vue";
+import AuditView from "./components/views/settings/AuditView.vue";
+import OperatorsView from "./components/views/settings/OperatorsView.vue";
 
 export default [
   {
@@ -23,6 +33,14 @@ export default [
     path: '/report',
     component: ReportView
   },
+  {
+    path: '/report/comparison',
+    component: ComparisonView
+  },
+  {
+    path: '/dashboard',
+    component: DashboardView
+  },
   {
     path: '/settings',
     component: SettingsView,
@@ -38,6 +56,38 @@ export default [
       {
         path: 'printer',
         component: PrinterView,
+      },
+      {
+        path: 'event',
+        component: EventView,
+      },
+      {
+        path: 'export',
+        component: ExportView,
+      },
+      {
+        path: 'backup',
+        component: BackupView,
+      },
+      {
+        path: 'merge',
+        component: MergeView,
+      },
+      {
+        path: 'terminal',
+        component: TerminalView,
+      },
+      {
+        path: 'sync',
+        component: SyncView,
+      },
+      {
+        path: 'audit',
+        component: AuditView,
+      },
+      {
+        path: 'operators',
+        component: OperatorsView,
       }
     ]
   }
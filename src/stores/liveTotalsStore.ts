@@ -0,0 +1,29 @@
+import { defineStore } from "pinia";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { LiveTotals } from "../lib";
+
+export const useLiveTotalsStore = defineStore('liveTotals', {
+  state: () => ({
+    totals: null as LiveTotals | null,
+    listening: false
+  }),
+  actions: {
+    async watch() {
+      if (this.listening) {
+        return
+      }
+      this.listening = true
+
+      await listen<LiveTotals>('sales-totals-changed', (event) => {
+        this.totals = event.payload
+      })
+
+      try {
+        this.totals = await invoke<LiveTotals>('get_live_totals')
+      } catch (err) {
+        console.warn("Sales totals not available", err)
+      }
+    }
+  }
+})
@@ -1,5 +1,6 @@
 import { defineStore } from "pinia";
 import { AppMessage } from "../lib";
+import { useOperatorStore } from "./operatorStore";
 
 type Messages = {[key: number]: AppMessage}
 
@@ -40,6 +41,11 @@ export const useMessagesStore = defineStore('messages', {
         message = { type: 'Unknown', message }
       }
 
+      // The session expired, or the server restarted, the operator must log in again
+      if (message?.type === 'NotLoggedIn') {
+        useOperatorStore().logout().catch(() => {})
+      }
+
       this.addMessage(message)
     },
     removeMessage(index: number) {
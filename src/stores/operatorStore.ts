@@ -0,0 +1,43 @@
+import { defineStore } from "pinia";
+import { invoke } from "@tauri-apps/api/core";
+import { Operator, Role, SessionStatus } from "../lib";
+
+const roles: Role[] = ['Cashier', 'Supervisor', 'Admin']
+
+export const useOperatorStore = defineStore('operator', {
+  state: () => ({
+    operator: null as Operator | null,
+    required: false,
+    loaded: false
+  }),
+  getters: {
+    // Without accounts everything is allowed, as before operators existed
+    can(state) {
+      return (role: Role) => {
+        if (!state.required) {
+          return true
+        }
+
+        return state.operator !== null && roles.indexOf(state.operator.role) >= roles.indexOf(role)
+      }
+    },
+    loginNeeded(state): boolean {
+      return state.loaded && state.required && state.operator === null
+    }
+  },
+  actions: {
+    async load() {
+      const session = await invoke<SessionStatus>('get_operator_session')
+      this.operator = session.operator
+      this.required = session.required
+      this.loaded = true
+    },
+    async login(pin: string) {
+      this.operator = await invoke<Operator>('login_operator', { pin })
+    },
+    async logout() {
+      await invoke('logout_operator')
+      await this.load()
+    }
+  }
+})
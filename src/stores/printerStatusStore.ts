@@ -0,0 +1,46 @@
+import { defineStore } from "pinia";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { PrinterStatus } from "../lib";
+
+export const usePrinterStatusStore = defineStore('printerStatus', {
+  state: () => ({
+    status: null as PrinterStatus | null,
+    listening: false
+  }),
+  getters: {
+    problem(state): string | null {
+      const status = state.status
+      if (!status) {
+        return null
+      }
+
+      if (!status.connected) return 'disconnected'
+      if (status.cover_open) return 'cover-open'
+      if (status.paper_out) return 'paper-out'
+      if (status.error) return 'error'
+      if (!status.online) return 'offline'
+      if (status.paper_near_end) return 'paper-near-end'
+
+      return null
+    }
+  },
+  actions: {
+    async watch() {
+      if (this.listening) {
+        return
+      }
+      this.listening = true
+
+      await listen<PrinterStatus>('printer-status-changed', (event) => {
+        this.status = event.payload
+      })
+
+      try {
+        this.status = await invoke<PrinterStatus | null>('get_printer_status')
+      } catch (err) {
+        console.warn("Printer status not available", err)
+      }
+    }
+  }
+})